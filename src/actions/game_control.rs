@@ -1,40 +1,370 @@
-use bevy::prelude::{Input, KeyCode, MouseButton, Res};
+use std::{collections::BTreeMap, fmt, marker::PhantomData};
 
+use bevy::{ecs::system::SystemParam, prelude::*};
+use serde::{Deserialize, Serialize};
+
+use crate::storage::{self, Directory};
+
+const INPUT_MAP_FILE: &str = "controls.json";
+const DEFAULT_DEADZONE: f32 = 0.15;
+
+#[derive(Serialize, Deserialize, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug, Hash)]
 pub enum GameControl {
     Left,
     Right,
     Action,
+    /// The controls of the second paddle, in the modes that have one.
+    SecondLeft,
+    SecondRight,
+    SecondAction,
 }
 
 impl GameControl {
-    pub fn pressed(
-        &self,
-        keyboard_input: &Res<Input<KeyCode>>,
-        mouse_button_input: &Res<Input<MouseButton>>,
-    ) -> bool {
+    pub const ALL: [GameControl; 6] = [
+        GameControl::Left,
+        GameControl::Right,
+        GameControl::Action,
+        GameControl::SecondLeft,
+        GameControl::SecondRight,
+        GameControl::SecondAction,
+    ];
+
+    /// The player the control belongs to, counted from 0.
+    pub fn player(&self) -> usize {
         match self {
-            GameControl::Left => {
-                keyboard_input.pressed(KeyCode::A) || keyboard_input.pressed(KeyCode::Left)
-            }
-            GameControl::Right => {
-                keyboard_input.pressed(KeyCode::D) || keyboard_input.pressed(KeyCode::Right)
+            GameControl::Left | GameControl::Right | GameControl::Action => 0,
+            GameControl::SecondLeft | GameControl::SecondRight | GameControl::SecondAction => 1,
+        }
+    }
+
+    /// While the controls are split, the gamepads only count for the second player.
+    pub fn pressed(&self, input_map: &InputMap, devices: &InputDevices, split: bool) -> bool {
+        let mut bindings = input_map
+            .bindings(*self)
+            .iter()
+            .filter(|binding| !(split && binding.is_gamepad() && self.player() == 0));
+
+        match self {
+            GameControl::Left
+            | GameControl::Right
+            | GameControl::SecondLeft
+            | GameControl::SecondRight => bindings.any(|binding| binding.pressed(devices)),
+            GameControl::Action | GameControl::SecondAction => {
+                bindings.any(|binding| binding.just_pressed(devices))
             }
-            GameControl::Action => {
-                keyboard_input.just_pressed(KeyCode::Space)
-                    || mouse_button_input.just_pressed(MouseButton::Left)
+        }
+    }
+}
+
+impl fmt::Display for GameControl {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GameControl::Left => write!(f, "Move left"),
+            GameControl::Right => write!(f, "Move right"),
+            GameControl::Action => write!(f, "Shoot"),
+            GameControl::SecondLeft => write!(f, "P2 move left"),
+            GameControl::SecondRight => write!(f, "P2 move right"),
+            GameControl::SecondAction => write!(f, "P2 shoot"),
+        }
+    }
+}
+
+/// Every device the player can play with.
+#[derive(SystemParam)]
+pub struct InputDevices<'w, 's> {
+    pub keyboard: Res<'w, Input<KeyCode>>,
+    pub mouse_buttons: Res<'w, Input<MouseButton>>,
+    pub gamepads: Res<'w, Gamepads>,
+    pub gamepad_buttons: Res<'w, Input<GamepadButton>>,
+    pub gamepad_axes: Res<'w, Axis<GamepadAxis>>,
+    /// How far the analog buttons, like the triggers, are pressed.
+    pub gamepad_button_axes: Res<'w, Axis<GamepadButton>>,
+    #[system_param(ignore)]
+    marker: PhantomData<&'s ()>,
+}
+
+impl InputDevices<'_, '_> {
+    /// Whether `button` is pressed on any of the connected gamepads.
+    pub fn gamepad_pressed(&self, button: GamepadButtonType) -> bool {
+        self.gamepads.iter().any(|gamepad| {
+            self.gamepad_buttons
+                .pressed(GamepadButton::new(gamepad, button))
+        })
+    }
+
+    pub fn gamepad_just_pressed(&self, button: GamepadButtonType) -> bool {
+        self.gamepads.iter().any(|gamepad| {
+            self.gamepad_buttons
+                .just_pressed(GamepadButton::new(gamepad, button))
+        })
+    }
+
+    /// How far the left stick and the triggers push the paddle, from -1 (left) to 1 (right).
+    /// When several gamepads are connected, the one pushed the furthest wins.
+    pub fn gamepad_movement(&self, deadzone: f32) -> f32 {
+        self.gamepads
+            .iter()
+            .map(|gamepad| {
+                let stick = self
+                    .gamepad_axes
+                    .get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickX))
+                    .unwrap_or(0.);
+
+                let trigger = |button| {
+                    self.gamepad_button_axes
+                        .get(GamepadButton::new(gamepad, button))
+                        .unwrap_or(0.)
+                };
+
+                let triggers = trigger(GamepadButtonType::RightTrigger2)
+                    - trigger(GamepadButtonType::LeftTrigger2);
+
+                apply_deadzone(stick, deadzone) + apply_deadzone(triggers, deadzone)
+            })
+            .fold(0., |movement: f32, gamepad_movement| {
+                if gamepad_movement.abs() > movement.abs() {
+                    gamepad_movement
+                } else {
+                    movement
+                }
+            })
+            .clamp(-1., 1.)
+    }
+}
+
+/// Ignores the axis values closer to 0 than `deadzone`, and scales the rest so the movement
+/// still starts at 0 and goes up to 1.
+fn apply_deadzone(value: f32, deadzone: f32) -> f32 {
+    if value.abs() <= deadzone {
+        return 0.;
+    }
+
+    value.signum() * ((value.abs() - deadzone) / (1. - deadzone)).min(1.)
+}
+
+/// A key or a button a [`GameControl`] is bound to.
+#[derive(Serialize, Deserialize, Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    /// A button of any of the connected gamepads.
+    Gamepad(GamepadButtonType),
+}
+
+impl Binding {
+    pub fn is_gamepad(&self) -> bool {
+        matches!(self, Binding::Gamepad(_))
+    }
+
+    pub fn pressed(&self, devices: &InputDevices) -> bool {
+        match *self {
+            Binding::Key(key) => devices.keyboard.pressed(key),
+            Binding::Mouse(button) => devices.mouse_buttons.pressed(button),
+            Binding::Gamepad(button) => devices.gamepad_pressed(button),
+        }
+    }
+
+    pub fn just_pressed(&self, devices: &InputDevices) -> bool {
+        match *self {
+            Binding::Key(key) => devices.keyboard.just_pressed(key),
+            Binding::Mouse(button) => devices.mouse_buttons.just_pressed(button),
+            Binding::Gamepad(button) => devices.gamepad_just_pressed(button),
+        }
+    }
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Binding::Key(key) => write!(f, "{key:?}"),
+            Binding::Mouse(MouseButton::Other(button)) => write!(f, "Mouse {button}"),
+            Binding::Mouse(button) => write!(f, "Mouse {button:?}"),
+            Binding::Gamepad(GamepadButtonType::Other(button)) => write!(f, "Gamepad {button}"),
+            Binding::Gamepad(button) => write!(f, "Gamepad {button:?}"),
+        }
+    }
+}
+
+/// The bindings of every [`GameControl`], kept between sessions.
+#[derive(Resource, Serialize, Deserialize, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct InputMap {
+    bindings: BTreeMap<GameControl, Vec<Binding>>,
+    /// How far the sticks and the triggers have to be pushed before the paddle moves, from 0 to 1.
+    pub deadzone: f32,
+}
+
+impl Default for InputMap {
+    fn default() -> Self {
+        Self {
+            bindings: BTreeMap::from([
+                (
+                    GameControl::Left,
+                    vec![
+                        Binding::Key(KeyCode::A),
+                        Binding::Key(KeyCode::Left),
+                        Binding::Gamepad(GamepadButtonType::DPadLeft),
+                    ],
+                ),
+                (
+                    GameControl::Right,
+                    vec![
+                        Binding::Key(KeyCode::D),
+                        Binding::Key(KeyCode::Right),
+                        Binding::Gamepad(GamepadButtonType::DPadRight),
+                    ],
+                ),
+                (
+                    GameControl::Action,
+                    vec![
+                        Binding::Key(KeyCode::Space),
+                        Binding::Mouse(MouseButton::Left),
+                        Binding::Gamepad(GamepadButtonType::South),
+                    ],
+                ),
+                (
+                    GameControl::SecondLeft,
+                    vec![
+                        Binding::Key(KeyCode::J),
+                        Binding::Gamepad(GamepadButtonType::DPadLeft),
+                    ],
+                ),
+                (
+                    GameControl::SecondRight,
+                    vec![
+                        Binding::Key(KeyCode::L),
+                        Binding::Gamepad(GamepadButtonType::DPadRight),
+                    ],
+                ),
+                (
+                    GameControl::SecondAction,
+                    vec![
+                        Binding::Key(KeyCode::I),
+                        Binding::Gamepad(GamepadButtonType::South),
+                    ],
+                ),
+            ]),
+            deadzone: DEFAULT_DEADZONE,
+        }
+    }
+}
+
+impl InputMap {
+    /// Controls that were added after the bindings were saved get their default bindings.
+    pub fn load() -> Self {
+        let mut input_map: Self = storage::load(Directory::Config, INPUT_MAP_FILE);
+
+        for (control, bindings) in Self::default().bindings {
+            input_map.bindings.entry(control).or_insert(bindings);
+        }
+
+        input_map
+    }
+
+    pub fn save(&self) {
+        if let Err(error) = storage::save(Directory::Config, INPUT_MAP_FILE, self) {
+            warn!("Couldn't save the controls: {error:#}");
+        }
+    }
+
+    pub fn bindings(&self, control: GameControl) -> &[Binding] {
+        self.bindings.get(&control).map_or(&[], Vec::as_slice)
+    }
+
+    /// Adds `binding` to `control`, unless it's already there.
+    pub fn bind(&mut self, control: GameControl, binding: Binding) {
+        let bindings = self.bindings.entry(control).or_default();
+
+        if !bindings.contains(&binding) {
+            bindings.push(binding);
+        }
+    }
+
+    /// Replaces every binding of `control` with `binding`.
+    pub fn rebind(&mut self, control: GameControl, binding: Binding) {
+        self.bindings.insert(control, vec![binding]);
+    }
+
+    /// Returns every binding shared by more than one control, along with the controls. The
+    /// players may share gamepad buttons, since the gamepads belong to the second player while
+    /// there are two of them.
+    pub fn conflicts(&self) -> Vec<(Binding, Vec<GameControl>)> {
+        let mut conflicts = Vec::<(Binding, Vec<GameControl>)>::new();
+
+        for (control, bindings) in &self.bindings {
+            for binding in bindings {
+                match conflicts.iter_mut().find(|(other, _)| other == binding) {
+                    Some((_, controls)) => controls.push(*control),
+                    None => conflicts.push((*binding, vec![*control])),
+                }
             }
         }
+
+        conflicts.retain(|(binding, controls)| {
+            controls.iter().enumerate().any(|(i, control)| {
+                controls[i + 1..]
+                    .iter()
+                    .any(|other| !binding.is_gamepad() || other.player() == control.player())
+            })
+        });
+        conflicts
     }
 }
 
 pub fn get_movement(
     control: GameControl,
-    input: &Res<Input<KeyCode>>,
-    mouse_button_input: &Res<Input<MouseButton>>,
+    input_map: &InputMap,
+    devices: &InputDevices,
+    split: bool,
 ) -> f32 {
-    if control.pressed(input, mouse_button_input) {
+    if control.pressed(input_map, devices, split) {
         1.0
     } else {
         0.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shared_bindings_conflict() {
+        let mut input_map = InputMap::default();
+
+        assert!(input_map.conflicts().is_empty());
+
+        input_map.bind(GameControl::Action, Binding::Key(KeyCode::A));
+
+        assert_eq!(
+            input_map.conflicts(),
+            vec![(
+                Binding::Key(KeyCode::A),
+                vec![GameControl::Left, GameControl::Action]
+            )]
+        );
+
+        input_map.rebind(GameControl::Action, Binding::Key(KeyCode::Space));
+
+        assert!(input_map.conflicts().is_empty());
+
+        input_map.bind(GameControl::SecondLeft, Binding::Key(KeyCode::A));
+
+        assert_eq!(
+            input_map.conflicts(),
+            vec![(
+                Binding::Key(KeyCode::A),
+                vec![GameControl::Left, GameControl::SecondLeft]
+            )]
+        );
+    }
+
+    #[test]
+    fn deadzone_rescales_axis() {
+        assert_eq!(apply_deadzone(0.25, 0.5), 0.);
+        assert_eq!(apply_deadzone(-0.5, 0.5), 0.);
+        assert_eq!(apply_deadzone(0.75, 0.5), 0.5);
+        assert_eq!(apply_deadzone(-1., 0.5), -1.);
+        assert_eq!(apply_deadzone(0.5, 0.), 0.5);
+    }
+}
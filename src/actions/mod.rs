@@ -1,53 +1,233 @@
 use bevy::prelude::*;
 
-use crate::actions::game_control::{get_movement, GameControl};
+use crate::{actions::game_control::get_movement, GameState};
+
+pub use self::game_control::{Binding, GameControl, InputDevices, InputMap};
 
 mod game_control;
 
 pub struct ActionsPlugin;
 
-// This plugin listens for keyboard input and converts the input into Actions
+// This plugin listens for keyboard, mouse and gamepad input and converts the input into Actions
 // Actions can then be used as a resource in other systems to act on the player input.
 impl Plugin for ActionsPlugin {
     fn build(&self, app: &mut App) {
+        if !app.world.contains_resource::<InputMap>() {
+            app.insert_resource(InputMap::load());
+        }
+
         app.add_event::<InputEvent>()
+            .add_event::<UiNavigation>()
             .init_resource::<Actions>()
-            .add_system(set_movement_actions)
-            .add_system(call_input_events);
+            .init_resource::<SplitControls>()
+            .init_resource::<InputSource>()
+            .add_startup_system(spawn_cursor)
+            .add_system(set_movement_actions.label(ActionsSystem::Input))
+            .add_system(call_input_events.label(ActionsSystem::Input))
+            .add_system(call_navigation_events.label(ActionsSystem::Input))
+            .add_system(save_input_map)
+            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(drop_input_events))
+            .add_system_set(
+                SystemSet::on_resume(GameState::Playing).with_system(drop_input_events),
+            );
     }
 }
 
+/// Systems that fill the [`Actions`] and send the [`InputEvent`]s. Systems that act on the input
+/// run after them.
+#[derive(SystemLabel, Debug, Hash, PartialEq, Eq, Copy, Clone)]
+pub enum ActionsSystem {
+    Input,
+}
+
+/// Where the input comes from.
+#[derive(Resource, Default, Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum InputSource {
+    /// The keyboard, the mouse and the gamepads.
+    #[default]
+    Devices,
+    /// A replay that's being played back, the devices are ignored.
+    Replay,
+}
+
+/// Whether two players share the devices, each with their own controls. The gamepads belong to
+/// the second player then.
+#[derive(Resource, Default, Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct SplitControls(pub bool);
+
 #[derive(Default, Resource)]
 pub struct Actions {
     pub player_movement: Option<f32>,
+    /// The movement of the second paddle, while the controls are split.
+    pub second_player_movement: Option<f32>,
+}
+
+impl Actions {
+    /// The movement of the paddle of `player`, counted from 0.
+    pub fn movement(&self, player: usize) -> f32 {
+        match player {
+            0 => self.player_movement,
+            _ => self.second_player_movement,
+        }
+        .unwrap_or(0.)
+    }
 }
 
 #[derive(Debug, Clone, Eq, PartialEq, Hash)]
 pub enum InputEvent {
     PrimaryAction,
+    /// The primary action of the second player, while the controls are split.
+    SecondPlayerAction,
+}
+
+impl InputEvent {
+    /// The primary action of `player`, counted from 0.
+    pub fn primary_action(player: usize) -> Self {
+        match player {
+            0 => InputEvent::PrimaryAction,
+            _ => InputEvent::SecondPlayerAction,
+        }
+    }
+}
+
+/// Moves the focus between the buttons of the menus, or presses the focused one.
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum UiNavigation {
+    Up,
+    Down,
+    Left,
+    Right,
+    /// The next button in reading order.
+    Next,
+    Previous,
+    Confirm,
+}
+
+/// Marks the entity that follows the mouse cursor around the arena.
+#[derive(Component)]
+pub struct FollowCursor;
+
+fn spawn_cursor(mut commands: Commands) {
+    commands
+        .spawn(SpatialBundle::default())
+        .insert(FollowCursor)
+        .insert(Name::new("Cursor"));
 }
 
 pub fn set_movement_actions(
     mut actions: ResMut<Actions>,
-    keyboard_input: Res<Input<KeyCode>>,
-    mouse_button_input: Res<Input<MouseButton>>,
+    input_source: Res<InputSource>,
+    input_map: Res<InputMap>,
+    split_controls: Res<SplitControls>,
+    devices: InputDevices,
 ) {
-    let player_movement = get_movement(GameControl::Right, &keyboard_input, &mouse_button_input)
-        - get_movement(GameControl::Left, &keyboard_input, &mouse_button_input);
+    if *input_source != InputSource::Devices {
+        return;
+    }
+
+    let split = split_controls.0;
+    let gamepad_movement = devices.gamepad_movement(input_map.deadzone);
+
+    let movement = |left: GameControl, right: GameControl, gamepad_movement: f32| {
+        let digital_movement = get_movement(right, &input_map, &devices, split)
+            - get_movement(left, &input_map, &devices, split);
+
+        Some((digital_movement + gamepad_movement).clamp(-1., 1.))
+            .filter(|movement| *movement != 0.)
+    };
 
-    if player_movement != 0. {
-        actions.player_movement = Some(player_movement);
+    if split {
+        actions.player_movement = movement(GameControl::Left, GameControl::Right, 0.);
+        actions.second_player_movement = movement(
+            GameControl::SecondLeft,
+            GameControl::SecondRight,
+            gamepad_movement,
+        );
     } else {
-        actions.player_movement = None;
+        actions.player_movement = movement(GameControl::Left, GameControl::Right, gamepad_movement);
+        actions.second_player_movement = None;
     }
 }
 
 pub fn call_input_events(
     mut input_events: EventWriter<InputEvent>,
-    keyboard_input: Res<Input<KeyCode>>,
-    mouse_button_input: Res<Input<MouseButton>>,
+    input_source: Res<InputSource>,
+    input_map: Res<InputMap>,
+    split_controls: Res<SplitControls>,
+    devices: InputDevices,
 ) {
-    if GameControl::Action.pressed(&keyboard_input, &mouse_button_input) {
+    if *input_source != InputSource::Devices {
+        return;
+    }
+
+    let split = split_controls.0;
+
+    if GameControl::Action.pressed(&input_map, &devices, split) {
         input_events.send(InputEvent::PrimaryAction);
     }
+
+    if split && GameControl::SecondAction.pressed(&input_map, &devices, split) {
+        input_events.send(InputEvent::SecondPlayerAction);
+    }
+}
+
+/// The arrow keys and the d-pad move through the menus, Tab and Shift+Tab go through the buttons
+/// in order, and Enter, Space or the south face button press the focused button.
+fn call_navigation_events(mut navigation_events: EventWriter<UiNavigation>, devices: InputDevices) {
+    let keys = [
+        (KeyCode::Up, UiNavigation::Up),
+        (KeyCode::Down, UiNavigation::Down),
+        (KeyCode::Left, UiNavigation::Left),
+        (KeyCode::Right, UiNavigation::Right),
+        (KeyCode::Return, UiNavigation::Confirm),
+        (KeyCode::NumpadEnter, UiNavigation::Confirm),
+        (KeyCode::Space, UiNavigation::Confirm),
+    ];
+
+    let gamepad_buttons = [
+        (GamepadButtonType::DPadUp, UiNavigation::Up),
+        (GamepadButtonType::DPadDown, UiNavigation::Down),
+        (GamepadButtonType::DPadLeft, UiNavigation::Left),
+        (GamepadButtonType::DPadRight, UiNavigation::Right),
+        (GamepadButtonType::South, UiNavigation::Confirm),
+    ];
+
+    for (key, navigation) in keys {
+        if devices.keyboard.just_pressed(key) {
+            navigation_events.send(navigation);
+        }
+    }
+
+    if devices.keyboard.just_pressed(KeyCode::Tab) {
+        let shift = devices
+            .keyboard
+            .any_pressed([KeyCode::LShift, KeyCode::RShift]);
+
+        navigation_events.send(if shift {
+            UiNavigation::Previous
+        } else {
+            UiNavigation::Next
+        });
+    }
+
+    for (button, navigation) in gamepad_buttons {
+        if devices.gamepad_just_pressed(button) {
+            navigation_events.send(navigation);
+        }
+    }
+}
+
+/// Space and the south face button confirm the focused button, and are bound to the primary
+/// action as well. The press that started or resumed the game mustn't launch the ball.
+fn drop_input_events(mut input_events: ResMut<Events<InputEvent>>) {
+    input_events.clear();
+}
+
+fn save_input_map(input_map: Res<InputMap>) {
+    // The bindings were just loaded from the disk, there's nothing new to save.
+    if input_map.is_added() || !input_map.is_changed() {
+        return;
+    }
+
+    input_map.save();
 }
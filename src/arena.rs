@@ -0,0 +1,134 @@
+//! The playfield the game takes place in.
+//!
+//! All gameplay happens in arena coordinates, with the origin in the middle of the arena. The
+//! camera scales the arena to fit the window, so the window size never affects gameplay.
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    assets::LevelAssets,
+    level::{CurrentLevel, LevelAsset},
+    run::{GameMode, Run},
+    util::cleanup,
+    GameState,
+};
+
+/// The arena size used by levels that don't specify their own (qHD).
+pub const DEFAULT_ARENA_SIZE: Vec2 = Vec2::new(960., 540.);
+
+/// The thickness of the walls around the arena.
+const WALL_THICKNESS: f32 = 32.;
+
+pub struct ArenaPlugin;
+
+impl Plugin for ArenaPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Arena>()
+            .init_resource::<Arena>()
+            .add_system_set(
+                SystemSet::on_enter(GameState::Playing)
+                    .with_system(arena_setup.label(ArenaSystem::Setup)),
+            )
+            .add_system_set(SystemSet::on_exit(GameState::Playing).with_system(cleanup::<Wall>));
+    }
+}
+
+#[derive(SystemLabel, Debug, Hash, PartialEq, Eq, Copy, Clone)]
+pub enum ArenaSystem {
+    Setup,
+}
+
+#[derive(Resource, Reflect, Copy, Clone, PartialEq, Debug)]
+pub struct Arena {
+    pub size: Vec2,
+}
+
+impl Default for Arena {
+    fn default() -> Self {
+        Self::new(DEFAULT_ARENA_SIZE)
+    }
+}
+
+impl Arena {
+    pub fn new(size: Vec2) -> Self {
+        Self { size }
+    }
+
+    pub fn half_size(&self) -> Vec2 {
+        self.size / 2.
+    }
+
+    /// The y coordinate of the open bottom edge of the arena.
+    pub fn bottom(&self) -> f32 {
+        -self.size.y / 2.
+    }
+
+    /// The y coordinate of the top edge of the arena, which is only open in versus runs.
+    pub fn top(&self) -> f32 {
+        self.size.y / 2.
+    }
+}
+
+/// A static collider that keeps the ball inside the arena. The walls only matter while the ball is
+/// around, so they're cleaned up along with it whenever the level is left.
+#[derive(Component, Default, Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct Wall;
+
+fn arena_setup(
+    mut commands: Commands,
+    mut arena: ResMut<Arena>,
+    level_assets: Res<LevelAssets>,
+    levels: Res<Assets<LevelAsset>>,
+    current_level: Res<CurrentLevel>,
+    run: Res<Run>,
+) {
+    let level = levels.get(&level_assets.levels[current_level.0]).unwrap();
+
+    let size = level
+        .arena
+        .map(|arena| Vec2::new(arena.width, arena.height))
+        .unwrap_or(DEFAULT_ARENA_SIZE);
+
+    if arena.size != size {
+        *arena = Arena::new(size);
+    }
+
+    let half_size = arena.half_size();
+    let half_thickness = WALL_THICKNESS / 2.;
+
+    let walls = [
+        (
+            "TopWall",
+            Vec2::new(0., half_size.y + half_thickness),
+            Vec2::new(half_size.x + WALL_THICKNESS, half_thickness),
+        ),
+        (
+            "LeftWall",
+            Vec2::new(-half_size.x - half_thickness, 0.),
+            Vec2::new(half_thickness, half_size.y + WALL_THICKNESS),
+        ),
+        (
+            "RightWall",
+            Vec2::new(half_size.x + half_thickness, 0.),
+            Vec2::new(half_thickness, half_size.y + WALL_THICKNESS),
+        ),
+    ];
+
+    // The top edge is a goal in versus runs.
+    let open_top = run.mode == GameMode::Versus;
+
+    for (name, position, half_extents) in walls {
+        if open_top && name == "TopWall" {
+            continue;
+        }
+
+        commands.spawn((
+            Wall,
+            Name::new(name),
+            RigidBody::Fixed,
+            Collider::cuboid(half_extents.x, half_extents.y),
+            TransformBundle::from_transform(Transform::from_translation(position.extend(0.))),
+        ));
+    }
+}
@@ -4,10 +4,18 @@ use bevy_kira_audio::AudioSource;
 
 use crate::{level::LevelAsset, GameState};
 
-pub struct AssetPlugin;
+/// The key the level folder is registered under in [`DynamicAssets`].
+const LEVELS_KEY: &str = "levels";
+
+pub struct AssetPlugin {
+    /// The folder the levels are loaded from, relative to the assets folder.
+    pub level_folder: String,
+}
 
 impl Plugin for AssetPlugin {
     fn build(&self, app: &mut App) {
+        register_level_folder(app, &self.level_folder);
+
         app.add_loading_state(
             LoadingState::new(GameState::Loading)
                 .with_collection::<FontAssets>()
@@ -19,13 +27,73 @@ impl Plugin for AssetPlugin {
     }
 }
 
-#[derive(AssetCollection, Resource)]
+/// Loads only the assets the game logic needs.
+///
+/// The other collections are filled with placeholder handles, so the systems that spawn sprites
+/// still run when there's nothing to render them with.
+pub struct HeadlessAssetPlugin {
+    /// The folder the levels are loaded from, relative to the assets folder.
+    pub level_folder: String,
+}
+
+impl Plugin for HeadlessAssetPlugin {
+    fn build(&self, app: &mut App) {
+        register_level_folder(app, &self.level_folder);
+
+        app.insert_resource(FontAssets::default())
+            .insert_resource(AudioAssets::default())
+            .insert_resource(TextureAssets::default())
+            .add_loading_state(
+                LoadingState::new(GameState::Loading)
+                    .with_collection::<LevelAssets>()
+                    .continue_to_state(GameState::Menu),
+            );
+    }
+}
+
+fn register_level_folder(app: &mut App, level_folder: &str) {
+    app.world
+        .get_resource_or_insert_with(DynamicAssets::default)
+        .register_asset(
+            LEVELS_KEY,
+            Box::new(LevelFolder {
+                path: level_folder.to_string(),
+            }),
+        );
+}
+
+/// Loads every level in a folder, so the level source can be picked at runtime.
+#[derive(Debug)]
+struct LevelFolder {
+    path: String,
+}
+
+impl DynamicAsset for LevelFolder {
+    fn load(&self, asset_server: &AssetServer) -> Vec<HandleUntyped> {
+        asset_server
+            .load_folder(&self.path)
+            .unwrap_or_else(|error| {
+                error!("Couldn't load the levels from {}: {error}", self.path);
+                Vec::new()
+            })
+    }
+
+    fn build(&self, world: &mut World) -> Result<DynamicAssetType, anyhow::Error> {
+        let asset_server = world.resource::<AssetServer>();
+
+        Ok(DynamicAssetType::Collection(
+            asset_server.load_folder(&self.path)?,
+        ))
+    }
+}
+
+#[derive(AssetCollection, Resource, Default)]
 pub struct FontAssets {
     #[asset(path = "fonts/Unbounded-Medium.ttf")]
     pub title_font: Handle<Font>,
 }
 
-#[derive(AssetCollection, Resource)]
+#[derive(AssetCollection, Resource, Default)]
 pub struct AudioAssets {
     #[asset(path = "audio/bounce.wav")]
     pub bounce: Handle<AudioSource>,
@@ -41,7 +109,7 @@ pub struct AudioAssets {
     pub lose_live: Handle<AudioSource>,
 }
 
-#[derive(AssetCollection, Resource)]
+#[derive(AssetCollection, Resource, Default)]
 pub struct TextureAssets {
     #[asset(path = "img/paddle.png")]
     pub paddle: Handle<Image>,
@@ -69,6 +137,6 @@ pub struct TextureAssets {
 
 #[derive(AssetCollection, Resource)]
 pub struct LevelAssets {
-    #[asset(path = "levels", collection(typed))]
+    #[asset(key = "levels", collection(typed))]
     pub levels: Vec<Handle<LevelAsset>>,
 }
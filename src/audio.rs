@@ -0,0 +1,71 @@
+//! Plays the sound effects requested by the rest of the game.
+//!
+//! Gameplay code only sends [`SoundEvent`]s, so it keeps working when this plugin isn't added,
+//! for example in a headless simulation.
+
+use bevy::prelude::*;
+use bevy_kira_audio::prelude::*;
+
+use crate::{assets::AudioAssets, settings::Settings};
+
+pub struct GameAudioPlugin;
+
+impl Plugin for GameAudioPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugin(AudioPlugin)
+            .add_system(play_sounds)
+            .add_system(apply_volume);
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum Sound {
+    Bounce,
+    BlockBreak,
+    BlockBounce,
+    Win,
+    Lose,
+    LoseLive,
+}
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct SoundEvent(pub Sound);
+
+impl Sound {
+    fn source(&self, audio_assets: &AudioAssets) -> Handle<AudioSource> {
+        match self {
+            Sound::Bounce => audio_assets.bounce.clone(),
+            Sound::BlockBreak => audio_assets.block_break.clone(),
+            Sound::BlockBounce => audio_assets.block_bounce.clone(),
+            Sound::Win => audio_assets.win.clone(),
+            Sound::Lose => audio_assets.lose.clone(),
+            Sound::LoseLive => audio_assets.lose_live.clone(),
+        }
+    }
+}
+
+fn play_sounds(
+    mut sound_events: EventReader<SoundEvent>,
+    audio: Res<Audio>,
+    audio_assets: Option<Res<AudioAssets>>,
+) {
+    // Sounds can't be played before the assets are loaded.
+    let audio_assets = match audio_assets {
+        Some(audio_assets) => audio_assets,
+        None => return,
+    };
+
+    for SoundEvent(sound) in sound_events.iter() {
+        audio.play(sound.source(&audio_assets));
+    }
+}
+
+fn apply_volume(settings: Res<Settings>, audio: Res<Audio>) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    let volume = settings.audio;
+
+    audio.set_volume((volume.master * volume.sfx) as f64);
+}
@@ -1,29 +1,44 @@
 use bevy::prelude::*;
-use bevy_kira_audio::{Audio, AudioControl};
 use bevy_rapier2d::prelude::*;
 
 use crate::{
     actions::InputEvent,
-    assets::{AudioAssets, TextureAssets},
+    arena::Arena,
+    assets::TextureAssets,
+    audio::{Sound, SoundEvent},
     block::Block,
-    paddle::{Paddle, PaddleSystem},
+    difficulty::Difficulty,
+    paddle::{Edge, Paddle, PaddleSystem, Player},
+    physics::PhysicsBackend,
+    replay::ReplaySystem,
     util::cleanup,
     GameState,
 };
 
 pub const DEFAULT_BALL_SPEED: f32 = 300.0;
+/// How fast the ball can get, compared to the speed it started with.
+pub const MAX_BALL_SPEED_SCALE: f32 = 1.5;
+/// The size of `img/ball.png`. Colliders are sized from this instead of the loaded image, so the
+/// game logic doesn't depend on the renderer.
+pub const BALL_TEXTURE_SIZE: Vec2 = Vec2::new(50., 50.);
 
 pub struct BallPlugin;
 
 impl Plugin for BallPlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<BlockHitEvent>()
+            .add_event::<PaddleHitEvent>()
             .add_event::<BallResetEvent>()
-            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(ball_setup))
+            .init_resource::<Difficulty>()
+            .add_system_set(
+                SystemSet::on_enter(GameState::Playing)
+                    .with_system(ball_setup.after(ReplaySystem::Start)),
+            )
             .add_system_set(
                 SystemSet::on_update(GameState::Playing)
                     .with_system(ball_movement)
                     .after(PaddleSystem::Movement)
+                    .with_system(speed_up_ball.after(ball_movement))
                     .with_system(ball_control)
                     .with_system(ball_reset)
                     .after(PaddleSystem::LoseLives),
@@ -48,6 +63,8 @@ pub struct Ball {
     pub direction: Vec2,
     pub speed: f32,
     pub state: BallState,
+    /// The player whose paddle the ball is glued to or last bounced off, counted from 0.
+    pub player: usize,
 }
 
 impl Default for Ball {
@@ -56,12 +73,27 @@ impl Default for Ball {
             direction: Vec2::new(0., 1.),
             speed: DEFAULT_BALL_SPEED,
             state: BallState::Glued { percentage: 0.5 },
+            player: 0,
         }
     }
 }
 
+impl Ball {
+    /// A ball glued to the paddle, as fast as `difficulty` makes it.
+    pub fn new(difficulty: Difficulty) -> Self {
+        Self {
+            speed: Self::starting_speed(difficulty),
+            ..default()
+        }
+    }
+
+    pub fn starting_speed(difficulty: Difficulty) -> f32 {
+        DEFAULT_BALL_SPEED * difficulty.ball_speed_scale()
+    }
+}
+
 #[derive(Bundle)]
-struct BallBundle {
+pub(crate) struct BallBundle {
     ball: Ball,
     collider: Collider,
     #[bundle]
@@ -78,61 +110,92 @@ impl Default for BallBundle {
     }
 }
 
+impl BallBundle {
+    pub(crate) fn new(ball: Ball, transform: Transform, texture: Handle<Image>) -> Self {
+        Self {
+            ball,
+            collider: Collider::ball(BALL_TEXTURE_SIZE.x / 2.),
+            sprite: SpriteBundle {
+                transform,
+                texture,
+                ..default()
+            },
+        }
+    }
+}
+
+/// Sent when a ball hits a block, with the player the ball last bounced off.
 #[derive(Copy, Clone, PartialEq, Debug)]
-pub struct BlockHitEvent(pub Entity);
+pub struct BlockHitEvent(pub Entity, pub usize);
 
+/// Sent when a ball bounces off a paddle, with the paddle and the ball.
 #[derive(Copy, Clone, PartialEq, Debug)]
-pub struct BallResetEvent;
+pub struct PaddleHitEvent(pub Entity, pub Entity);
+
+/// Replaces the balls with a new one, glued to the paddle of the player, counted from 0.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct BallResetEvent(pub usize);
 
 fn ball_setup(
     mut commands: Commands,
     texture_assets: Res<TextureAssets>,
-    images: Res<Assets<Image>>,
+    difficulty: Res<Difficulty>,
 ) {
-    let image = images
-        .get(&texture_assets.ball)
-        .expect("Ball texture not loaded yet!");
-
-    let ball_size = image.size();
-
-    commands.spawn(BallBundle {
-        collider: Collider::ball(ball_size.x / 2.),
-        sprite: SpriteBundle {
-            transform: Transform::from_xyz(0.0, 999.0, 1.0).with_scale(Vec3::splat(0.5)),
-            texture: texture_assets.ball.clone(),
-            ..default()
-        },
-        ..default()
-    });
+    commands.spawn(BallBundle::new(
+        Ball::new(*difficulty),
+        Transform::from_xyz(0.0, 999.0, 1.0).with_scale(Vec3::splat(0.5)),
+        texture_assets.ball.clone(),
+    ));
 }
 
 #[allow(clippy::too_many_arguments)]
 fn ball_movement(
-    mut ball_query: Query<(&mut Ball, &Collider, &mut Transform)>,
-    paddle_query: Query<(&Transform, &Collider), (With<Paddle>, Without<Block>, Without<Ball>)>,
+    mut ball_query: Query<(Entity, &mut Ball, &Collider, &mut Transform)>,
+    paddle_query: Query<
+        (&Transform, &Collider, &Player, &Edge),
+        (With<Paddle>, Without<Block>, Without<Ball>),
+    >,
     block_query: Query<(&Transform, &Collider), (With<Block>, Without<Paddle>, Without<Ball>)>,
     time: Res<Time>,
-    windows: Res<Windows>,
+    arena: Res<Arena>,
+    physics_backend: Res<PhysicsBackend>,
     rapier_context: Res<RapierContext>,
-    audio: Res<Audio>,
-    audio_assets: Res<AudioAssets>,
+    mut sound_events: EventWriter<SoundEvent>,
     mut hit_block_event_writer: EventWriter<BlockHitEvent>,
+    mut hit_paddle_event_writer: EventWriter<PaddleHitEvent>,
 ) {
-    let window = windows.get_primary().expect("No primary window found.");
+    let half_arena = arena.half_size();
+    // The top of the arena is a goal once a paddle guards it.
+    let open_top = paddle_query
+        .iter()
+        .any(|(_, _, _, edge)| *edge == Edge::Top);
 
-    for (mut ball, collider, mut transform) in ball_query.iter_mut() {
+    for (ball_entity, mut ball, collider, mut transform) in ball_query.iter_mut() {
         match ball.state {
             BallState::Glued { percentage } => {
-                let (paddle_transform, paddle_collider) = paddle_query.single();
+                let paddle = paddle_query
+                    .iter()
+                    .find(|(_, _, player, _)| player.0 == ball.player);
+
+                let (paddle_transform, paddle_collider, _, edge) = match paddle {
+                    Some(paddle) => paddle,
+                    None => continue,
+                };
 
                 let paddle_extents = paddle_collider.as_cuboid().unwrap().half_extents();
 
+                // The ball sits on the side of the paddle it's launched to.
                 transform.translation = paddle_transform.translation
                     + Vec3::new(
                         paddle_extents.x * 2. * (percentage - 0.5),
-                        paddle_extents.y + collider.as_ball().unwrap().radius(),
+                        edge.direction()
+                            * (paddle_extents.y + collider.as_ball().unwrap().radius()),
                         0.0,
                     );
+                ball.direction = Vec2::new(0., edge.direction());
+            }
+            BallState::Free if *physics_backend == PhysicsBackend::Rapier => {
+                // Rapier moves free balls on its own, see `physics::ball_collisions`.
             }
             BallState::Free => {
                 let move_vector = ball.direction * time.delta_seconds() * ball.speed;
@@ -140,18 +203,17 @@ fn ball_movement(
 
                 let ball_radius = collider.as_ball().unwrap().radius();
 
-                // Bounce off the top of the screen
-                if destination.y + ball_radius > window.height() / 2.0 {
+                // Bounce off the top of the arena
+                if !open_top && destination.y + ball_radius > half_arena.y {
                     ball.direction.y = -ball.direction.y;
-                    destination.y = window.height() / 2.0 - ball_radius;
+                    destination.y = half_arena.y - ball_radius;
                 }
 
-                // Bounce off the sides of the screen
-                if destination.x.abs() > window.width() / 2.0 - ball_radius {
-                    destination.x = destination.x.clamp(
-                        -window.width() / 2.0 + ball_radius,
-                        window.width() / 2.0 - ball_radius,
-                    );
+                // Bounce off the sides of the arena
+                if destination.x.abs() > half_arena.x - ball_radius {
+                    destination.x = destination
+                        .x
+                        .clamp(-half_arena.x + ball_radius, half_arena.x - ball_radius);
                     ball.direction.x = -ball.direction.x;
                 }
 
@@ -175,28 +237,33 @@ fn ball_movement(
                     let collision_point = transform.translation.truncate() + move_vector * hit.toi;
 
                     // Find the paddle's position and size
-                    let (paddle_transform, paddle_collider) = paddle_query.get(entity).unwrap();
+                    let (paddle_transform, paddle_collider, player, edge) =
+                        paddle_query.get(entity).unwrap();
                     let paddle_center = paddle_transform.translation.truncate();
                     let paddle_extents = paddle_collider.as_cuboid().unwrap().half_extents();
+                    let up = edge.direction();
 
-                    // Make sure the ball is above the paddle
-                    if collision_point.y >= paddle_center.y + paddle_extents.y {
+                    // Make sure the ball is on the front of the paddle, above it unless it's
+                    // flipped
+                    if (collision_point.y - paddle_center.y) * up >= paddle_extents.y {
                         // Find the percentage of the paddle that the ball hit
                         let percentage = (collision_point.x - paddle_center.x) / paddle_extents.x;
 
                         // Bounce the ball in the correct direction
-                        ball.direction = Vec2::new(percentage / 2., 1.0).normalize();
+                        ball.direction = Vec2::new(percentage / 2., up).normalize();
+                        ball.player = player.0;
 
                         // Move the ball to the correct position
                         destination = Vec3::new(
                             collision_point.x,
-                            paddle_center.y + paddle_extents.y + ball_radius + 1.,
+                            paddle_center.y + up * (paddle_extents.y + ball_radius + 1.),
                             0.0,
                         );
                     }
 
                     // Play the sound
-                    audio.play(audio_assets.bounce.clone());
+                    sound_events.send(SoundEvent(Sound::Bounce));
+                    hit_paddle_event_writer.send(PaddleHitEvent(entity, ball_entity));
                 }
 
                 // Bounce off the block
@@ -239,7 +306,7 @@ fn ball_movement(
                     }
 
                     // Send out the hit event
-                    hit_block_event_writer.send(BlockHitEvent(entity));
+                    hit_block_event_writer.send(BlockHitEvent(entity, ball.player));
                 }
 
                 transform.translation = destination;
@@ -248,43 +315,54 @@ fn ball_movement(
     }
 }
 
+/// Every player releases the balls glued to their own paddle.
 fn ball_control(mut ball_query: Query<&mut Ball>, mut input_events: EventReader<InputEvent>) {
-    for mut ball in ball_query.iter_mut() {
-        for input_event in input_events.iter() {
-            if *input_event == InputEvent::PrimaryAction {
+    for input_event in input_events.iter() {
+        for mut ball in ball_query.iter_mut() {
+            if *input_event == InputEvent::primary_action(ball.player) {
                 ball.state = BallState::Free;
             }
         }
     }
 }
 
+/// A ball gets a little faster every time it touches a paddle, as much as the difficulty says.
+fn speed_up_ball(
+    mut ball_query: Query<&mut Ball>,
+    mut paddle_hit_events: EventReader<PaddleHitEvent>,
+    difficulty: Res<Difficulty>,
+) {
+    let starting_speed = Ball::starting_speed(*difficulty);
+    let max_speed = starting_speed * MAX_BALL_SPEED_SCALE;
+
+    for PaddleHitEvent(_, ball_entity) in paddle_hit_events.iter() {
+        if let Ok(mut ball) = ball_query.get_mut(*ball_entity) {
+            ball.speed =
+                (ball.speed + starting_speed * difficulty.ball_speed_ramp()).min(max_speed);
+        }
+    }
+}
+
 fn ball_reset(
     mut commands: Commands,
     ball_query: Query<Entity, With<Ball>>,
     mut ball_reset_event_reader: EventReader<BallResetEvent>,
     texture_assets: Res<TextureAssets>,
-    images: Res<Assets<Image>>,
+    difficulty: Res<Difficulty>,
 ) {
-    for _ in ball_reset_event_reader.iter() {
+    for event in ball_reset_event_reader.iter() {
         for entity in ball_query.iter() {
             commands.entity(entity).despawn_recursive();
         }
 
-        let image = images
-            .get(&texture_assets.ball)
-            .expect("Ball texture not loaded yet!");
-
-        let ball_size = image.size();
-
-        commands.spawn(BallBundle {
-            collider: Collider::ball(ball_size.x / 2.),
-            sprite: SpriteBundle {
-                transform: Transform::from_xyz(0.0, 999.0, 1.0).with_scale(Vec3::splat(0.5)),
-                texture: texture_assets.ball.clone(),
-                ..default()
+        commands.spawn(BallBundle::new(
+            Ball {
+                player: event.0,
+                ..Ball::new(*difficulty)
             },
-            ..default()
-        });
+            Transform::from_xyz(0.0, 999.0, 1.0).with_scale(Vec3::splat(0.5)),
+            texture_assets.ball.clone(),
+        ));
     }
 }
 
@@ -323,4 +401,73 @@ mod tests {
 
         assert_eq!(ball.state, BallState::Free);
     }
+
+    #[test]
+    fn players_release_their_own_balls() {
+        let mut world = World::new();
+
+        world.spawn(BallBundle {
+            ball: Ball {
+                player: 1,
+                ..default()
+            },
+            ..default()
+        });
+
+        world.init_resource::<Events<InputEvent>>();
+
+        let mut update_stage = SystemStage::parallel();
+
+        update_stage.add_system(ball_control);
+
+        world.send_event(InputEvent::PrimaryAction);
+
+        update_stage.run(&mut world);
+
+        let ball = world.query::<&Ball>().single(&world);
+
+        assert_matches!(ball.state, BallState::Glued { .. });
+
+        world.send_event(InputEvent::SecondPlayerAction);
+
+        update_stage.run(&mut world);
+
+        let ball = world.query::<&Ball>().single(&world);
+
+        assert_eq!(ball.state, BallState::Free);
+    }
+
+    #[test]
+    fn paddle_hits_speed_up_only_their_ball() {
+        let mut world = World::new();
+
+        let paddle = world.spawn(PaddleBundle::default()).id();
+        let speed = Ball::starting_speed(Difficulty::Hard);
+        let hit_ball = world
+            .spawn(BallBundle {
+                ball: Ball { speed, ..default() },
+                ..default()
+            })
+            .id();
+        let other_ball = world
+            .spawn(BallBundle {
+                ball: Ball { speed, ..default() },
+                ..default()
+            })
+            .id();
+
+        world.insert_resource(Difficulty::Hard);
+        world.init_resource::<Events<PaddleHitEvent>>();
+
+        let mut update_stage = SystemStage::parallel();
+
+        update_stage.add_system(speed_up_ball);
+
+        world.send_event(PaddleHitEvent(paddle, hit_ball));
+
+        update_stage.run(&mut world);
+
+        assert!(world.get::<Ball>(hit_ball).unwrap().speed > speed);
+        assert_eq!(world.get::<Ball>(other_ball).unwrap().speed, speed);
+    }
 }
@@ -1,27 +1,56 @@
 use crate::{
-    assets::{AudioAssets, LevelAssets, TextureAssets},
-    ball::BlockHitEvent,
+    assets::{LevelAssets, TextureAssets},
+    audio::{Sound, SoundEvent},
+    ball::{BlockHitEvent, PaddleHitEvent},
+    daily::daily_level,
+    difficulty::Difficulty,
     level::{CurrentLevel, LevelAsset},
-    score::Score,
+    lives::{extra_lives_earned, Lives},
+    paddle::{pool_owner, Player},
+    replay::ReplaySystem,
+    run::{GameMode, LevelStats, Run},
+    score::{Combo, Score, ScoreEvent},
     util::cleanup,
     GameState,
 };
 use bevy::prelude::*;
-use bevy_kira_audio::{Audio, AudioControl};
 use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// The size of `img/block.png`, used to lay out the level without loading the image.
+pub const BLOCK_TEXTURE_SIZE: Vec2 = Vec2::new(500., 125.);
 
 pub struct BlockPlugin;
 
 impl Plugin for BlockPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_set(SystemSet::on_enter(GameState::Playing).with_system(load_current_level))
-            .add_system_set(SystemSet::on_update(GameState::Playing).with_system(destroy_blocks))
+        app.init_resource::<Palette>()
+            .init_resource::<ResumedBlocks>()
+            .add_system(apply_palette)
+            .add_system_set(
+                SystemSet::on_enter(GameState::Playing)
+                    .with_system(generate_daily_level.before(ReplaySystem::Start))
+                    .with_system(load_current_level.after(ReplaySystem::Start)),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(destroy_blocks.label(BlockSystem::Destroy)),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Playing).with_system(forget_resumed_blocks),
+            )
             .add_system_set(SystemSet::on_exit(GameState::GameOver).with_system(cleanup::<Block>))
             .add_system_set(SystemSet::on_exit(GameState::Win).with_system(cleanup::<Block>));
     }
 }
 
-#[derive(Reflect, Copy, Clone, PartialEq, Debug)]
+#[derive(SystemLabel, Debug, Hash, PartialEq, Eq, Copy, Clone)]
+pub enum BlockSystem {
+    /// Breaks the blocks the ball hit and sends a [`ScoreEvent`] for each of them.
+    Destroy,
+}
+
+#[derive(Reflect, Serialize, Deserialize, Copy, Clone, PartialEq, Debug)]
 pub enum BlockType {
     Orange,
     LightBlue,
@@ -33,18 +62,54 @@ pub enum BlockType {
     Gold,
 }
 
+/// The colors the blocks are drawn with.
+#[derive(Resource, Serialize, Deserialize, Default, Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum Palette {
+    #[default]
+    Standard,
+    /// Based on the Okabe-Ito palette, which stays distinguishable for most kinds of color
+    /// blindness.
+    Colorblind,
+}
+
+impl Palette {
+    pub const ALL: [Palette; 2] = [Palette::Standard, Palette::Colorblind];
+
+    pub fn color(&self, block_type: BlockType) -> Color {
+        let hex = match (self, block_type) {
+            (Palette::Standard, BlockType::Orange) => "ff870f",
+            (Palette::Standard, BlockType::LightBlue) => "0fffc3",
+            (Palette::Standard, BlockType::Green) => "219c0b",
+            (Palette::Standard, BlockType::Red) => "a8180d",
+            (Palette::Standard, BlockType::Blue) => "0a13ad",
+            (Palette::Standard, BlockType::Pink) => "c016c9",
+            (Palette::Standard, BlockType::Gold) => "d4af37",
+            (Palette::Colorblind, BlockType::Orange) => "e69f00",
+            (Palette::Colorblind, BlockType::LightBlue) => "56b4e9",
+            (Palette::Colorblind, BlockType::Green) => "009e73",
+            (Palette::Colorblind, BlockType::Red) => "d55e00",
+            (Palette::Colorblind, BlockType::Blue) => "0072b2",
+            (Palette::Colorblind, BlockType::Pink) => "cc79a7",
+            (Palette::Colorblind, BlockType::Gold) => "f0e442",
+            (_, BlockType::Silver { .. }) => "c5ced4",
+        };
+
+        Color::hex(hex).unwrap()
+    }
+}
+
+impl std::fmt::Display for Palette {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Palette::Standard => write!(f, "Standard"),
+            Palette::Colorblind => write!(f, "Colorblind"),
+        }
+    }
+}
+
 impl From<BlockType> for Color {
     fn from(val: BlockType) -> Self {
-        match val {
-            BlockType::Orange => Color::hex("ff870f").unwrap(),
-            BlockType::LightBlue => Color::hex("0fffc3").unwrap(),
-            BlockType::Green => Color::hex("219c0b").unwrap(),
-            BlockType::Red => Color::hex("a8180d").unwrap(),
-            BlockType::Blue => Color::hex("0a13ad").unwrap(),
-            BlockType::Pink => Color::hex("c016c9").unwrap(),
-            BlockType::Silver { .. } => Color::hex("c5ced4").unwrap(),
-            BlockType::Gold => Color::hex("d4af37").unwrap(),
-        }
+        Palette::Standard.color(val)
     }
 }
 
@@ -68,6 +133,18 @@ pub struct Block {
     pub block_type: BlockType,
 }
 
+/// A block of a level that was left in the middle.
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Debug)]
+pub struct SavedBlock {
+    pub block_type: BlockType,
+    pub position: Vec2,
+}
+
+/// The blocks the level starts with in place of its own layout, when a saved run is continued or
+/// a daily level is played. Restarting the level brings its own layout back.
+#[derive(Resource, Clone, Default, PartialEq, Debug)]
+pub struct ResumedBlocks(pub Option<Vec<SavedBlock>>);
+
 #[derive(Bundle)]
 pub struct BlockBundle {
     block: Block,
@@ -77,7 +154,7 @@ pub struct BlockBundle {
 }
 
 impl BlockBundle {
-    fn new(block_type: BlockType, block_size: &Vec2, texture: Handle<Image>) -> Self {
+    pub(crate) fn new(block_type: BlockType, block_size: &Vec2, texture: Handle<Image>) -> Self {
         Self {
             block: Block { block_type },
             collider: Collider::cuboid(block_size.x, block_size.y),
@@ -93,7 +170,7 @@ impl BlockBundle {
         }
     }
 
-    fn with_pos(mut self, pos: Vec2) -> Self {
+    pub(crate) fn with_pos(mut self, pos: Vec2) -> Self {
         self.sprite.transform.translation = pos.extend(0.0);
         self
     }
@@ -102,34 +179,55 @@ impl BlockBundle {
 fn load_current_level(
     mut commands: Commands,
     textures: Res<TextureAssets>,
-    images: Res<Assets<Image>>,
     level_assets: Res<LevelAssets>,
     levels: Res<Assets<LevelAsset>>,
     current_level: Res<CurrentLevel>,
+    resumed_blocks: Res<ResumedBlocks>,
 ) {
+    let block_size = BLOCK_TEXTURE_SIZE / 2.;
+
+    if let Some(blocks) = &resumed_blocks.0 {
+        for block in blocks {
+            commands.spawn(
+                BlockBundle::new(block.block_type, &block_size, textures.block.clone())
+                    .with_pos(block.position),
+            );
+        }
+
+        return;
+    }
+
     let level = levels.get(&level_assets.levels[current_level.0]).unwrap();
 
-    let level_height = level.tiles.len();
+    for block in lay_out_blocks(&level.tiles) {
+        commands.spawn(
+            BlockBundle::new(block.block_type, &block_size, textures.block.clone())
+                .with_pos(block.position),
+        );
+    }
+}
 
-    let block_image = images
-        .get(&textures.block)
-        .expect("Block texture is not loaded");
+/// Places the tiles of a level in rows, centered on the arena.
+pub fn lay_out_blocks(tiles: &[Vec<String>]) -> Vec<SavedBlock> {
+    let block_size = BLOCK_TEXTURE_SIZE / 2.;
 
-    let block_size = block_image.size() / 2.;
+    let level_height = tiles.len();
 
     let block_gap = Vec2::new(10., 10.);
 
+    let mut blocks = Vec::new();
+
     for i in 0..level_height {
         let level_physical_height =
             level_height as f32 * block_size.y + (level_height - 1) as f32 * block_gap.y;
 
-        let level_width = level.tiles[i].len();
+        let level_width = tiles[i].len();
 
         let level_physical_width =
             level_width as f32 * block_size.x + (level_width - 1) as f32 * block_gap.x;
 
         for j in 0..level_width {
-            let block_type = match level.tiles[i][j].as_str() {
+            let block_type = match tiles[i][j].as_str() {
                 "silver" => BlockType::Silver { hits_taken: 0 },
                 "gold" => BlockType::Gold,
                 "orange" => BlockType::Orange,
@@ -139,47 +237,86 @@ fn load_current_level(
                 "blue" => BlockType::Blue,
                 "pink" => BlockType::Pink,
                 "blank" => continue,
-                _ => panic!("Invalid block type: {}", level.tiles[i][j]),
+                _ => panic!("Invalid block type: {}", tiles[i][j]),
             };
 
-            commands.spawn(
-                BlockBundle::new(block_type, &block_size, textures.block.clone()).with_pos(
-                    Vec2::new(
-                        -level_physical_width / 2.
-                            + block_size.x / 2.
-                            + j as f32 * block_size.x
-                            + j as f32 * block_gap.x,
-                        level_physical_height / 2.
-                            - block_size.y / 2.
-                            - i as f32 * block_size.y
-                            - i as f32 * block_gap.y,
-                    ) / 2.,
-                ),
-            );
+            blocks.push(SavedBlock {
+                block_type,
+                position: Vec2::new(
+                    -level_physical_width / 2.
+                        + block_size.x / 2.
+                        + j as f32 * block_size.x
+                        + j as f32 * block_gap.x,
+                    level_physical_height / 2.
+                        - block_size.y / 2.
+                        - i as f32 * block_size.y
+                        - i as f32 * block_gap.y,
+                ) / 2.,
+            });
         }
     }
+
+    blocks
+}
+
+/// Daily runs play the level made from their seed in place of one from the level pack. It's
+/// handed over like the blocks of a continued run, so replays record it the same way.
+fn generate_daily_level(run: Res<Run>, mut resumed_blocks: ResMut<ResumedBlocks>) {
+    if let GameMode::Daily { seed, .. } = run.mode {
+        if resumed_blocks.0.is_none() {
+            resumed_blocks.0 = Some(lay_out_blocks(&daily_level(seed).tiles));
+        }
+    }
+}
+
+fn forget_resumed_blocks(mut resumed_blocks: ResMut<ResumedBlocks>) {
+    resumed_blocks.0 = None;
 }
 
+/// Breaking blocks between two touches of the paddle builds up a [`Combo`], which multiplies the
+/// points of every block. The points go to the player the ball came from, or to the shared score
+/// when their paddle has none.
+#[allow(clippy::too_many_arguments)]
 fn destroy_blocks(
     mut commands: Commands,
-    mut blocks: Query<&mut Block>,
-    mut paddle_points: Query<&mut Score>,
+    mut blocks: Query<(&mut Block, &Transform)>,
+    mut paddle_query: Query<(&mut Score, &mut Combo, &mut Lives, &Player)>,
     current_level: Res<CurrentLevel>,
-    audio: Res<Audio>,
-    audio_assets: Res<AudioAssets>,
+    difficulty: Res<Difficulty>,
+    mut level_stats: ResMut<LevelStats>,
+    mut sound_events: EventWriter<SoundEvent>,
+    mut score_events: EventWriter<ScoreEvent>,
+    mut paddle_hit_events: EventReader<PaddleHitEvent>,
     mut events: EventReader<BlockHitEvent>,
 ) {
-    let mut paddle_points = paddle_points.single_mut();
+    if paddle_hit_events.iter().count() > 0 {
+        for (_, mut combo, _, _) in paddle_query.iter_mut() {
+            combo.reset();
+        }
+    }
 
     for event in events.iter() {
-        if let Ok(mut block) = blocks.get_mut(event.0) {
+        let scorer = pool_owner(
+            event.1,
+            paddle_query.iter().map(|(_, _, _, player)| player.0),
+        );
+
+        let (mut paddle_points, mut combo, mut lives, _) = match paddle_query
+            .iter_mut()
+            .find(|(_, _, _, player)| player.0 == scorer)
+        {
+            Some(paddle) => paddle,
+            None => continue,
+        };
+
+        if let Ok((mut block, transform)) = blocks.get_mut(event.0) {
             let block_type = &mut block.block_type;
 
             let break_block = match block_type {
                 BlockType::Silver { hits_taken } => {
                     *hits_taken += 1;
 
-                    *hits_taken >= current_level.0 as u32 / 8 + 2
+                    *hits_taken >= difficulty.silver_hits(current_level.0)
                 }
                 BlockType::Gold => false,
                 _ => true,
@@ -188,12 +325,38 @@ fn destroy_blocks(
             if break_block {
                 commands.entity(event.0).despawn_recursive();
 
-                **paddle_points += block_type.score(current_level.0 as u32);
+                let multiplier = combo.hit();
+                let points = block_type.score(current_level.0 as u32) * multiplier;
+
+                let old_points = **paddle_points;
+                **paddle_points += points;
+                lives.gain(extra_lives_earned(old_points, **paddle_points));
+                level_stats.blocks_broken += 1;
+                level_stats.best_combo = level_stats.best_combo.max(combo.0);
 
-                audio.play(audio_assets.block_break.clone());
+                score_events.send(ScoreEvent {
+                    points,
+                    multiplier,
+                    position: transform.translation.truncate(),
+                    player: scorer,
+                });
+
+                sound_events.send(SoundEvent(Sound::BlockBreak));
             } else {
-                audio.play(audio_assets.block_bounce.clone());
+                sound_events.send(SoundEvent(Sound::BlockBounce));
             }
         }
     }
 }
+
+/// Recolors the blocks when the palette changes, and colors the ones that were just spawned.
+fn apply_palette(
+    palette: Res<Palette>,
+    mut blocks: Query<(&Block, ChangeTrackers<Block>, &mut Sprite)>,
+) {
+    for (block, block_tracker, mut sprite) in blocks.iter_mut() {
+        if palette.is_changed() || block_tracker.is_added() {
+            sprite.color = palette.color(block.block_type);
+        }
+    }
+}
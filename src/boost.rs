@@ -1,110 +1,160 @@
-use crate::{assets::TextureAssets, paddle::Paddle, GameState};
+//! Boosts that broken blocks sometimes drop, caught by the paddle.
+
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
+use rand::Rng;
+
+use crate::{
+    arena::Arena,
+    block::BlockSystem,
+    difficulty::Difficulty,
+    lives::Lives,
+    paddle::{pool_owner, Edge, Paddle, Player},
+    rng::GameRng,
+    run::LevelStats,
+    score::ScoreEvent,
+    util::cleanup,
+    GameState,
+};
+
+/// The size of a falling boost.
+pub const BOOST_SIZE: Vec2 = Vec2::new(40., 16.);
+/// How fast a boost falls, in pixels per second.
+pub const BOOST_SPEED: f32 = 150.;
 
 pub struct BoostPlugin;
 
 impl Plugin for BoostPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<ApplyBoostEvent>()
-            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(boost_setup))
-            .add_system_set(
-                SystemSet::on_update(GameState::Playing)
-                    .with_system(boost_movement)
-                    .with_system(apply_boosts),
-            );
+        app.add_system_set(
+            SystemSet::on_update(GameState::Playing)
+                .with_system(drop_boosts.after(BlockSystem::Destroy))
+                .with_system(boost_movement.after(drop_boosts)),
+        )
+        .add_system_set(SystemSet::on_exit(GameState::Playing).with_system(cleanup::<Boost>))
+        .add_system_set(SystemSet::on_exit(GameState::Win).with_system(cleanup::<Boost>));
     }
 }
 
+#[derive(Reflect, Copy, Clone, Eq, PartialEq, Debug, Hash)]
 pub enum BoostType {
+    /// An extra life.
     Life,
 }
 
-#[derive(Component)]
+impl BoostType {
+    fn color(self) -> Color {
+        match self {
+            BoostType::Life => Color::hex("e64d4d").unwrap(),
+        }
+    }
+}
+
+#[derive(Component, Copy, Clone, PartialEq, Debug)]
 pub struct Boost {
     pub kind: BoostType,
-    pub speed: f32,
+    /// The edge the boost falls towards, the one of the player who broke the block.
+    pub edge: Edge,
 }
 
 #[derive(Bundle)]
 pub struct BoostBundle {
     boost: Boost,
-    collider: Collider,
     #[bundle]
     sprite: SpriteBundle,
 }
 
 impl BoostBundle {
-    fn new(texture: Handle<Image>, boost_size: &Vec2) -> Self {
+    pub fn new(boost: Boost, transform: Transform) -> Self {
         Self {
-            boost: Boost {
-                kind: BoostType::Life,
-                speed: 150.,
-            },
+            boost,
             sprite: SpriteBundle {
-                texture,
-                transform: Transform::from_translation(Vec3::new(0., 200., 0.))
-                    .with_scale(Vec3::splat(0.5)),
+                sprite: Sprite {
+                    color: boost.kind.color(),
+                    custom_size: Some(BOOST_SIZE),
+                    ..default()
+                },
+                transform,
                 ..default()
             },
-            collider: Collider::cuboid(boost_size.x / 2., boost_size.y / 2.),
         }
     }
 }
 
-fn boost_setup(mut commands: Commands, textures: Res<TextureAssets>, images: Res<Assets<Image>>) {
-    let boost_image = images
-        .get(&textures.life_boost)
-        .expect("Boost texture is not loaded");
+/// Rolls for a boost for every broken block, with the chance the difficulty gives.
+fn drop_boosts(
+    mut commands: Commands,
+    mut score_events: EventReader<ScoreEvent>,
+    paddle_query: Query<(&Player, &Edge), With<Paddle>>,
+    mut rng: ResMut<GameRng>,
+    difficulty: Res<Difficulty>,
+) {
+    for event in score_events.iter() {
+        if !rng.gen_bool(difficulty.boost_drop_chance()) {
+            continue;
+        }
 
-    commands.spawn(BoostBundle::new(
-        textures.life_boost.clone(),
-        &boost_image.size(),
-    ));
-}
+        let edge = paddle_query
+            .iter()
+            .find(|(player, _)| player.0 == event.player)
+            .map_or(Edge::Bottom, |(_, edge)| *edge);
 
-#[derive(Copy, Clone, PartialEq, Debug)]
-pub struct ApplyBoostEvent {
-    pub boost: BoostType,
+        commands.spawn(BoostBundle::new(
+            Boost {
+                kind: BoostType::Life,
+                edge,
+            },
+            Transform::from_translation(event.position.extend(1.)),
+        ));
+    }
 }
 
 fn boost_movement(
     mut commands: Commands,
-    mut boost_query: Query<(&mut Transform, &Boost)>,
+    mut boost_query: Query<(Entity, &Boost, &mut Transform), Without<Paddle>>,
+    paddle_query: Query<(&Transform, &Collider, &Player), With<Paddle>>,
+    mut lives_query: Query<(&mut Lives, &Player)>,
+    mut level_stats: ResMut<LevelStats>,
+    arena: Res<Arena>,
     time: Res<Time>,
-    paddle_query: Query<(&Collider, &Transform), (With<Paddle>, Without<Boost>)>,
-    rapier_context: Res<RapierContext>,
-    mut apply_boost_event_writer: EventWriter<ApplyBoostEvent>,
 ) {
-    // Check for boost collision
-    let (paddle_collider, paddle_transform) = paddle_query.single();
-
-    if let Some((entity, _)) = rapier_context.cast_shape(
-        paddle_transform.translation.truncate(),
-        0.,
-        Vec2::new(0., -1.),
-        paddle_collider,
-        1.,
-        QueryFilter::default().predicate(&|entity| boost_query.get(entity).is_ok()),
-    ) {
-        apply_boost_event_writer.send(ApplyBoostEvent {
-            boost: boost_query.get(entity).unwrap().1.kind,
-        });
-        commands.entity(entity).despawn_recursive();
-    }
+    for (entity, boost, mut transform) in boost_query.iter_mut() {
+        transform.translation.y -= boost.edge.direction() * BOOST_SPEED * time.delta_seconds();
 
-    // Free fall
-    for (mut transform, boost) in boost_query.iter_mut() {
-        transform.translation.y -= time.delta_seconds() * boost.speed;
-    }
-}
+        if Edge::passed(&arena, transform.translation.truncate()).is_some() {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        for (paddle_transform, paddle_collider, player) in paddle_query.iter() {
+            let paddle_extents = paddle_collider.as_cuboid().unwrap().half_extents();
+            let distance = (transform.translation - paddle_transform.translation)
+                .truncate()
+                .abs();
+
+            if distance.x > paddle_extents.x + BOOST_SIZE.x / 2.
+                || distance.y > paddle_extents.y + BOOST_SIZE.y / 2.
+            {
+                continue;
+            }
+
+            match boost.kind {
+                BoostType::Life => {
+                    // A paddle without lives of its own shares the ones of the first paddle.
+                    let owner =
+                        pool_owner(player.0, lives_query.iter().map(|(_, player)| player.0));
 
-fn apply_boosts(apply_boost_event_reader: EventReader<ApplyBoostEvent>) {
-    for event in apply_boost_event_reader.iter() {
-        match event.boost {
-            BoostType::Life => {
-                println!("Life boost applied");
+                    for (mut lives, player) in lives_query.iter_mut() {
+                        if player.0 == owner {
+                            lives.gain(1);
+                        }
+                    }
+                }
             }
+
+            level_stats.power_ups_collected += 1;
+            commands.entity(entity).despawn_recursive();
+            break;
         }
     }
 }
@@ -1,12 +1,112 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, render::camera::ScalingMode, window::WindowResized};
+
+use crate::{
+    arena::Arena,
+    settings::{Scaling, Settings},
+};
+
+/// How far the letterbox bars reach past the arena, far enough to cover any window.
+const LETTERBOX_SIZE: f32 = 100_000.;
+const LETTERBOX_COLOR: Color = Color::BLACK;
+
 pub struct CameraPlugin;
 
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(setup);
+        app.insert_resource(ClearColor(Color::rgb(0.4, 0.4, 0.4)))
+            .add_startup_system(setup)
+            .add_system(fit_arena);
     }
 }
 
+/// One of the four bars covering the area outside of the arena.
+#[derive(Component, Copy, Clone, Eq, PartialEq, Debug, Hash)]
+enum Letterbox {
+    Top,
+    Bottom,
+    Left,
+    Right,
+}
+
 fn setup(mut commands: Commands) {
     commands.spawn(Camera2dBundle { ..default() });
+
+    for letterbox in [
+        Letterbox::Top,
+        Letterbox::Bottom,
+        Letterbox::Left,
+        Letterbox::Right,
+    ] {
+        commands.spawn((
+            letterbox,
+            Name::new("Letterbox"),
+            SpriteBundle {
+                sprite: Sprite {
+                    color: LETTERBOX_COLOR,
+                    custom_size: Some(Vec2::splat(LETTERBOX_SIZE)),
+                    ..default()
+                },
+                ..default()
+            },
+        ));
+    }
+}
+
+/// Scales the camera so that the whole arena is visible, whatever the window size, and moves the
+/// letterbox bars to the edges of the arena.
+fn fit_arena(
+    arena: Res<Arena>,
+    settings: Res<Settings>,
+    windows: Res<Windows>,
+    mut resized_events: EventReader<WindowResized>,
+    mut projection_query: Query<&mut OrthographicProjection>,
+    mut letterbox_query: Query<(&Letterbox, &mut Transform)>,
+) {
+    let resized = resized_events.iter().last().is_some();
+
+    if !arena.is_changed() && !settings.is_changed() && !resized {
+        return;
+    }
+
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return,
+    };
+
+    // The largest whole-number scale the arena fits in, which is 0 in a window smaller than the
+    // arena. Integer scaling falls back to fit scaling then, so the arena isn't cropped.
+    let integer_factor = (window.width() / arena.size.x)
+        .min(window.height() / arena.size.y)
+        .floor();
+
+    for mut projection in projection_query.iter_mut() {
+        match settings.display.scaling {
+            Scaling::Integer if integer_factor >= 1. => {
+                projection.scaling_mode = ScalingMode::WindowSize;
+                projection.scale = 1. / integer_factor;
+            }
+            Scaling::Fit | Scaling::Integer => {
+                projection.scaling_mode = ScalingMode::Auto {
+                    min_width: arena.size.x,
+                    min_height: arena.size.y,
+                };
+                projection.scale = 1.;
+            }
+        }
+    }
+
+    let half_arena = arena.half_size();
+    let half_letterbox = LETTERBOX_SIZE / 2.;
+
+    for (letterbox, mut transform) in letterbox_query.iter_mut() {
+        let position = match letterbox {
+            Letterbox::Top => Vec2::new(0., half_arena.y + half_letterbox),
+            Letterbox::Bottom => Vec2::new(0., -half_arena.y - half_letterbox),
+            Letterbox::Left => Vec2::new(-half_arena.x - half_letterbox, 0.),
+            Letterbox::Right => Vec2::new(half_arena.x + half_letterbox, 0.),
+        };
+
+        // Draw the bars above everything else in the world.
+        transform.translation = position.extend(100.);
+    }
 }
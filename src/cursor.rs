@@ -1,38 +1,63 @@
 use bevy::prelude::*;
 
+use crate::{
+    actions::{ActionsSystem, FollowCursor, InputSource},
+    arena::Arena,
+    settings::Settings,
+};
+
 pub struct CursorPlugin;
 
 impl Plugin for CursorPlugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(setup).add_system(follow_cursor);
+        app.add_system(follow_cursor.label(ActionsSystem::Input));
     }
 }
 
-#[derive(Component)]
-pub struct FollowCursor;
-
-fn setup(mut commands: Commands) {
-    commands
-        .spawn(SpatialBundle::default())
-        .insert(FollowCursor)
-        .insert(Name::new("Cursor"));
-}
-
+/// The followers move as far as the cursor did, scaled by the mouse sensitivity, and stay in the
+/// arena. They jump to the cursor the first time it moves.
 fn follow_cursor(
     mut cursor_followers: Query<&mut Transform, With<FollowCursor>>,
     mut cursor_moved: EventReader<CursorMoved>,
-    windows: Res<Windows>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    input_source: Res<InputSource>,
+    settings: Res<Settings>,
+    arena: Res<Arena>,
+    mut last_position: Local<Option<Vec2>>,
 ) {
-    let cursor_position = if let Some(event) = cursor_moved.iter().next() {
-        event.position.extend(0.0)
+    let cursor_position = if let Some(event) = cursor_moved.iter().last() {
+        event.position
     } else {
         return;
     };
 
-    let window = windows.get_primary().expect("No application window found!");
+    if *input_source != InputSource::Devices {
+        // The followers were moved by a replay, the next move starts over from the cursor.
+        *last_position = None;
+        return;
+    }
+
+    let (camera, camera_transform) = camera_query.single();
+
+    // The camera scales the arena to fit the window, so the cursor has to go through it as well.
+    let world_position = match camera.viewport_to_world(camera_transform, cursor_position) {
+        Some(ray) => ray.origin.truncate(),
+        None => return,
+    };
+
+    let movement = last_position.map(|last| world_position - last);
+    *last_position = Some(world_position);
+
+    let half_arena = arena.half_size();
 
     for mut transform in cursor_followers.iter_mut() {
-        transform.translation =
-            cursor_position - Vec3::new(window.width(), window.height(), 0.) / 2.;
+        let position = match movement {
+            Some(movement) => {
+                transform.translation.truncate() + movement * settings.controls.mouse_sensitivity
+            }
+            None => world_position,
+        };
+
+        transform.translation = position.clamp(-half_arena, half_arena).extend(0.0);
     }
 }
@@ -0,0 +1,259 @@
+//! The daily challenge, a single level made from the date.
+//!
+//! The date is turned into a seed, which lays out the level and seeds the [`GameRng`] it's played
+//! with, so everyone who plays on the same day gets the same challenge. The day changes at the
+//! player's own midnight. Every day has one attempt that goes into the history, the ones after it
+//! are practice.
+
+use bevy::prelude::*;
+use chrono::{Datelike, Local, NaiveDate};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    actions::InputSource,
+    level::LevelAsset,
+    paddle::Paddle,
+    rng::GameRng,
+    run::{GameMode, Run},
+    score::Score,
+    storage::{self, Directory},
+    GameState,
+};
+
+/// How many days the history keeps.
+pub const DAILY_HISTORY_LEN: usize = 30;
+const DAILY_FILE: &str = "daily.json";
+/// The block types a daily level is built from, besides the silver and the gold ones.
+const DAILY_COLORS: [&str; 6] = ["orange", "lightblue", "green", "red", "blue", "pink"];
+
+pub struct DailyPlugin;
+
+impl Plugin for DailyPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.world.contains_resource::<DailyHistory>() {
+            app.insert_resource(DailyHistory::load());
+        }
+
+        app.add_system_set(SystemSet::on_enter(GameState::Playing).with_system(start_attempt))
+            .add_system_set(SystemSet::on_enter(GameState::Win).with_system(finish_attempt))
+            .add_system_set(SystemSet::on_enter(GameState::GameOver).with_system(finish_attempt));
+    }
+}
+
+/// The seed of `date`. It reads as the date, `YYYYMMDD`.
+pub fn daily_seed(date: NaiveDate) -> u64 {
+    date.year() as u64 * 10_000 + u64::from(date.month()) * 100 + u64::from(date.day())
+}
+
+/// The seed of today in the player's time zone.
+pub fn todays_seed() -> u64 {
+    daily_seed(Local::now().date_naive())
+}
+
+/// The day a seed was made from, as `YYYY-MM-DD`.
+pub fn seed_date_string(seed: u64) -> String {
+    format!(
+        "{:04}-{:02}-{:02}",
+        seed / 10_000,
+        seed / 100 % 100,
+        seed % 100
+    )
+}
+
+/// The level of the day `seed` was made from. Its rows are mirrored, like the levels of the level
+/// pack are.
+pub fn daily_level(seed: u64) -> LevelAsset {
+    let mut rng = GameRng::new(seed);
+
+    let rows = rng.gen_range(3..=6);
+    // Seven blocks are as many as fit next to each other in the default arena.
+    let columns = rng.gen_range(3..=7);
+
+    let mut tiles: Vec<Vec<String>> = (0..rows)
+        .map(|_| {
+            let color = DAILY_COLORS[rng.gen_range(0..DAILY_COLORS.len())];
+
+            let half: Vec<&str> = (0..(columns + 1) / 2)
+                .map(|_| match rng.gen_range(0..20) {
+                    0..=2 => "blank",
+                    3..=4 => "silver",
+                    5 => "gold",
+                    _ => color,
+                })
+                .collect();
+
+            half.iter()
+                .chain(half.iter().rev().skip(columns % 2))
+                .map(|tile| tile.to_string())
+                .collect()
+        })
+        .collect();
+
+    // A level without a block to break would be won right away.
+    let breakable = tiles
+        .iter()
+        .flatten()
+        .any(|tile| tile != "blank" && tile != "gold");
+
+    if !breakable {
+        tiles[rows - 1] = vec![DAILY_COLORS[0].to_string(); columns];
+    }
+
+    LevelAsset {
+        name: Some(format!("Daily {}", seed_date_string(seed))),
+        tiles,
+        arena: None,
+        stars: Vec::new(),
+    }
+}
+
+/// How the scored attempt of a day went.
+#[derive(Serialize, Deserialize, Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct DailyResult {
+    /// The seed of the day, which plays the same level again.
+    pub seed: u64,
+    pub score: u32,
+    /// Attempts that were quit in the middle count as not cleared.
+    pub cleared: bool,
+}
+
+/// The scored attempts of the last days, newest first.
+#[derive(Resource, Serialize, Deserialize, Clone, Default, PartialEq, Debug)]
+#[serde(default)]
+pub struct DailyHistory {
+    results: Vec<DailyResult>,
+}
+
+impl DailyHistory {
+    pub fn load() -> Self {
+        storage::load(Directory::Data, DAILY_FILE)
+    }
+
+    pub fn save(&self) {
+        if let Err(error) = storage::save(Directory::Data, DAILY_FILE, self) {
+            warn!("Couldn't save the daily history: {error:#}");
+        }
+    }
+
+    pub fn results(&self) -> &[DailyResult] {
+        &self.results
+    }
+
+    pub fn result(&self, seed: u64) -> Option<&DailyResult> {
+        self.results.iter().find(|result| result.seed == seed)
+    }
+
+    /// Counts the attempt of the day of `seed` as made, and returns whether it wasn't already.
+    /// The attempt is scored 0 until it's finished, so quitting doesn't give a second one.
+    pub fn start(&mut self, seed: u64) -> bool {
+        if self.result(seed).is_some() {
+            return false;
+        }
+
+        self.results.insert(
+            0,
+            DailyResult {
+                seed,
+                score: 0,
+                cleared: false,
+            },
+        );
+        self.results.truncate(DAILY_HISTORY_LEN);
+
+        true
+    }
+
+    pub fn finish(&mut self, seed: u64, score: u32, cleared: bool) {
+        if let Some(result) = self.results.iter_mut().find(|result| result.seed == seed) {
+            result.score = score;
+            result.cleared = cleared;
+        }
+    }
+}
+
+fn start_attempt(
+    mut run: ResMut<Run>,
+    mut history: ResMut<DailyHistory>,
+    input_source: Res<InputSource>,
+) {
+    if let GameMode::Daily { seed, scored: true } = run.mode {
+        if *input_source == InputSource::Devices && history.start(seed) {
+            history.save();
+        } else {
+            run.mode = GameMode::Daily {
+                seed,
+                scored: false,
+            };
+        }
+    }
+}
+
+/// Retrying after the scored attempt is practice.
+fn finish_attempt(
+    mut run: ResMut<Run>,
+    mut history: ResMut<DailyHistory>,
+    state: Res<State<GameState>>,
+    paddle_query: Query<&Score, With<Paddle>>,
+) {
+    if let GameMode::Daily { seed, scored: true } = run.mode {
+        let score = paddle_query.get_single().map_or(0, |score| score.0);
+
+        history.finish(seed, score, *state.current() == GameState::Win);
+        history.save();
+
+        run.mode = GameMode::Daily {
+            seed,
+            scored: false,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_same_day_gets_the_same_level() {
+        let seed = daily_seed(NaiveDate::from_ymd_opt(2024, 2, 29).unwrap());
+
+        assert_eq!(seed, 20_240_229);
+        assert_eq!(seed_date_string(seed), "2024-02-29");
+
+        let level = daily_level(seed);
+
+        assert_eq!(level.tiles, daily_level(seed).tiles);
+        assert_ne!(level.tiles, daily_level(seed + 1).tiles);
+
+        for row in &level.tiles {
+            let mirrored: Vec<_> = row.iter().rev().cloned().collect();
+            assert_eq!(*row, mirrored);
+        }
+    }
+
+    #[test]
+    fn every_day_has_one_scored_attempt() {
+        let mut history = DailyHistory::default();
+
+        assert!(history.start(20_240_229));
+        assert!(!history.start(20_240_229));
+
+        history.finish(20_240_229, 1200, true);
+
+        assert_eq!(
+            history.result(20_240_229),
+            Some(&DailyResult {
+                seed: 20_240_229,
+                score: 1200,
+                cleared: true,
+            })
+        );
+
+        for seed in 0..DAILY_HISTORY_LEN as u64 {
+            history.start(seed);
+        }
+
+        assert_eq!(history.results().len(), DAILY_HISTORY_LEN);
+        assert_eq!(history.result(20_240_229), None);
+    }
+}
@@ -0,0 +1,137 @@
+//! How hard the levels are.
+
+use std::fmt;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// The difficulty the current level is played on. It's chosen before a run and stays the same
+/// until the run is over.
+#[derive(
+    Resource,
+    Serialize,
+    Deserialize,
+    Default,
+    Copy,
+    Clone,
+    Eq,
+    PartialEq,
+    Ord,
+    PartialOrd,
+    Debug,
+    Hash,
+)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+    /// Harder than hard, for players who know the levels.
+    Arcade,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 4] = [
+        Difficulty::Easy,
+        Difficulty::Normal,
+        Difficulty::Hard,
+        Difficulty::Arcade,
+    ];
+
+    /// The lives a run starts with, when it starts with `normal_lives` on [`Difficulty::Normal`].
+    pub fn starting_lives(self, normal_lives: u32) -> u32 {
+        match self {
+            Difficulty::Easy => normal_lives + 2,
+            Difficulty::Normal => normal_lives,
+            Difficulty::Hard => normal_lives.saturating_sub(1).max(1),
+            Difficulty::Arcade => normal_lives.saturating_sub(2).max(1),
+        }
+    }
+
+    /// How fast the ball starts compared to [`crate::ball::DEFAULT_BALL_SPEED`].
+    pub fn ball_speed_scale(self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.8,
+            Difficulty::Normal => 1.,
+            Difficulty::Hard => 1.25,
+            Difficulty::Arcade => 1.4,
+        }
+    }
+
+    /// How much of its starting speed the ball gains every time it touches the paddle. It keeps
+    /// its speed on [`Difficulty::Normal`] and below.
+    pub fn ball_speed_ramp(self) -> f32 {
+        match self {
+            Difficulty::Easy => 0.,
+            Difficulty::Normal => 0.,
+            Difficulty::Hard => 0.02,
+            Difficulty::Arcade => 0.03,
+        }
+    }
+
+    /// How wide the paddle is compared to its image.
+    pub fn paddle_width_scale(self) -> f32 {
+        match self {
+            Difficulty::Easy => 1.25,
+            Difficulty::Normal => 1.,
+            Difficulty::Hard => 0.85,
+            Difficulty::Arcade => 0.75,
+        }
+    }
+
+    /// How many hits a silver block takes to break on `level`, counted from 0.
+    pub fn silver_hits(self, level: usize) -> u32 {
+        let level = level as u32;
+
+        match self {
+            Difficulty::Easy => level / 8 + 1,
+            Difficulty::Normal => level / 8 + 2,
+            Difficulty::Hard => level / 6 + 2,
+            Difficulty::Arcade => level / 4 + 3,
+        }
+    }
+
+    /// The chance a broken block drops a boost.
+    pub fn boost_drop_chance(self) -> f64 {
+        match self {
+            Difficulty::Easy => 0.15,
+            Difficulty::Normal => 0.1,
+            Difficulty::Hard => 0.07,
+            Difficulty::Arcade => 0.05,
+        }
+    }
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Difficulty::Easy => write!(f, "Easy"),
+            Difficulty::Normal => write!(f, "Normal"),
+            Difficulty::Hard => write!(f, "Hard"),
+            Difficulty::Arcade => write!(f, "Arcade"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn harder_presets_are_never_easier() {
+        for pair in Difficulty::ALL.windows(2) {
+            let (easier, harder) = (pair[0], pair[1]);
+
+            assert!(easier.starting_lives(3) >= harder.starting_lives(3));
+            assert!(easier.ball_speed_scale() < harder.ball_speed_scale());
+            assert!(easier.paddle_width_scale() > harder.paddle_width_scale());
+            assert!(easier.boost_drop_chance() > harder.boost_drop_chance());
+
+            for level in 0..32 {
+                assert!(easier.silver_hits(level) <= harder.silver_hits(level));
+            }
+        }
+
+        assert_eq!(Difficulty::Arcade.starting_lives(1), 1);
+    }
+}
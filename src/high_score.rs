@@ -0,0 +1,299 @@
+//! The best scores, kept between sessions for every level pack.
+
+use std::collections::BTreeMap;
+
+use bevy::prelude::*;
+use chrono::{Local, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    actions::InputSource,
+    difficulty::Difficulty,
+    level::CurrentLevel,
+    paddle::Paddle,
+    run::{GameMode, Run},
+    score::Score,
+    storage::{self, Directory},
+    ArkanoidSettings, GameState,
+};
+
+/// How many scores every table keeps.
+pub const HIGH_SCORE_COUNT: usize = 10;
+/// How many letters the initials have.
+pub const INITIALS_LEN: usize = 3;
+const HIGH_SCORE_FILE: &str = "high_scores.json";
+
+pub struct HighScorePlugin;
+
+impl Plugin for HighScorePlugin {
+    fn build(&self, app: &mut App) {
+        if !app.world.contains_resource::<HighScores>() {
+            app.insert_resource(HighScores::load());
+        }
+
+        app.init_resource::<PendingHighScore>()
+            .add_system_set(
+                SystemSet::on_enter(GameState::GameOver)
+                    .with_system(check_for_high_score.label(HighScoreSystem::Check)),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::Win)
+                    .with_system(check_for_high_score.label(HighScoreSystem::Check)),
+            );
+    }
+}
+
+#[derive(SystemLabel, Debug, Hash, PartialEq, Eq, Copy, Clone)]
+pub enum HighScoreSystem {
+    /// Sets the [`PendingHighScore`] when the score made it onto the table.
+    Check,
+}
+
+/// A single entry of a high score table.
+#[derive(Serialize, Deserialize, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct HighScore {
+    pub initials: String,
+    pub score: u32,
+    /// The level the run ended on, counted from 1.
+    pub level: usize,
+    /// When the score was set, in seconds since the Unix epoch.
+    pub date: u64,
+    #[serde(default)]
+    pub difficulty: Difficulty,
+}
+
+impl HighScore {
+    /// The day the score was set on, as `YYYY-MM-DD` in the player's time zone.
+    pub fn date_string(&self) -> String {
+        date_string(self.date)
+    }
+}
+
+/// The current time in seconds since the Unix epoch, which is how the tables store dates.
+pub fn now() -> u64 {
+    Utc::now().timestamp().max(0) as u64
+}
+
+/// The day of `date`, in seconds since the Unix epoch, as `YYYY-MM-DD` in the player's time
+/// zone, like the days of the daily challenge.
+pub fn date_string(date: u64) -> String {
+    Local
+        .timestamp_opt(date as i64, 0)
+        .single()
+        .map_or_else(String::new, |date| date.format("%Y-%m-%d").to_string())
+}
+
+/// The high score tables of every level pack and difficulty, keyed by the level folder, best
+/// score first.
+#[derive(Resource, Serialize, Deserialize, Clone, Default, PartialEq, Debug)]
+#[serde(default)]
+pub struct HighScores {
+    tables: BTreeMap<String, BTreeMap<Difficulty, Vec<HighScore>>>,
+}
+
+impl HighScores {
+    pub fn load() -> Self {
+        storage::load(Directory::Data, HIGH_SCORE_FILE)
+    }
+
+    pub fn save(&self) {
+        if let Err(error) = storage::save(Directory::Data, HIGH_SCORE_FILE, self) {
+            warn!("Couldn't save the high scores: {error:#}");
+        }
+    }
+
+    pub fn table(&self, level_pack: &str, difficulty: Difficulty) -> &[HighScore] {
+        self.tables
+            .get(level_pack)
+            .and_then(|tables| tables.get(&difficulty))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Whether `score` would make it onto the table of `level_pack` and `difficulty`.
+    pub fn qualifies(&self, level_pack: &str, difficulty: Difficulty, score: u32) -> bool {
+        let table = self.table(level_pack, difficulty);
+
+        score > 0
+            && (table.len() < HIGH_SCORE_COUNT
+                || table.last().map_or(true, |last| score > last.score))
+    }
+
+    /// Puts the score on the table of `level_pack` and the score's difficulty, and returns its
+    /// place, counted from 0. Ties go to the score that was set first.
+    pub fn insert(&mut self, level_pack: &str, high_score: HighScore) -> Option<usize> {
+        let table = self
+            .tables
+            .entry(level_pack.to_string())
+            .or_default()
+            .entry(high_score.difficulty)
+            .or_default();
+
+        let place = table
+            .iter()
+            .position(|other| other.score < high_score.score)
+            .unwrap_or(table.len());
+
+        if place >= HIGH_SCORE_COUNT {
+            return None;
+        }
+
+        table.insert(place, high_score);
+        table.truncate(HIGH_SCORE_COUNT);
+
+        Some(place)
+    }
+}
+
+/// A score that made it onto the table and is waiting for the player's initials.
+#[derive(Resource, Default, Clone, PartialEq, Debug)]
+pub struct PendingHighScore(pub Option<HighScore>);
+
+impl PendingHighScore {
+    /// Puts the pending score on the table under `initials` and saves the table.
+    pub fn submit(
+        &mut self,
+        initials: &str,
+        high_scores: &mut HighScores,
+        settings: &ArkanoidSettings,
+    ) -> Option<usize> {
+        let mut high_score = self.0.take()?;
+        high_score.initials = initials.to_string();
+
+        let place = high_scores.insert(&settings.level_folder, high_score);
+        high_scores.save();
+
+        place
+    }
+}
+
+/// The paddle, and its score with it, is cleaned up when the game over or the win screen is left,
+/// so the score has to be taken before that. A won level only ends the run if it was the last
+/// one or the player goes back to the menu, which the win screen decides.
+fn check_for_high_score(
+    paddle_query: Query<&Score, With<Paddle>>,
+    current_level: Res<CurrentLevel>,
+    run: Res<Run>,
+    input_source: Res<InputSource>,
+    settings: Res<ArkanoidSettings>,
+    high_scores: Res<HighScores>,
+    mut pending: ResMut<PendingHighScore>,
+) {
+    pending.0 = None;
+
+    // A replay that's played back already set its score when it was recorded. Time attack runs
+    // are ranked by their time instead.
+    if *input_source != InputSource::Devices || run.mode != GameMode::Classic {
+        return;
+    }
+
+    let score = match paddle_query.get_single() {
+        Ok(score) => score.0,
+        Err(_) => return,
+    };
+
+    if !high_scores.qualifies(&settings.level_folder, run.difficulty, score) {
+        return;
+    }
+
+    pending.0 = Some(HighScore {
+        initials: String::new(),
+        score,
+        level: current_level.0 + 1,
+        date: now(),
+        difficulty: run.difficulty,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn high_score(score: u32) -> HighScore {
+        HighScore {
+            initials: "AAA".to_string(),
+            score,
+            level: 1,
+            date: 0,
+            difficulty: Difficulty::Normal,
+        }
+    }
+
+    #[test]
+    fn table_keeps_the_best_scores() {
+        let mut high_scores = HighScores::default();
+
+        for score in 1..=HIGH_SCORE_COUNT as u32 {
+            high_scores.insert("levels", high_score(score * 10));
+        }
+
+        assert!(!high_scores.qualifies("levels", Difficulty::Normal, 10));
+        assert!(high_scores.qualifies("levels", Difficulty::Normal, 11));
+        assert!(high_scores.qualifies("levels", Difficulty::Hard, 1));
+        assert!(high_scores.qualifies("other", Difficulty::Normal, 1));
+
+        assert_eq!(high_scores.insert("levels", high_score(55)), Some(5));
+        assert_eq!(high_scores.insert("levels", high_score(5)), None);
+        assert_eq!(
+            high_scores.insert(
+                "levels",
+                HighScore {
+                    difficulty: Difficulty::Hard,
+                    ..high_score(5)
+                }
+            ),
+            Some(0)
+        );
+
+        let table = high_scores.table("levels", Difficulty::Normal);
+
+        assert_eq!(table.len(), HIGH_SCORE_COUNT);
+        assert_eq!(table[0].score, 100);
+        assert_eq!(table[5].score, 55);
+        assert_eq!(table.last().unwrap().score, 20);
+    }
+
+    #[test]
+    fn completed_run_asks_for_initials() {
+        let mut app = App::new();
+
+        app.add_state(GameState::Playing)
+            .insert_resource(HighScores::default())
+            .insert_resource(Run::new(Difficulty::Normal, 3, true))
+            .insert_resource(CurrentLevel(4))
+            .init_resource::<InputSource>()
+            .init_resource::<ArkanoidSettings>()
+            .add_plugin(HighScorePlugin);
+
+        app.world.spawn((Paddle, Score(1500)));
+
+        app.update();
+        app.world
+            .resource_mut::<State<GameState>>()
+            .set(GameState::Win)
+            .unwrap();
+        app.update();
+
+        let pending = app.world.resource::<PendingHighScore>();
+        let high_score = pending.0.as_ref().unwrap();
+
+        assert_eq!(high_score.score, 1500);
+        assert_eq!(high_score.level, 5);
+    }
+
+    #[test]
+    fn dates_are_formatted_in_local_time() {
+        let noon = NaiveDate::from_ymd_opt(2024, 2, 29)
+            .and_then(|date| date.and_hms_opt(12, 0, 0))
+            .and_then(|date| date.and_local_timezone(Local).single())
+            .unwrap();
+
+        let high_score = HighScore {
+            date: noon.timestamp() as u64,
+            ..high_score(0)
+        };
+
+        assert_eq!(high_score.date_string(), "2024-02-29");
+    }
+}
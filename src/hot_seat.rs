@@ -0,0 +1,223 @@
+//! Hot-seat runs, where two players take turns at the same controls.
+//!
+//! Every player has their own [`Run`], level and blocks. The turn passes to the other player
+//! whenever a life is lost, as long as they have lives left, and the level is left as it is until
+//! the player comes back to it. The game is over once both players are out of lives.
+
+use bevy::prelude::*;
+
+use crate::{
+    block::{Block, ResumedBlocks, SavedBlock},
+    level::CurrentLevel,
+    lives::Lives,
+    paddle::Paddle,
+    run::{GameMode, LevelStats, Run, RunSystem},
+    score::Score,
+    GameState,
+};
+
+pub const PLAYER_COUNT: usize = 2;
+
+pub struct HotSeatPlugin;
+
+impl Plugin for HotSeatPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HotSeat>()
+            .add_system_set(
+                SystemSet::on_exit(GameState::Menu)
+                    .with_system(seat_players.after(RunSystem::Start)),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::GameOver)
+                    .with_system(seat_players.after(RunSystem::Start)),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::TurnChange)
+                    .with_system(pass_turn.label(HotSeatSystem::EndTurn)),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::GameOver).with_system(
+                    end_last_turn
+                        .label(HotSeatSystem::EndTurn)
+                        .after(RunSystem::Finish),
+                ),
+            );
+    }
+}
+
+#[derive(SystemLabel, Debug, Hash, PartialEq, Eq, Copy, Clone)]
+pub enum HotSeatSystem {
+    /// Keeps what the player whose turn ended was left with.
+    EndTurn,
+}
+
+/// What a player comes back to on their next turn.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Seat {
+    pub run: Run,
+    pub level: usize,
+    /// The blocks that were left when the turn ended, `None` until the player had a turn.
+    pub blocks: Option<Vec<SavedBlock>>,
+}
+
+/// The players of a hot-seat run. There are no seats in the other runs.
+#[derive(Resource, Clone, Default, PartialEq, Debug)]
+pub struct HotSeat {
+    /// The player whose turn it is, counted from 0.
+    pub current: usize,
+    /// The seat of the current player is only up to date between turns.
+    pub seats: Vec<Seat>,
+}
+
+impl HotSeat {
+    /// Seats every player at the start of `run`, on `level`.
+    pub fn new(run: Run, level: usize) -> Self {
+        Self {
+            current: 0,
+            seats: vec![
+                Seat {
+                    run,
+                    level,
+                    blocks: None,
+                };
+                PLAYER_COUNT
+            ],
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        !self.seats.is_empty()
+    }
+
+    /// The player who plays once the current turn ends, as long as they have lives left.
+    pub fn next_player(&self) -> Option<usize> {
+        if self.seats.is_empty() {
+            return None;
+        }
+
+        let next = (self.current + 1) % self.seats.len();
+
+        (next != self.current && self.seats[next].run.lives > 0).then_some(next)
+    }
+
+    /// Ends the turn of the current player, who's left with `seat`, and returns the seat of the
+    /// next player. The turn stays with the current player if the other one is out.
+    pub fn pass_turn(&mut self, seat: Seat) -> &Seat {
+        let next = self.next_player().unwrap_or(self.current);
+
+        self.seats[self.current] = seat;
+        self.current = next;
+
+        &self.seats[next]
+    }
+}
+
+/// Retrying a hot-seat run starts both players over from the first level.
+fn seat_players(
+    run: Res<Run>,
+    mut hot_seat: ResMut<HotSeat>,
+    mut current_level: ResMut<CurrentLevel>,
+) {
+    if run.mode == GameMode::HotSeat {
+        current_level.0 = 0;
+        *hot_seat = HotSeat::new(*run, 0);
+    } else {
+        *hot_seat = HotSeat::default();
+    }
+}
+
+/// Takes the score, the lives and the blocks from the level the turn ended on, then clears it
+/// for the next player, who starts their turn on the level they left.
+#[allow(clippy::too_many_arguments)]
+fn pass_turn(
+    mut commands: Commands,
+    mut hot_seat: ResMut<HotSeat>,
+    mut run: ResMut<Run>,
+    level_stats: Res<LevelStats>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut resumed_blocks: ResMut<ResumedBlocks>,
+    paddle_query: Query<(&Score, &Lives), With<Paddle>>,
+    block_query: Query<(&Block, &Transform)>,
+    level_query: Query<Entity, Or<(With<Paddle>, With<Block>)>>,
+) {
+    let mut left_run = *run;
+    left_run.stats += level_stats.0;
+
+    if let Ok((score, lives)) = paddle_query.get_single() {
+        left_run.score = score.0;
+        left_run.lives = lives.lives();
+    }
+
+    let blocks = block_query
+        .iter()
+        .map(|(block, transform)| SavedBlock {
+            block_type: block.block_type,
+            position: transform.translation.truncate(),
+        })
+        .collect();
+
+    for entity in level_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let next_seat = hot_seat.pass_turn(Seat {
+        run: left_run,
+        level: current_level.0,
+        blocks: Some(blocks),
+    });
+
+    *run = next_seat.run;
+    current_level.0 = next_seat.level;
+    resumed_blocks.0 = next_seat.blocks.clone();
+}
+
+/// Keeps the final score of the player who lost the last life, for the game over screen.
+fn end_last_turn(mut hot_seat: ResMut<HotSeat>, run: Res<Run>, current_level: Res<CurrentLevel>) {
+    let current = hot_seat.current;
+
+    if let Some(seat) = hot_seat.seats.get_mut(current) {
+        seat.run = *run;
+        seat.level = current_level.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::difficulty::Difficulty;
+
+    use super::*;
+
+    fn seat(lives: u32) -> Seat {
+        Seat {
+            run: Run {
+                lives,
+                ..Run::new(Difficulty::Normal, 3, false)
+            },
+            level: 1,
+            blocks: Some(Vec::new()),
+        }
+    }
+
+    #[test]
+    fn turn_passes_to_players_with_lives_left() {
+        let mut hot_seat = HotSeat::new(Run::new(Difficulty::Normal, 3, false), 0);
+
+        assert!(hot_seat.is_active());
+        assert_eq!(hot_seat.next_player(), Some(1));
+
+        let next_seat = hot_seat.pass_turn(seat(2));
+
+        assert_eq!(next_seat.level, 0);
+        assert_eq!(next_seat.blocks, None);
+        assert_eq!(hot_seat.current, 1);
+
+        // The second player is out, so the first one keeps playing after losing a life.
+        hot_seat.pass_turn(seat(0));
+
+        assert_eq!(hot_seat.current, 0);
+        assert_eq!(hot_seat.seats[0], seat(2));
+        assert_eq!(hot_seat.next_player(), None);
+
+        assert_eq!(HotSeat::default().next_player(), None);
+    }
+}
@@ -7,7 +7,21 @@ use bevy::{
 
 use serde::Deserialize;
 
-use crate::GameState;
+use crate::{progress::MAX_STARS, run::Stats, GameState};
+
+/// Used by levels that don't set their own thresholds.
+const DEFAULT_STAR_THRESHOLDS: [StarThreshold; 2] = [
+    StarThreshold {
+        score: 0,
+        time: None,
+        lives_lost: Some(1),
+    },
+    StarThreshold {
+        score: 0,
+        time: None,
+        lives_lost: Some(0),
+    },
+];
 
 pub struct LevelPlugin;
 
@@ -16,10 +30,18 @@ impl Plugin for LevelPlugin {
         app.init_asset_loader::<LevelLoader>()
             .add_asset::<LevelAsset>()
             .init_resource::<CurrentLevel>()
-            .add_system_set(SystemSet::on_exit(GameState::Menu).with_system(reset_current_level));
+            .add_system_set(
+                SystemSet::on_exit(GameState::Menu)
+                    .with_system(reset_current_level.label(LevelSystem::Reset)),
+            );
     }
 }
 
+#[derive(SystemLabel, Debug, Hash, PartialEq, Eq, Copy, Clone)]
+pub enum LevelSystem {
+    Reset,
+}
+
 #[derive(Resource, Default, Deref, DerefMut)]
 pub struct CurrentLevel(pub usize);
 
@@ -30,7 +52,70 @@ fn reset_current_level(mut current_level: ResMut<CurrentLevel>) {
 #[derive(Debug, Deserialize, Reflect, TypeUuid)]
 #[uuid = "5c8be95c-5d54-46d2-a903-ac7bc7d5b4c2"]
 pub struct LevelAsset {
+    /// The name shown on the level select screen.
+    #[serde(default)]
+    pub name: Option<String>,
     pub tiles: Vec<Vec<String>>,
+    /// The size of the arena, the default arena is used when it's missing.
+    #[serde(default)]
+    pub arena: Option<ArenaSize>,
+    /// What it takes to earn the second and the third star.
+    #[serde(default)]
+    pub stars: Vec<StarThreshold>,
+}
+
+impl LevelAsset {
+    /// The name of the level, or `Level N` when it has none. `level` is counted from 0.
+    pub fn display_name(&self, level: usize) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| format!("Level {}", level + 1))
+    }
+
+    /// Clearing the level is worth a star, and every threshold met in a row is worth another.
+    pub fn rate(&self, score: u32, stats: &Stats) -> u8 {
+        let thresholds = if self.stars.is_empty() {
+            &DEFAULT_STAR_THRESHOLDS
+        } else {
+            self.stars.as_slice()
+        };
+
+        let met = thresholds
+            .iter()
+            .take_while(|threshold| threshold.is_met(score, stats))
+            .count();
+
+        (1 + met as u8).min(MAX_STARS)
+    }
+}
+
+/// The limits a level has to be cleared within to earn a star. Missing limits always pass.
+#[derive(Debug, Default, Deserialize, Reflect, FromReflect, Copy, Clone, PartialEq)]
+#[serde(default)]
+pub struct StarThreshold {
+    /// The points to score in the level alone.
+    pub score: u32,
+    /// The most seconds the level can take.
+    pub time: Option<f32>,
+    pub lives_lost: Option<u32>,
+}
+
+impl StarThreshold {
+    pub fn is_met(&self, score: u32, stats: &Stats) -> bool {
+        score >= self.score
+            && self
+                .time
+                .map_or(true, |time| stats.time.as_secs_f32() <= time)
+            && self
+                .lives_lost
+                .map_or(true, |lives_lost| stats.lives_lost <= lives_lost)
+    }
+}
+
+#[derive(Debug, Deserialize, Reflect, FromReflect, Copy, Clone, PartialEq)]
+pub struct ArenaSize {
+    pub width: f32,
+    pub height: f32,
 }
 
 #[derive(Default)]
@@ -53,3 +138,53 @@ impl AssetLoader for LevelLoader {
         &["lvl"]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn levels_are_rated_by_their_thresholds() {
+        let level = LevelAsset {
+            name: None,
+            tiles: Vec::new(),
+            arena: None,
+            stars: vec![
+                StarThreshold {
+                    time: Some(60.),
+                    ..default()
+                },
+                StarThreshold {
+                    score: 1000,
+                    time: Some(30.),
+                    lives_lost: Some(0),
+                },
+            ],
+        };
+
+        let mut stats = Stats {
+            lives_lost: 0,
+            time: Duration::from_secs(25),
+            ..default()
+        };
+
+        assert_eq!(level.rate(1200, &stats), 3);
+        // Too slow for either threshold.
+        assert_eq!(
+            level.rate(
+                1200,
+                &Stats {
+                    time: Duration::from_secs(90),
+                    ..stats
+                }
+            ),
+            1
+        );
+        assert_eq!(level.rate(800, &stats), 2);
+
+        stats.lives_lost = 2;
+        assert_eq!(level.rate(1200, &stats), 2);
+    }
+}
@@ -4,24 +4,41 @@
 // WARNING: Be careful with this, it is an unstable feature.
 #![feature(assert_matches)]
 
-use bevy::prelude::*;
+use std::path::PathBuf;
+
+use bevy::{app::PluginGroupBuilder, input::InputPlugin, prelude::*};
 use bevy_inspector_egui::quick::WorldInspectorPlugin;
-use bevy_kira_audio::prelude::*;
-use bevy_rapier2d::prelude::*;
-
-mod actions;
-mod assets;
-mod ball;
-mod block;
-mod camera;
-mod cursor;
-mod level;
-mod lives;
-mod paddle;
-mod score;
-mod ui;
+use physics::PhysicsBackend;
+
+pub mod actions;
+pub mod arena;
+pub mod assets;
+pub mod audio;
+pub mod ball;
+pub mod block;
+pub mod boost;
+pub mod camera;
+pub mod cursor;
+pub mod daily;
+pub mod difficulty;
+pub mod high_score;
+pub mod hot_seat;
+pub mod level;
+pub mod lives;
+pub mod paddle;
+pub mod physics;
+pub mod progress;
+pub mod replay;
+pub mod rng;
+pub mod run;
+pub mod save;
+pub mod score;
+pub mod settings;
+mod storage;
+pub mod time_attack;
+pub mod ui;
 mod util;
-mod win;
+pub mod win;
 
 #[derive(Clone, Eq, PartialEq, Debug, Hash)]
 pub enum GameState {
@@ -29,41 +46,85 @@ pub enum GameState {
     Menu,
     Help,
     Playing,
+    /// Pushed on top of [`GameState::Playing`], which keeps the level around but stops it.
+    Paused,
     Win,
     GameOver,
+    /// The list of saved replays.
+    Replays,
+    Controls,
+    ReplayViewer,
+    /// Opened from the menu, or pushed on top of [`GameState::Paused`].
+    Settings,
+    HighScores,
+    /// Picks the level a run starts from, opened by the play button of the menu.
+    LevelSelect,
+    /// Today's daily challenge and the results of the days before.
+    Daily,
+    /// Between two turns of a hot-seat run, until the next player is ready.
+    TurnChange,
+}
+
+/// Configures the game when it's embedded in another app.
+#[derive(Resource, Clone, Debug)]
+pub struct ArkanoidSettings {
+    /// The window opened by [`ArkanoidPlugin`]. When it's `None`, the window follows the display
+    /// settings the player saved.
+    pub window: Option<WindowDescriptor>,
+    pub physics_backend: PhysicsBackend,
+    /// The scale between the game's pixels and rapier's meters.
+    pub pixels_per_meter: f32,
+    /// How many lives the paddle starts a level with.
+    pub starting_lives: u32,
+    /// The folder the levels are loaded from, relative to the assets folder.
+    pub level_folder: String,
+    /// Whether the sound effects are played.
+    pub audio: bool,
+    /// A replay to play back once the levels are loaded.
+    pub replay: Option<PathBuf>,
+    /// Whether every level played is saved as a replay in the data directory.
+    pub record_replays: bool,
+}
+
+impl Default for ArkanoidSettings {
+    fn default() -> Self {
+        Self {
+            window: None,
+            physics_backend: PhysicsBackend::default(),
+            pixels_per_meter: 4.,
+            starting_lives: 3,
+            level_folder: "levels".to_string(),
+            audio: true,
+            replay: None,
+            record_replays: true,
+        }
+    }
 }
 
-pub struct ArkanoidPlugin;
+/// Opens a window and runs the whole game in it.
+#[derive(Default)]
+pub struct ArkanoidPlugin {
+    pub settings: ArkanoidSettings,
+}
 
 impl Plugin for ArkanoidPlugin {
     fn build(&self, app: &mut App) {
-        app.insert_resource(ClearColor(Color::rgb(0.4, 0.4, 0.4)))
+        let user_settings = settings::Settings::load();
+
+        let window = self
+            .settings
+            .window
+            .clone()
+            .unwrap_or_else(|| user_settings.display.window_descriptor());
+
+        app.insert_resource(user_settings)
             .add_plugins(DefaultPlugins.set(WindowPlugin {
-                window: WindowDescriptor {
-                    title: "Arkanoid".to_string(),
-                    // qHD
-                    width: 960.0,
-                    height: 540.0,
-                    resizable: false,
-                    ..default()
-                },
+                window,
                 ..default()
             }))
-            .add_plugin(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(4.))
-            .add_plugin(AudioPlugin)
-            .add_plugin(camera::CameraPlugin)
-            .add_plugin(actions::ActionsPlugin)
-            .add_plugin(cursor::CursorPlugin)
-            .add_plugin(assets::AssetPlugin)
-            .add_plugin(level::LevelPlugin)
-            .add_plugin(paddle::PaddlePlugin)
-            .add_plugin(ball::BallPlugin)
-            .add_plugin(win::WinPlugin)
-            .add_plugin(lives::LivesPlugin)
-            .add_plugin(score::PointsPlugin)
-            .add_plugin(ui::UiPlugin)
-            .add_plugin(block::BlockPlugin)
-            .add_state(GameState::Loading);
+            .add_plugins(ArkanoidPlugins {
+                settings: self.settings.clone(),
+            });
 
         // Debug-only plugins
         if cfg!(debug_assertions) {
@@ -71,3 +132,239 @@ impl Plugin for ArkanoidPlugin {
         }
     }
 }
+
+/// Runs the game logic without a window, a renderer or audio.
+///
+/// Meant for integration tests, bots and batch simulations. The game moves from
+/// [`GameState::Loading`] to [`GameState::Menu`] once the levels are loaded, and it's up to the
+/// caller to change the state from there.
+#[derive(Default)]
+pub struct HeadlessArkanoidPlugin {
+    pub settings: ArkanoidSettings,
+}
+
+impl Plugin for HeadlessArkanoidPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(MinimalPlugins)
+            .add_plugin(TransformPlugin)
+            .add_plugin(HierarchyPlugin)
+            .add_plugin(InputPlugin)
+            .add_plugin(bevy::asset::AssetPlugin::default())
+            .add_plugins(HeadlessArkanoidPlugins {
+                settings: self.settings.clone(),
+            });
+    }
+}
+
+/// Every plugin the game is made of, for apps that already added the [`DefaultPlugins`].
+///
+/// Single plugins can be replaced with [`PluginGroupBuilder::set`] or turned off with
+/// [`PluginGroupBuilder::disable`], for example to drop the [`ui::UiPlugin`].
+#[derive(Default)]
+pub struct ArkanoidPlugins {
+    pub settings: ArkanoidSettings,
+}
+
+impl PluginGroup for ArkanoidPlugins {
+    fn build(self) -> PluginGroupBuilder {
+        let audio = self.settings.audio;
+
+        let group = PluginGroupBuilder::start::<Self>()
+            .add(settings::SettingsPlugin)
+            .add(high_score::HighScorePlugin)
+            .add(save::SavePlugin)
+            .add(progress::ProgressPlugin)
+            .add(time_attack::TimeAttackPlugin)
+            .add(daily::DailyPlugin)
+            .add(audio::GameAudioPlugin)
+            .add(camera::CameraPlugin)
+            .add(cursor::CursorPlugin)
+            .add(assets::AssetPlugin {
+                level_folder: self.settings.level_folder.clone(),
+            })
+            .add(ui::UiPlugin)
+            .add(replay::viewer::ReplayViewerPlugin);
+
+        let group = add_gameplay_plugins(group, self.settings);
+
+        if audio {
+            group
+        } else {
+            group.disable::<audio::GameAudioPlugin>()
+        }
+    }
+}
+
+/// The game logic alone, for apps built on the [`MinimalPlugins`].
+#[derive(Default)]
+pub struct HeadlessArkanoidPlugins {
+    pub settings: ArkanoidSettings,
+}
+
+impl PluginGroup for HeadlessArkanoidPlugins {
+    fn build(self) -> PluginGroupBuilder {
+        let group = PluginGroupBuilder::start::<Self>().add(assets::HeadlessAssetPlugin {
+            level_folder: self.settings.level_folder.clone(),
+        });
+
+        add_gameplay_plugins(group, self.settings)
+    }
+}
+
+fn add_gameplay_plugins(
+    group: PluginGroupBuilder,
+    settings: ArkanoidSettings,
+) -> PluginGroupBuilder {
+    group
+        .add(physics::PhysicsPlugin {
+            backend: settings.physics_backend,
+            pixels_per_meter: settings.pixels_per_meter,
+        })
+        .add(actions::ActionsPlugin)
+        .add(replay::ReplayPlugin {
+            replay: settings.replay.clone(),
+            record: settings.record_replays,
+        })
+        .add(level::LevelPlugin)
+        .add(arena::ArenaPlugin)
+        .add(paddle::PaddlePlugin)
+        .add(ball::BallPlugin)
+        .add(win::WinPlugin)
+        .add(lives::LivesPlugin)
+        .add(score::PointsPlugin)
+        .add(run::RunPlugin)
+        .add(hot_seat::HotSeatPlugin)
+        .add(block::BlockPlugin)
+        .add(boost::BoostPlugin)
+        .add(GameStatePlugin { settings })
+}
+
+/// Shares the [`ArkanoidSettings`] with the rest of the game and starts the state machine. The
+/// [`audio::SoundEvent`]s are registered here too, since every part of the game sends them while
+/// the [`audio::GameAudioPlugin`] that plays them can be left out.
+struct GameStatePlugin {
+    settings: ArkanoidSettings,
+}
+
+impl Plugin for GameStatePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.settings.clone())
+            .add_event::<audio::SoundEvent>()
+            .add_state(GameState::Loading);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{assert_matches::assert_matches, thread, time::Duration};
+
+    use bevy::input::{keyboard::KeyboardInput, ButtonState};
+
+    use crate::{
+        actions::{ActionsSystem, InputMap, UiNavigation},
+        ball::{Ball, BallState},
+        block::Block,
+        paddle::Paddle,
+    };
+
+    use super::*;
+
+    fn count<C: Component>(app: &mut App) -> usize {
+        app.world.query::<&C>().iter(&app.world).count()
+    }
+
+    fn set_state(app: &mut App, state: GameState) {
+        app.world
+            .resource_mut::<State<GameState>>()
+            .set(state)
+            .unwrap();
+        app.update();
+    }
+
+    /// A headless app that loaded the levels and waits in the menu. Nothing is recorded, and the
+    /// bindings aren't read from or written to the disk.
+    fn loaded_app() -> App {
+        // The levels load in the background, so they get a few seconds at most.
+        const MAX_LOADING_UPDATES: usize = 5000;
+
+        let mut app = App::new();
+
+        app.insert_resource(InputMap::default())
+            .add_plugin(HeadlessArkanoidPlugin {
+                settings: ArkanoidSettings {
+                    audio: false,
+                    record_replays: false,
+                    ..default()
+                },
+            });
+
+        let loaded = (0..MAX_LOADING_UPDATES).any(|_| {
+            app.update();
+            thread::sleep(Duration::from_millis(1));
+
+            *app.world.resource::<State<GameState>>().current() == GameState::Menu
+        });
+
+        assert!(loaded, "the levels didn't load");
+
+        app
+    }
+
+    #[test]
+    fn headless_level_loading() {
+        let mut app = loaded_app();
+
+        set_state(&mut app, GameState::Playing);
+        app.update();
+
+        assert_eq!(
+            *app.world.resource::<State<GameState>>().current(),
+            GameState::Playing
+        );
+        assert!(count::<Block>(&mut app) > 0);
+        assert_eq!(count::<Paddle>(&mut app), 1);
+        assert_eq!(count::<Ball>(&mut app), 1);
+    }
+
+    /// Stands in for the play button of the menu, which isn't part of the headless plugins.
+    fn play_on_confirm(
+        mut navigation_events: EventReader<UiNavigation>,
+        mut state: ResMut<State<GameState>>,
+    ) {
+        if navigation_events
+            .iter()
+            .any(|navigation| *navigation == UiNavigation::Confirm)
+        {
+            let _ = state.set(GameState::Playing);
+        }
+    }
+
+    #[test]
+    fn confirming_a_button_keeps_the_ball_glued() {
+        let mut app = loaded_app();
+
+        app.add_system_set(
+            SystemSet::on_update(GameState::Menu)
+                .with_system(play_on_confirm.after(ActionsSystem::Input)),
+        );
+
+        // Space confirms the button, and it's bound to the primary action as well.
+        app.world.send_event(KeyboardInput {
+            scan_code: 0,
+            key_code: Some(KeyCode::Space),
+            state: ButtonState::Pressed,
+        });
+
+        app.update();
+        app.update();
+
+        assert_eq!(
+            *app.world.resource::<State<GameState>>().current(),
+            GameState::Playing
+        );
+
+        let ball = app.world.query::<&Ball>().single(&app.world);
+
+        assert_matches!(ball.state, BallState::Glued { .. });
+    }
+}
@@ -1,24 +1,30 @@
 use bevy::prelude::*;
 
-use crate::{assets::TextureAssets, ui::in_game::LivesUi, GameState};
+/// The most lives a paddle can have, unless it's given another cap.
+pub const MAX_LIVES: u32 = 99;
+/// The score the first extra life is awarded at.
+pub const FIRST_EXTRA_LIFE_SCORE: u32 = 20_000;
+/// The score between the extra lives after the first one.
+pub const EXTRA_LIFE_SCORE_INTERVAL: u32 = 60_000;
 
 pub struct LivesPlugin;
 
 impl Plugin for LivesPlugin {
     fn build(&self, app: &mut App) {
-        app.register_type::<Lives>()
-            .add_system_set(SystemSet::on_update(GameState::Playing).with_system(display_lives));
+        app.register_type::<Lives>();
     }
 }
 
 #[derive(Component, Reflect, Copy, Clone, PartialEq, Debug)]
 pub struct Lives {
     lives: u32,
+    /// The most lives that can be gained.
+    cap: u32,
 }
 
 impl Default for Lives {
     fn default() -> Self {
-        Self { lives: 3 }
+        Self::new(3)
     }
 }
 
@@ -28,9 +34,39 @@ pub struct LivesReachedZero {
 }
 
 impl Lives {
+    pub fn new(lives: u32) -> Self {
+        Self {
+            lives: lives.min(MAX_LIVES),
+            cap: MAX_LIVES,
+        }
+    }
+
+    pub fn lives(&self) -> u32 {
+        self.lives
+    }
+
+    pub fn cap(&self) -> u32 {
+        self.cap
+    }
+
+    /// Changes the most lives there can be, and drops the lives above it.
+    pub fn set_cap(&mut self, cap: u32) {
+        self.cap = cap;
+        self.lives = self.lives.min(cap);
+    }
+
+    /// Adds up to `amount` lives without going over the cap, and returns how many were added.
+    pub fn gain(&mut self, amount: u32) -> u32 {
+        let gained = amount.min(self.cap.saturating_sub(self.lives));
+        self.lives += gained;
+
+        gained
+    }
+
+    /// Takes away up to `amount` lives, never going below zero.
     #[must_use]
     pub fn lose(&mut self, amount: u32) -> LivesReachedZero {
-        self.lives -= amount;
+        self.lives = self.lives.saturating_sub(amount);
 
         LivesReachedZero {
             lives_reached_zero: self.lives == 0,
@@ -44,38 +80,17 @@ impl LivesReachedZero {
     }
 }
 
-#[derive(Component, Copy, Clone, PartialEq, Debug)]
-struct LivesUiElement;
-
-fn display_lives(
-    mut commands: Commands,
-    lives_query: Query<&Lives, Changed<Lives>>,
-    ui_query: Query<Entity, With<LivesUi>>,
-    ui_children_query: Query<Entity, With<LivesUiElement>>,
-    texture_assets: Res<TextureAssets>,
-) {
-    if let Ok(lives) = lives_query.get_single() {
-        for child in ui_children_query.iter() {
-            commands.entity(child).despawn_recursive();
-        }
-
-        if let Ok(ui) = ui_query.get_single() {
-            commands.entity(ui).with_children(|parent| {
-                for _ in 0..lives.lives {
-                    parent
-                        .spawn(ImageBundle {
-                            style: Style {
-                                margin: UiRect::left(Val::Px(8.)),
-                                ..default()
-                            },
-                            image: texture_assets.ball_small.clone().into(),
-                            ..default()
-                        })
-                        .insert(LivesUiElement);
-                }
-            });
+/// How many extra lives were earned by going from `old_score` to `new_score`.
+pub fn extra_lives_earned(old_score: u32, new_score: u32) -> u32 {
+    fn milestones_reached(score: u32) -> u32 {
+        if score < FIRST_EXTRA_LIFE_SCORE {
+            0
+        } else {
+            1 + (score - FIRST_EXTRA_LIFE_SCORE) / EXTRA_LIFE_SCORE_INTERVAL
         }
     }
+
+    milestones_reached(new_score).saturating_sub(milestones_reached(old_score))
 }
 
 #[cfg(test)]
@@ -106,5 +121,34 @@ mod tests {
         assert!(!lives.lose(1).lives_reached_zero());
 
         assert!(lives.lose(2).lives_reached_zero());
+        assert!(lives.lose(1).lives_reached_zero());
+        assert_eq!(lives.lives, 0);
+    }
+
+    #[test]
+    fn gain_lives_up_to_the_cap() {
+        let mut lives = Lives::new(3);
+
+        assert_eq!(lives.gain(2), 2);
+        assert_eq!(lives.lives, 5);
+
+        lives.set_cap(6);
+
+        assert_eq!(lives.gain(3), 1);
+        assert_eq!(lives.lives, 6);
+
+        lives.set_cap(4);
+
+        assert_eq!(lives.lives, 4);
+        assert_eq!(lives.gain(1), 0);
+    }
+
+    #[test]
+    fn extra_lives_at_score_milestones() {
+        assert_eq!(extra_lives_earned(0, FIRST_EXTRA_LIFE_SCORE - 1), 0);
+        assert_eq!(extra_lives_earned(19_900, 20_100), 1);
+        assert_eq!(extra_lives_earned(20_100, 79_900), 0);
+        assert_eq!(extra_lives_earned(79_900, 80_000), 1);
+        assert_eq!(extra_lives_earned(0, 140_000), 3);
     }
 }
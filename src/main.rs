@@ -1,6 +1,19 @@
-use arkanoid::ArkanoidPlugin;
+use std::{env, path::PathBuf};
+
+use arkanoid::{ArkanoidPlugin, ArkanoidSettings};
 use bevy::prelude::*;
 
 fn main() {
-    App::new().add_plugin(ArkanoidPlugin).run();
+    let mut settings = ArkanoidSettings::default();
+
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--replay" => settings.replay = args.next().map(PathBuf::from),
+            _ => eprintln!("Unknown argument: {arg}"),
+        }
+    }
+
+    App::new().add_plugin(ArkanoidPlugin { settings }).run();
 }
@@ -1,42 +1,65 @@
 use bevy::prelude::*;
-use bevy_kira_audio::{Audio, AudioControl};
 use bevy_rapier2d::prelude::*;
+use serde::{Deserialize, Serialize};
 
 use crate::{
-    actions::Actions,
-    assets::{AudioAssets, TextureAssets},
+    actions::{Actions, ActionsSystem, FollowCursor, SplitControls},
+    arena::{Arena, ArenaSystem},
+    assets::TextureAssets,
+    audio::{Sound, SoundEvent},
     ball::{Ball, BallResetEvent},
-    cursor::FollowCursor,
+    difficulty::Difficulty,
+    hot_seat::HotSeat,
     lives::Lives,
-    score::Score,
+    replay::ReplaySystem,
+    run::{GameMode, LevelStats, Run},
+    score::{Combo, Score},
     util::cleanup,
     GameState,
 };
 
-pub const PADDLE_SPEED: f32 = 500.0;
-pub const PADDLE_ALTITUDE: f32 = -200.0;
+pub const DEFAULT_PADDLE_SPEED: f32 = 500.0;
+/// How high above the bottom of the arena the paddle sits.
+pub const PADDLE_ALTITUDE: f32 = 70.0;
+/// The points a paddle gets when the ball gets past the edge of the other one.
+pub const GOAL_POINTS: u32 = 500;
+/// Tints the second paddle, so the players can tell them apart.
+const SECOND_PADDLE_COLOR: Color = Color::rgb(0.6, 0.8, 1.);
+/// The size of `img/paddle.png`, used to size the collider without loading the image.
+pub const PADDLE_TEXTURE_SIZE: Vec2 = Vec2::new(500., 125.);
 
 pub struct PaddlePlugin;
 
 impl Plugin for PaddlePlugin {
     fn build(&self, app: &mut App) {
-        app.add_system_set(
-            SystemSet::on_enter(GameState::Playing)
-                .with_system(paddle_setup.label(PaddleSystem::Setup)),
-        )
-        .add_system_set(
-            SystemSet::on_update(GameState::Playing)
-                .with_system(paddle_movement.label(PaddleSystem::Movement))
-                .with_system(lose_lives.label(PaddleSystem::LoseLives)),
-        )
-        .add_system_set(
-            SystemSet::on_exit(GameState::GameOver)
-                .with_system(cleanup::<Paddle>.label(PaddleSystem::Cleanup)),
-        )
-        .add_system_set(
-            SystemSet::on_exit(GameState::Win)
-                .with_system(cleanup::<Paddle>.label(PaddleSystem::Cleanup)),
-        );
+        app.register_type::<Player>()
+            .register_type::<Edge>()
+            .init_resource::<PaddleSpeed>()
+            .add_system_set(
+                SystemSet::on_enter(GameState::Playing).with_system(
+                    paddle_setup
+                        .label(PaddleSystem::Setup)
+                        .after(ArenaSystem::Setup)
+                        .after(ReplaySystem::Start),
+                ),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(
+                        paddle_movement
+                            .label(PaddleSystem::Movement)
+                            .after(ActionsSystem::Input),
+                    )
+                    .with_system(lose_lives.label(PaddleSystem::LoseLives)),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::GameOver)
+                    .with_system(cleanup::<Paddle>.label(PaddleSystem::Cleanup)),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Win)
+                    .with_system(cleanup::<Paddle>.label(PaddleSystem::Cleanup)),
+            );
     }
 }
 
@@ -51,45 +74,166 @@ pub enum PaddleSystem {
 #[derive(Component, Default, Copy, Clone, Eq, PartialEq, Debug, Hash)]
 pub struct Paddle;
 
+/// The player who moves the paddle, counted from 0.
+#[derive(Component, Reflect, Default, Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct Player(pub usize);
+
+/// The player whose score and lives count for `player`, out of the `owners` of a score and lives.
+/// Paddles without their own share the ones of the first paddle.
+pub fn pool_owner(player: usize, owners: impl IntoIterator<Item = usize>) -> usize {
+    if owners.into_iter().any(|owner| owner == player) {
+        player
+    } else {
+        0
+    }
+}
+
+/// The edge of the arena a paddle guards. A ball that gets past it costs the paddle a life.
+#[derive(Component, Reflect, Default, Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum Edge {
+    #[default]
+    Bottom,
+    /// The paddle at the top is flipped, and sends the ball down.
+    Top,
+}
+
+impl Edge {
+    /// Which way the paddle sends the ball, 1 for up and -1 for down.
+    pub fn direction(self) -> f32 {
+        match self {
+            Edge::Bottom => 1.,
+            Edge::Top => -1.,
+        }
+    }
+
+    /// The edge of `arena` that `position` is past, if any.
+    pub fn passed(arena: &Arena, position: Vec2) -> Option<Self> {
+        if position.y < arena.bottom() {
+            Some(Edge::Bottom)
+        } else if position.y > arena.top() {
+            Some(Edge::Top)
+        } else {
+            None
+        }
+    }
+}
+
+/// How fast the keyboard and the gamepads move the paddle, in pixels per second.
+#[derive(Resource, Serialize, Deserialize, Copy, Clone, PartialEq, Debug)]
+pub struct PaddleSpeed(pub f32);
+
+impl Default for PaddleSpeed {
+    fn default() -> Self {
+        Self(DEFAULT_PADDLE_SPEED)
+    }
+}
+
 #[derive(Bundle, Default)]
 pub struct PaddleBundle {
     paddle: Paddle,
+    player: Player,
+    edge: Edge,
     name: Name,
     lives: Lives,
     points: Score,
+    combo: Combo,
     collider: Collider,
     #[bundle]
     sprite: SpriteBundle,
 }
 
+/// In co-op runs, the second paddle shares the lives and the score of the first one, so it has
+/// none of its own. In versus runs, it has its own and guards the top edge.
 fn paddle_setup(
     mut commands: Commands,
     texture_assets: Res<TextureAssets>,
-    images: Res<Assets<Image>>,
+    arena: Res<Arena>,
+    run: Res<Run>,
+    difficulty: Res<Difficulty>,
+    mut split_controls: ResMut<SplitControls>,
 ) {
-    let image = images
-        .get(&texture_assets.paddle)
-        .expect("Paddle texture not loaded yet!");
-    let paddle_size = image.size();
+    let paddle_count = run.mode.paddle_count();
+    let versus = run.mode == GameMode::Versus;
+    split_controls.0 = paddle_count > 1;
+
+    let sprite = |player: usize, edge: Edge| {
+        // Paddles on the same edge start spread out over the width of the arena.
+        let x = if versus {
+            0.
+        } else {
+            arena.size.x * ((player as f32 + 0.5) / paddle_count as f32 - 0.5)
+        };
 
-    commands.spawn(PaddleBundle {
-        name: Name::new("Paddle"),
-        sprite: SpriteBundle {
-            transform: Transform::from_xyz(0.0, PADDLE_ALTITUDE, 1.0).with_scale(Vec3::splat(0.25)),
+        let y = match edge {
+            Edge::Bottom => arena.bottom() + PADDLE_ALTITUDE,
+            Edge::Top => arena.top() - PADDLE_ALTITUDE,
+        };
+
+        SpriteBundle {
+            transform: Transform::from_xyz(x, y, 1.0).with_scale(Vec3::new(
+                0.25 * difficulty.paddle_width_scale(),
+                0.25,
+                0.25,
+            )),
+            sprite: Sprite {
+                color: if player == 0 {
+                    Color::WHITE
+                } else {
+                    SECOND_PADDLE_COLOR
+                },
+                flip_y: edge == Edge::Top,
+                ..default()
+            },
             texture: texture_assets.paddle.clone(),
             ..default()
-        },
-        collider: Collider::cuboid(paddle_size.x / 2., paddle_size.y / 2.),
+        }
+    };
+
+    let collider = Collider::cuboid(PADDLE_TEXTURE_SIZE.x / 2., PADDLE_TEXTURE_SIZE.y / 2.);
+
+    commands.spawn(PaddleBundle {
+        name: Name::new("Paddle"),
+        sprite: sprite(0, Edge::Bottom),
+        collider: collider.clone(),
+        lives: Lives::new(run.lives),
+        points: Score(run.score),
         ..default()
     });
+
+    for player in 1..paddle_count {
+        let name = Name::new(format!("Paddle {}", player + 1));
+
+        if versus {
+            commands.spawn(PaddleBundle {
+                player: Player(player),
+                edge: Edge::Top,
+                name,
+                sprite: sprite(player, Edge::Top),
+                collider: collider.clone(),
+                lives: Lives::new(run.lives),
+                ..default()
+            });
+        } else {
+            commands.spawn((
+                Paddle,
+                Player(player),
+                Edge::Bottom,
+                name,
+                collider.clone(),
+                sprite(player, Edge::Bottom),
+            ));
+        }
+    }
 }
 
+/// The mouse moves the paddle of the first player.
 fn paddle_movement(
-    mut paddle_query: Query<(&mut Transform, &Collider), With<Paddle>>,
+    mut paddle_query: Query<(&mut Transform, &Collider, &Player), With<Paddle>>,
     cursor_query: Query<&Transform, (With<FollowCursor>, Without<Paddle>, Changed<Transform>)>,
     actions: Res<Actions>,
+    paddle_speed: Res<PaddleSpeed>,
     time: Res<Time>,
-    windows: Res<Windows>,
+    arena: Res<Arena>,
 ) {
     let cursor_position = if let Ok(transform) = cursor_query.get_single() {
         Some(transform.translation)
@@ -97,57 +241,95 @@ fn paddle_movement(
         None
     };
 
-    let window_width = windows.get_primary().map(|window| window.width());
-
-    for (mut paddle_transform, paddle_collider) in paddle_query.iter_mut() {
-        if let Some(cursor_position) = cursor_position {
+    for (mut paddle_transform, paddle_collider, player) in paddle_query.iter_mut() {
+        if let (Some(cursor_position), 0) = (cursor_position, player.0) {
             paddle_transform.translation.x = cursor_position.x;
         }
 
-        let direction = actions.player_movement.unwrap_or(0.0);
+        let direction = actions.movement(player.0);
 
-        paddle_transform.translation.x += direction * PADDLE_SPEED * time.delta_seconds();
+        paddle_transform.translation.x += direction * paddle_speed.0 * time.delta_seconds();
 
-        if let Some(window_width) = window_width {
-            let bound = window_width / 2.
-                - paddle_collider
-                    .as_cuboid()
-                    .expect("The paddle collider is not a cuboid!")
-                    .half_extents()
-                    .x;
-
-            if bound < 0. {
-                panic!("Paddle is too big for the window!");
-            }
+        let bound = arena.half_size().x
+            - paddle_collider
+                .as_cuboid()
+                .expect("The paddle collider is not a cuboid!")
+                .half_extents()
+                .x;
 
-            paddle_transform.translation.x = paddle_transform.translation.x.clamp(-bound, bound);
+        if bound < 0. {
+            panic!("Paddle is too big for the arena!");
         }
+
+        paddle_transform.translation.x = paddle_transform.translation.x.clamp(-bound, bound);
     }
 }
 
+/// A ball that gets past an edge costs the paddles guarding it a life, and the paddles on the
+/// other edge get the points of a goal. In a hot-seat run, every life lost passes the turn to the
+/// other player while they have lives left.
+#[allow(clippy::too_many_arguments)]
 fn lose_lives(
     mut state: ResMut<State<GameState>>,
-    mut lives_query: Query<&mut Lives>,
-    ball_query: Query<&Transform, With<Ball>>,
-    windows: Res<Windows>,
-    audio: Res<Audio>,
-    audio_assets: Res<AudioAssets>,
+    mut lives_query: Query<(&mut Lives, &mut Combo, &mut Score, &Player, &Edge)>,
+    edge_query: Query<(&Player, &Edge), With<Paddle>>,
+    ball_query: Query<(&Ball, &Transform)>,
+    arena: Res<Arena>,
+    hot_seat: Res<HotSeat>,
+    mut level_stats: ResMut<LevelStats>,
+    mut sound_events: EventWriter<SoundEvent>,
     mut ball_reset_event_writer: EventWriter<BallResetEvent>,
 ) {
-    let window = windows.get_primary().expect("Primary window not found");
-    let transform = ball_query.single();
+    for (ball, transform) in ball_query.iter() {
+        let edge = match Edge::passed(&arena, transform.translation.truncate()) {
+            Some(edge) => edge,
+            None => continue,
+        };
+
+        // The paddle that lost the ball serves the next one, unless the ball came from a paddle
+        // on the same edge.
+        let from_same_edge = edge_query
+            .iter()
+            .any(|(player, paddle_edge)| player.0 == ball.player && *paddle_edge == edge);
+
+        for (mut lives, mut combo, mut score, player, paddle_edge) in lives_query.iter_mut() {
+            if *paddle_edge != edge {
+                score.0 += GOAL_POINTS;
+                continue;
+            }
+
+            level_stats.lives_lost += 1;
+            combo.reset();
+
+            let out_of_lives = lives.lose(1).lives_reached_zero();
+
+            if hot_seat.next_player().is_some() {
+                let _ = state.set(GameState::TurnChange);
 
-    if transform.translation.y < -window.height() / 2. {
-        for mut lives in lives_query.iter_mut() {
-            if lives.lose(1).lives_reached_zero() {
+                sound_events.send(SoundEvent(if out_of_lives {
+                    Sound::Lose
+                } else {
+                    Sound::LoseLive
+                }));
+
+                continue;
+            }
+
+            if out_of_lives {
                 let _ = state.set(GameState::GameOver);
 
-                audio.play(audio_assets.lose.clone());
+                sound_events.send(SoundEvent(Sound::Lose));
             } else {
-                audio.play(audio_assets.lose_live.clone());
+                sound_events.send(SoundEvent(Sound::LoseLive));
             }
 
-            ball_reset_event_writer.send(BallResetEvent);
+            let server = if from_same_edge {
+                ball.player
+            } else {
+                player.0
+            };
+
+            ball_reset_event_writer.send(BallResetEvent(server));
         }
     }
 }
@@ -170,6 +352,7 @@ mod tests {
                 transform: Transform::from_xyz(paddle_x, 0.0, 1.0),
                 ..default()
             },
+            collider: Collider::cuboid(50., 10.),
             ..default()
         });
 
@@ -179,15 +362,18 @@ mod tests {
 
         let actions = Actions {
             player_movement: Some(1.0),
+            ..default()
         };
 
         world.insert_resource(actions);
+        world.init_resource::<PaddleSpeed>();
 
         let mut time = Time::default();
         time.update();
         world.insert_resource(time);
 
-        world.init_resource::<Windows>();
+        // Make the arena wide enough for the paddle to never hit the walls.
+        world.insert_resource(Arena::new(Vec2::new(2000., 1000.)));
 
         let mut time = world.resource_mut::<Time>();
         let last_update = time.last_update().unwrap();
@@ -199,7 +385,10 @@ mod tests {
             .query_filtered::<&Transform, With<Paddle>>()
             .single(&world);
 
-        assert_eq!(paddle_transform.translation.x, paddle_x + PADDLE_SPEED);
+        assert_eq!(
+            paddle_transform.translation.x,
+            paddle_x + DEFAULT_PADDLE_SPEED
+        );
     }
 
     #[test]
@@ -214,6 +403,7 @@ mod tests {
                 transform: Transform::from_xyz(paddle_x, 0.0, 1.0),
                 ..default()
             },
+            collider: Collider::cuboid(50., 10.),
             ..default()
         });
 
@@ -223,15 +413,18 @@ mod tests {
 
         let actions = Actions {
             player_movement: Some(-1.0),
+            ..default()
         };
 
         world.insert_resource(actions);
+        world.init_resource::<PaddleSpeed>();
 
         let mut time = Time::default();
         time.update();
         world.insert_resource(time);
 
-        world.init_resource::<Windows>();
+        // Make the arena wide enough for the paddle to never hit the walls.
+        world.insert_resource(Arena::new(Vec2::new(2000., 1000.)));
 
         let mut time = world.resource_mut::<Time>();
         let last_update = time.last_update().unwrap();
@@ -243,6 +436,25 @@ mod tests {
             .query_filtered::<&Transform, With<Paddle>>()
             .single(&world);
 
-        assert_eq!(paddle_transform.translation.x, paddle_x - PADDLE_SPEED);
+        assert_eq!(
+            paddle_transform.translation.x,
+            paddle_x - DEFAULT_PADDLE_SPEED
+        );
+    }
+
+    #[test]
+    fn goal_lines_test() {
+        let arena = Arena::new(Vec2::new(800., 600.));
+
+        assert_eq!(
+            Edge::passed(&arena, Vec2::new(0., -301.)),
+            Some(Edge::Bottom)
+        );
+        assert_eq!(Edge::passed(&arena, Vec2::new(0., 301.)), Some(Edge::Top));
+        assert_eq!(Edge::passed(&arena, Vec2::new(390., 0.)), None);
+
+        // The second paddle of a co-op run shares the score and the lives of the first one.
+        assert_eq!(pool_owner(1, [0]), 0);
+        assert_eq!(pool_owner(1, [0, 1]), 1);
     }
 }
@@ -0,0 +1,196 @@
+//! Selects how the ball is simulated.
+//!
+//! The [`PhysicsBackend::Manual`] backend moves the ball in `ball_movement` and reflects it by
+//! hand, using rapier only for shape casts. The [`PhysicsBackend::Rapier`] backend turns the ball
+//! into a dynamic rigid body and lets rapier resolve every contact, which also covers ball-ball
+//! collisions, moving blocks and colliders that are not boxes. The walls it bounces off are spawned
+//! by the arena.
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+use crate::{
+    audio::{Sound, SoundEvent},
+    ball::{Ball, BallState, BlockHitEvent, PaddleHitEvent},
+    block::Block,
+    paddle::{Edge, Paddle, Player},
+    GameState,
+};
+
+pub struct PhysicsPlugin {
+    pub backend: PhysicsBackend,
+    /// The scale between the game's pixels and rapier's meters.
+    pub pixels_per_meter: f32,
+}
+
+impl Plugin for PhysicsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(self.backend)
+            .add_plugin(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(
+                self.pixels_per_meter,
+            ))
+            .add_system(attach_ball_body)
+            .add_system(attach_fixed_bodies)
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(sync_ball_body.label(PhysicsSystem::SyncBall))
+                    .with_system(ball_collisions.after(PhysicsSystem::SyncBall)),
+            )
+            .add_system_set(SystemSet::on_pause(GameState::Playing).with_system(pause_physics))
+            .add_system_set(SystemSet::on_resume(GameState::Playing).with_system(resume_physics));
+    }
+}
+
+#[derive(SystemLabel, Debug, Hash, PartialEq, Eq, Copy, Clone)]
+pub enum PhysicsSystem {
+    SyncBall,
+}
+
+/// Decides who is responsible for moving the ball and resolving its collisions.
+#[derive(Resource, Default, Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum PhysicsBackend {
+    #[default]
+    Manual,
+    Rapier,
+}
+
+fn attach_ball_body(
+    mut commands: Commands,
+    ball_query: Query<Entity, Added<Ball>>,
+    physics_backend: Res<PhysicsBackend>,
+) {
+    if *physics_backend != PhysicsBackend::Rapier {
+        return;
+    }
+
+    for entity in ball_query.iter() {
+        commands.entity(entity).insert((
+            RigidBody::KinematicPositionBased,
+            Velocity::zero(),
+            GravityScale(0.),
+            LockedAxes::ROTATION_LOCKED,
+            Ccd::enabled(),
+            Restitution {
+                coefficient: 1.,
+                combine_rule: CoefficientCombineRule::Max,
+            },
+            Friction {
+                coefficient: 0.,
+                combine_rule: CoefficientCombineRule::Min,
+            },
+            ActiveEvents::COLLISION_EVENTS,
+        ));
+    }
+}
+
+/// Rapier steps outside of the game states, so it's stopped by hand while the game is paused.
+fn pause_physics(mut rapier_configuration: ResMut<RapierConfiguration>) {
+    rapier_configuration.physics_pipeline_active = false;
+}
+
+fn resume_physics(mut rapier_configuration: ResMut<RapierConfiguration>) {
+    rapier_configuration.physics_pipeline_active = true;
+}
+
+/// Blocks and paddles are moved by setting their transforms, so they become kinematic bodies.
+fn attach_fixed_bodies(
+    mut commands: Commands,
+    query: Query<Entity, Or<(Added<Block>, Added<Paddle>)>>,
+    physics_backend: Res<PhysicsBackend>,
+) {
+    if *physics_backend != PhysicsBackend::Rapier {
+        return;
+    }
+
+    for entity in query.iter() {
+        commands
+            .entity(entity)
+            .insert(RigidBody::KinematicPositionBased);
+    }
+}
+
+/// Keeps the rigid body of every ball in line with its [`BallState`].
+///
+/// A glued ball is kinematic so it can ride on the paddle. A free ball is dynamic and always
+/// travels at [`Ball::speed`], since rapier would otherwise slowly lose or gain energy.
+fn sync_ball_body(
+    mut ball_query: Query<(&mut Ball, &mut RigidBody, &mut Velocity)>,
+    physics_backend: Res<PhysicsBackend>,
+) {
+    if *physics_backend != PhysicsBackend::Rapier {
+        return;
+    }
+
+    for (mut ball, mut rigid_body, mut velocity) in ball_query.iter_mut() {
+        match ball.state {
+            BallState::Glued { .. } => {
+                if *rigid_body != RigidBody::KinematicPositionBased {
+                    *rigid_body = RigidBody::KinematicPositionBased;
+                }
+
+                velocity.linvel = Vec2::ZERO;
+            }
+            BallState::Free => {
+                if *rigid_body != RigidBody::Dynamic {
+                    *rigid_body = RigidBody::Dynamic;
+                }
+
+                if let Some(direction) = velocity.linvel.try_normalize() {
+                    ball.direction = direction;
+                }
+
+                velocity.linvel = ball.direction * ball.speed;
+            }
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn ball_collisions(
+    mut ball_query: Query<(&mut Ball, &Transform, &mut Velocity)>,
+    paddle_query: Query<(&Transform, &Collider, &Player, &Edge), (With<Paddle>, Without<Ball>)>,
+    block_query: Query<(), With<Block>>,
+    physics_backend: Res<PhysicsBackend>,
+    mut sound_events: EventWriter<SoundEvent>,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut hit_block_event_writer: EventWriter<BlockHitEvent>,
+    mut hit_paddle_event_writer: EventWriter<PaddleHitEvent>,
+) {
+    if *physics_backend != PhysicsBackend::Rapier {
+        return;
+    }
+
+    for event in collision_events.iter() {
+        let (first, second) = match event {
+            CollisionEvent::Started(first, second, _) => (*first, *second),
+            CollisionEvent::Stopped(..) => continue,
+        };
+
+        for (ball_entity, other) in [(first, second), (second, first)] {
+            let (mut ball, ball_transform, mut velocity) = match ball_query.get_mut(ball_entity) {
+                Ok(ball) => ball,
+                Err(_) => continue,
+            };
+
+            if block_query.get(other).is_ok() {
+                hit_block_event_writer.send(BlockHitEvent(other, ball.player));
+            } else if let Ok((paddle_transform, paddle_collider, player, edge)) =
+                paddle_query.get(other)
+            {
+                let paddle_center = paddle_transform.translation.truncate();
+                let paddle_extents = paddle_collider.as_cuboid().unwrap().half_extents();
+
+                // Aim the ball the same way the manual backend does, so the paddle feels the same.
+                let percentage =
+                    (ball_transform.translation.x - paddle_center.x) / paddle_extents.x;
+
+                ball.direction = Vec2::new(percentage / 2., edge.direction()).normalize();
+                ball.player = player.0;
+                velocity.linvel = ball.direction * ball.speed;
+
+                sound_events.send(SoundEvent(Sound::Bounce));
+                hit_paddle_event_writer.send(PaddleHitEvent(other, ball_entity));
+            }
+        }
+    }
+}
@@ -0,0 +1,234 @@
+//! Which levels were cleared and the best results in them, kept between sessions for every
+//! level pack. Clearing a level unlocks the next one on the level select screen.
+
+use std::{collections::BTreeMap, time::Duration};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    actions::InputSource,
+    assets::LevelAssets,
+    daily::daily_level,
+    level::{CurrentLevel, LevelAsset},
+    paddle::Paddle,
+    run::{GameMode, LevelStats, Run, RunSystem, Stats},
+    score::Score,
+    storage::{self, Directory},
+    ArkanoidSettings, GameState,
+};
+
+/// The most stars a level can be cleared with.
+pub const MAX_STARS: u8 = 3;
+const PROGRESS_FILE: &str = "progress.json";
+
+pub struct ProgressPlugin;
+
+impl Plugin for ProgressPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.world.contains_resource::<Progress>() {
+            app.insert_resource(Progress::load());
+        }
+
+        app.init_resource::<LevelResult>().add_system_set(
+            SystemSet::on_enter(GameState::Win).with_system(
+                rate_cleared_level
+                    .label(ProgressSystem::Rate)
+                    .before(RunSystem::Finish),
+            ),
+        );
+    }
+}
+
+#[derive(SystemLabel, Debug, Hash, PartialEq, Eq, Copy, Clone)]
+pub enum ProgressSystem {
+    /// Sets the [`LevelResult`] once a level is won.
+    Rate,
+}
+
+/// How the level that was just won went.
+#[derive(Resource, Copy, Clone, Default, PartialEq, Debug)]
+pub struct LevelResult {
+    /// The points scored in the level alone.
+    pub score: u32,
+    pub stars: u8,
+    pub stats: Stats,
+    /// Whether the level went better than ever before in any way.
+    pub new_best: bool,
+}
+
+/// The best results in a single level.
+#[derive(Serialize, Deserialize, Copy, Clone, Default, Eq, PartialEq, Debug, Hash)]
+#[serde(default)]
+pub struct LevelRecord {
+    /// The most points scored in the level alone.
+    pub best_score: u32,
+    /// The most stars the level was cleared with, 0 until it's cleared.
+    pub stars: u8,
+    pub best_time: Option<Duration>,
+    pub best_combo: u32,
+}
+
+impl LevelRecord {
+    pub fn is_cleared(&self) -> bool {
+        self.stars > 0
+    }
+}
+
+/// The [`LevelRecord`]s of every level pack, keyed by the level folder and indexed by the level.
+#[derive(Resource, Serialize, Deserialize, Clone, Default, PartialEq, Debug)]
+#[serde(default)]
+pub struct Progress {
+    packs: BTreeMap<String, Vec<LevelRecord>>,
+}
+
+impl Progress {
+    pub fn load() -> Self {
+        storage::load(Directory::Data, PROGRESS_FILE)
+    }
+
+    pub fn save(&self) {
+        if let Err(error) = storage::save(Directory::Data, PROGRESS_FILE, self) {
+            warn!("Couldn't save the progress: {error:#}");
+        }
+    }
+
+    pub fn record(&self, level_pack: &str, level: usize) -> LevelRecord {
+        self.packs
+            .get(level_pack)
+            .and_then(|records| records.get(level))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// The first level is always unlocked, the others once the level before them is cleared.
+    pub fn is_unlocked(&self, level_pack: &str, level: usize) -> bool {
+        level == 0 || self.record(level_pack, level - 1).is_cleared()
+    }
+
+    /// Keeps the better of the old and the new results of `level`, and returns whether any of
+    /// the new ones were better.
+    pub fn clear_level(&mut self, level_pack: &str, level: usize, result: &LevelResult) -> bool {
+        let records = self.packs.entry(level_pack.to_string()).or_default();
+
+        if records.len() <= level {
+            records.resize(level + 1, LevelRecord::default());
+        }
+
+        let record = &mut records[level];
+        let old_record = *record;
+
+        record.best_score = record.best_score.max(result.score);
+        record.stars = record.stars.max(result.stars);
+        record.best_time = Some(
+            record
+                .best_time
+                .map_or(result.stats.time, |time| time.min(result.stats.time)),
+        );
+        record.best_combo = record.best_combo.max(result.stats.best_combo);
+
+        *record != old_record
+    }
+}
+
+/// Runs before the [`Run`] takes the score back from the paddle, while it still holds the score
+/// the level started with.
+#[allow(clippy::too_many_arguments)]
+fn rate_cleared_level(
+    paddle_query: Query<&Score, With<Paddle>>,
+    run: Res<Run>,
+    level_stats: Res<LevelStats>,
+    current_level: Res<CurrentLevel>,
+    level_assets: Res<LevelAssets>,
+    levels: Res<Assets<LevelAsset>>,
+    input_source: Res<InputSource>,
+    settings: Res<ArkanoidSettings>,
+    mut progress: ResMut<Progress>,
+    mut level_result: ResMut<LevelResult>,
+) {
+    let score = paddle_query
+        .get_single()
+        .map_or(0, |score| score.0.saturating_sub(run.score));
+
+    let stars = match run.mode {
+        GameMode::Daily { seed, .. } => daily_level(seed).rate(score, &level_stats.0),
+        _ => levels
+            .get(&level_assets.levels[current_level.0])
+            .map_or(1, |level| level.rate(score, &level_stats.0)),
+    };
+
+    *level_result = LevelResult {
+        score,
+        stars,
+        stats: level_stats.0,
+        new_best: false,
+    };
+
+    // Replays that are played back were already counted when they were recorded, and daily
+    // levels aren't part of the level pack.
+    if *input_source != InputSource::Devices || !run.mode.uses_level_pack() {
+        return;
+    }
+
+    level_result.new_best =
+        progress.clear_level(&settings.level_folder, current_level.0, &level_result);
+    progress.save();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clearing_a_level_unlocks_the_next() {
+        let mut progress = Progress::default();
+
+        assert!(progress.is_unlocked("levels", 0));
+        assert!(!progress.is_unlocked("levels", 1));
+
+        let result = LevelResult {
+            score: 500,
+            stars: 1,
+            stats: Stats {
+                best_combo: 4,
+                time: Duration::from_secs(60),
+                ..default()
+            },
+            new_best: false,
+        };
+
+        assert!(progress.clear_level("levels", 0, &result));
+
+        assert!(progress.is_unlocked("levels", 1));
+        assert!(!progress.is_unlocked("levels", 2));
+        assert!(!progress.is_unlocked("other", 1));
+
+        assert!(!progress.clear_level(
+            "levels",
+            0,
+            &LevelResult {
+                score: 300,
+                ..result
+            }
+        ));
+        assert!(progress.clear_level(
+            "levels",
+            0,
+            &LevelResult {
+                score: 300,
+                stars: 2,
+                ..result
+            }
+        ));
+
+        assert_eq!(
+            progress.record("levels", 0),
+            LevelRecord {
+                best_score: 500,
+                stars: 2,
+                best_time: Some(Duration::from_secs(60)),
+                best_combo: 4,
+            }
+        );
+    }
+}
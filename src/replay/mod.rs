@@ -0,0 +1,632 @@
+//! Recording the player's input and playing it back.
+//!
+//! Every level is recorded frame by frame: the [`Actions`], the cursor, the
+//! [`InputEvent::PrimaryAction`] and how long the frame took. Together with the level, the seed
+//! of the [`GameRng`] and the settings that change how the game plays that's enough to play the
+//! level again. A replay is played back by feeding
+//! the recorded input to the game in place of the devices and by driving [`Time`] with the
+//! recorded frame times, so every system sees exactly what it saw while the replay was recorded.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{ensure, Context};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    actions::{Actions, ActionsSystem, FollowCursor, InputEvent, InputSource},
+    assets::LevelAssets,
+    block::{ResumedBlocks, SavedBlock},
+    difficulty::Difficulty,
+    level::{CurrentLevel, LevelSystem},
+    paddle::PaddleSpeed,
+    rng::GameRng,
+    run::{GameMode, LevelStart, Run},
+    storage::{self, Directory},
+    ArkanoidSettings, GameState,
+};
+
+pub mod simulation;
+pub mod viewer;
+
+/// Bumped whenever a change to the replay format makes older replays unreadable.
+pub const REPLAY_FORMAT_VERSION: u32 = 1;
+/// Replays only play back exactly on the version of the game that recorded them.
+pub const GAME_VERSION: &str = env!("CARGO_PKG_VERSION");
+/// The folder in the data directory that replays are saved to.
+const REPLAY_FOLDER: &str = "replays";
+const REPLAY_EXTENSION: &str = "replay";
+
+pub struct ReplayPlugin {
+    /// A replay to play back once the game reaches the menu.
+    pub replay: Option<PathBuf>,
+    /// Whether the levels the player plays are saved as replays.
+    pub record: bool,
+}
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        let replay_state = match &self.replay {
+            Some(path) => match Replay::load(path) {
+                Ok(replay) => ReplayState::Pending(replay),
+                Err(error) => {
+                    error!("Couldn't load the replay {}: {error:#}", path.display());
+                    ReplayState::Idle
+                }
+            },
+            None => ReplayState::Idle,
+        };
+
+        app.init_resource::<GameRng>()
+            .insert_resource(replay_state)
+            .insert_resource(RecordReplays(self.record))
+            .add_system_set(SystemSet::on_update(GameState::Menu).with_system(start_playback))
+            .add_system_set(
+                SystemSet::on_exit(GameState::Menu)
+                    .with_system(select_replay_level.after(LevelSystem::Reset)),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::Playing)
+                    .with_system(start_level.label(ReplaySystem::Start)),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(play_frame.label(ActionsSystem::Input))
+                    .with_system(record_frame.after(ActionsSystem::Input)),
+            )
+            .add_system_set(SystemSet::on_exit(GameState::Playing).with_system(finish_level));
+    }
+}
+
+#[derive(SystemLabel, Debug, Hash, PartialEq, Eq, Copy, Clone)]
+pub enum ReplaySystem {
+    /// Seeds the [`GameRng`] for the level. Systems that draw from it on entering
+    /// [`GameState::Playing`] have to run after this. While a replay plays back it also sets the
+    /// [`Difficulty`], the [`PaddleSpeed`], the [`LevelStart`] and the [`ResumedBlocks`] it was
+    /// recorded with.
+    Start,
+}
+
+#[derive(Resource, Copy, Clone, Eq, PartialEq, Debug, Hash)]
+struct RecordReplays(bool);
+
+/// A recording of a single level.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Replay {
+    pub format_version: u32,
+    pub game_version: String,
+    /// The index of the level in the level folder.
+    pub level: usize,
+    /// The folder the level was loaded from, `None` for daily levels, whose blocks are in the
+    /// replay. Older replays don't have it either.
+    #[serde(default)]
+    pub level_folder: Option<String>,
+    /// The seed of the [`GameRng`] when the level started.
+    pub seed: u64,
+    #[serde(default)]
+    pub difficulty: Difficulty,
+    /// The paddle speed when the level started.
+    #[serde(default)]
+    pub paddle_speed: PaddleSpeed,
+    /// The score and the lives the level started with. Older replays don't have them, they
+    /// started every level with the starting lives.
+    #[serde(default)]
+    pub level_start: Option<LevelStart>,
+    /// The blocks the level started with, when it was continued from a saved run or was a daily
+    /// level.
+    #[serde(default)]
+    pub blocks: Option<Vec<SavedBlock>>,
+    pub frames: Vec<ReplayFrame>,
+}
+
+/// The input of a single frame.
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Debug)]
+pub struct ReplayFrame {
+    /// How long the frame took.
+    pub delta: Duration,
+    pub player_movement: Option<f32>,
+    /// The cursor position in arena coordinates, only present on frames it moved in.
+    pub cursor: Option<Vec2>,
+    pub primary_action: bool,
+    /// The keyboard paddle speed, only present on frames it changed in.
+    #[serde(default)]
+    pub paddle_speed: Option<f32>,
+}
+
+impl Replay {
+    pub fn new(
+        level: usize,
+        seed: u64,
+        difficulty: Difficulty,
+        paddle_speed: PaddleSpeed,
+        level_start: LevelStart,
+    ) -> Self {
+        Self {
+            format_version: REPLAY_FORMAT_VERSION,
+            game_version: GAME_VERSION.to_string(),
+            level,
+            level_folder: None,
+            seed,
+            difficulty,
+            paddle_speed,
+            level_start: Some(level_start),
+            blocks: None,
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let bytes = fs::read(path).with_context(|| format!("reading {}", path.display()))?;
+
+        let replay: Replay = serde_json::from_slice(&bytes)?;
+
+        ensure!(
+            replay.format_version == REPLAY_FORMAT_VERSION,
+            "the replay format version is {}, only version {REPLAY_FORMAT_VERSION} is supported",
+            replay.format_version,
+        );
+
+        if replay.game_version != GAME_VERSION {
+            warn!(
+                "The replay was recorded on version {} of the game, it might not play back the same on version {GAME_VERSION}",
+                replay.game_version
+            );
+        }
+
+        Ok(replay)
+    }
+
+    /// The folder the level is loaded from when the replay is played back. Replays without one
+    /// use the folder of the game.
+    pub fn level_folder<'a>(&'a self, settings: &'a ArkanoidSettings) -> &'a str {
+        self.level_folder
+            .as_deref()
+            .unwrap_or(&settings.level_folder)
+    }
+
+    /// Saves the replay to the replay folder in the data directory, named after the current time
+    /// and the level. A replay saved in the same millisecond as another one gets a number.
+    pub fn save(&self) -> anyhow::Result<PathBuf> {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+
+        let folder = Directory::Data.path().map(|dir| dir.join(REPLAY_FOLDER));
+        let mut copy = 0;
+
+        let file_name = loop {
+            let file_name = replay_file_name(timestamp, self.level, copy);
+
+            match &folder {
+                Some(folder) if folder.join(&file_name).exists() => copy += 1,
+                _ => break file_name,
+            }
+        };
+
+        storage::save(
+            Directory::Data,
+            &format!("{REPLAY_FOLDER}/{file_name}"),
+            self,
+        )
+    }
+}
+
+/// The name of a replay saved `timestamp` milliseconds after the Unix epoch. The timestamp comes
+/// first, so the names sort by time.
+fn replay_file_name(timestamp: u128, level: usize, copy: usize) -> String {
+    let level = level + 1;
+
+    if copy == 0 {
+        format!("{timestamp}-level{level}.{REPLAY_EXTENSION}")
+    } else {
+        format!("{timestamp}-level{level}-{copy}.{REPLAY_EXTENSION}")
+    }
+}
+
+/// Lists the saved replays, newest first.
+pub fn saved_replays() -> Vec<PathBuf> {
+    let folder = match Directory::Data.path() {
+        Some(dir) => dir.join(REPLAY_FOLDER),
+        None => return Vec::new(),
+    };
+
+    let entries = match fs::read_dir(&folder) {
+        Ok(entries) => entries,
+        Err(_) => return Vec::new(),
+    };
+
+    let mut replays = entries
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .map_or(false, |ext| ext == REPLAY_EXTENSION)
+        })
+        .collect::<Vec<_>>();
+
+    // The replays are named after the time they were saved at.
+    replays.sort_unstable_by(|a, b| b.cmp(a));
+
+    replays
+}
+
+#[derive(Resource, Default)]
+pub enum ReplayState {
+    #[default]
+    Idle,
+    /// A replay that starts as soon as the game reaches the menu.
+    Pending(Replay),
+    Recording(Replay),
+    Playing(Playback),
+}
+
+pub struct Playback {
+    pub replay: Replay,
+    /// The index of the next frame to play.
+    pub frame: usize,
+    /// The clock driven by the recorded frame times.
+    clock: Time,
+    /// The real clock, put back once the playback ends.
+    real_time: Option<Time>,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Self {
+        let mut clock = Time::default();
+        clock.update();
+
+        Self {
+            replay,
+            frame: 0,
+            clock,
+            real_time: None,
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.frame >= self.replay.frames.len()
+    }
+
+    /// Replaces the real frame time with the one recorded for `frame`.
+    fn apply_clock(&mut self, time: &mut Time, frame: &ReplayFrame) {
+        if self.real_time.is_none() {
+            self.real_time = Some(time.clone());
+        }
+
+        let last_update = self
+            .clock
+            .last_update()
+            .expect("The replay clock was never started!");
+
+        self.clock.update_with_instant(last_update + frame.delta);
+
+        *time = self.clock.clone();
+    }
+
+    /// Puts the real clock back.
+    fn restore_clock(&mut self, time: &mut Time) {
+        if let Some(mut real_time) = self.real_time.take() {
+            // The real clock stood still during the playback. The first update skips over that
+            // time and the second one gives the frame a normal delta.
+            real_time.update();
+            real_time.update();
+
+            *time = real_time;
+        }
+    }
+}
+
+fn start_playback(
+    mut replay_state: ResMut<ReplayState>,
+    mut input_source: ResMut<InputSource>,
+    mut state: ResMut<State<GameState>>,
+    level_assets: Res<LevelAssets>,
+    settings: Res<ArkanoidSettings>,
+) {
+    if !matches!(*replay_state, ReplayState::Pending(_)) {
+        return;
+    }
+
+    let replay = match std::mem::take(replay_state.as_mut()) {
+        ReplayState::Pending(replay) => replay,
+        _ => unreachable!(),
+    };
+
+    if replay.level_folder(&settings) != settings.level_folder {
+        error!(
+            "The replay was recorded on the levels in {}, but the levels are loaded from {}",
+            replay.level_folder(&settings),
+            settings.level_folder
+        );
+        return;
+    }
+
+    if replay.level >= level_assets.levels.len() {
+        error!(
+            "The replay is of level {}, but there are only {} levels",
+            replay.level + 1,
+            level_assets.levels.len()
+        );
+        return;
+    }
+
+    *input_source = InputSource::Replay;
+    *replay_state = ReplayState::Playing(Playback::new(replay));
+
+    let _ = state.set(GameState::Playing);
+}
+
+fn select_replay_level(replay_state: Res<ReplayState>, mut current_level: ResMut<CurrentLevel>) {
+    if let ReplayState::Playing(playback) = replay_state.as_ref() {
+        current_level.0 = playback.replay.level;
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn start_level(
+    mut replay_state: ResMut<ReplayState>,
+    mut rng: ResMut<GameRng>,
+    mut difficulty: ResMut<Difficulty>,
+    mut paddle_speed: ResMut<PaddleSpeed>,
+    mut run: ResMut<Run>,
+    mut resumed_blocks: ResMut<ResumedBlocks>,
+    record_replays: Res<RecordReplays>,
+    current_level: Res<CurrentLevel>,
+    settings: Res<ArkanoidSettings>,
+) {
+    match replay_state.as_ref() {
+        ReplayState::Playing(playback) => {
+            *rng = GameRng::new(playback.replay.seed);
+            *difficulty = playback.replay.difficulty;
+            *paddle_speed = playback.replay.paddle_speed;
+
+            if let Some(level_start) = playback.replay.level_start {
+                run.set_level_start(level_start);
+            }
+
+            resumed_blocks.0 = playback.replay.blocks.clone();
+        }
+        ReplayState::Idle | ReplayState::Recording(_) => {
+            *rng = match run.mode {
+                GameMode::Daily { seed, .. } => GameRng::new(seed),
+                _ => GameRng::from_entropy(),
+            };
+
+            // Replays only hold the input of one player.
+            if record_replays.0 && run.mode.paddle_count() == 1 {
+                *replay_state = ReplayState::Recording(Replay {
+                    level_folder: run
+                        .mode
+                        .uses_level_pack()
+                        .then(|| settings.level_folder.clone()),
+                    blocks: resumed_blocks.0.clone(),
+                    ..Replay::new(
+                        current_level.0,
+                        rng.seed(),
+                        *difficulty,
+                        *paddle_speed,
+                        run.level_start(),
+                    )
+                });
+            }
+        }
+        ReplayState::Pending(_) => {}
+    }
+}
+
+fn play_frame(
+    mut replay_state: ResMut<ReplayState>,
+    mut time: ResMut<Time>,
+    mut actions: ResMut<Actions>,
+    mut paddle_speed: ResMut<PaddleSpeed>,
+    mut input_source: ResMut<InputSource>,
+    mut cursor_query: Query<&mut Transform, With<FollowCursor>>,
+    mut input_events: EventWriter<InputEvent>,
+) {
+    let playback = match replay_state.as_mut() {
+        ReplayState::Playing(playback) => playback,
+        _ => return,
+    };
+
+    let frame = match playback.replay.frames.get(playback.frame) {
+        Some(frame) => *frame,
+        None => {
+            warn!("The replay ran out before the level ended, the playback went out of sync");
+
+            playback.restore_clock(&mut time);
+            *input_source = InputSource::Devices;
+            *replay_state = ReplayState::Idle;
+            return;
+        }
+    };
+
+    playback.frame += 1;
+
+    // Every system that reads the time runs after the input, so the recorded time can be
+    // applied here. The state changes in the middle of the frame, so it couldn't be applied any
+    // earlier on the first frame of the level anyway.
+    playback.apply_clock(&mut time, &frame);
+
+    actions.player_movement = frame.player_movement;
+
+    if let Some(speed) = frame.paddle_speed {
+        paddle_speed.0 = speed;
+    }
+
+    if let Some(cursor) = frame.cursor {
+        for mut transform in cursor_query.iter_mut() {
+            transform.translation = cursor.extend(0.);
+        }
+    }
+
+    if frame.primary_action {
+        input_events.send(InputEvent::PrimaryAction);
+    }
+}
+
+fn record_frame(
+    mut replay_state: ResMut<ReplayState>,
+    time: Res<Time>,
+    actions: Res<Actions>,
+    paddle_speed: Res<PaddleSpeed>,
+    cursor_query: Query<&Transform, (With<FollowCursor>, Changed<Transform>)>,
+    mut input_events: EventReader<InputEvent>,
+) {
+    let primary_action = input_events
+        .iter()
+        .any(|event| *event == InputEvent::PrimaryAction);
+
+    if let ReplayState::Recording(replay) = replay_state.as_mut() {
+        replay.frames.push(ReplayFrame {
+            delta: time.delta(),
+            player_movement: actions.player_movement,
+            cursor: cursor_query
+                .get_single()
+                .ok()
+                .map(|transform| transform.translation.truncate()),
+            primary_action,
+            paddle_speed: paddle_speed.is_changed().then_some(paddle_speed.0),
+        });
+    }
+}
+
+fn finish_level(
+    mut replay_state: ResMut<ReplayState>,
+    mut time: ResMut<Time>,
+    mut input_source: ResMut<InputSource>,
+) {
+    match replay_state.as_mut() {
+        ReplayState::Recording(replay) => match replay.save() {
+            Ok(path) => info!("Saved the replay to {}", path.display()),
+            Err(error) => warn!("Couldn't save the replay: {error:#}"),
+        },
+        ReplayState::Playing(playback) => {
+            if !playback.is_finished() {
+                warn!(
+                    "The level ended with {} frames of the replay left, the playback went out of sync",
+                    playback.replay.frames.len() - playback.frame
+                );
+            }
+
+            playback.restore_clock(&mut time);
+            *input_source = InputSource::Devices;
+        }
+        ReplayState::Idle | ReplayState::Pending(_) => return,
+    }
+
+    *replay_state = ReplayState::Idle;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(player_movement: Option<f32>, primary_action: bool) -> ReplayFrame {
+        ReplayFrame {
+            delta: Duration::from_millis(16),
+            player_movement,
+            cursor: None,
+            primary_action,
+            paddle_speed: None,
+        }
+    }
+
+    #[test]
+    fn replays_are_named_after_the_time_and_the_level() {
+        assert_eq!(
+            replay_file_name(1_700_000_000_123, 2, 0),
+            "1700000000123-level3.replay"
+        );
+        assert_eq!(
+            replay_file_name(1_700_000_000_123, 2, 1),
+            "1700000000123-level3-1.replay"
+        );
+    }
+
+    #[test]
+    fn recording_keeps_paddle_speed_changes() {
+        let mut world = World::new();
+
+        world.insert_resource(ReplayState::Recording(Replay::new(
+            0,
+            42,
+            Difficulty::default(),
+            PaddleSpeed::default(),
+            Run::default().level_start(),
+        )));
+        world.init_resource::<Time>();
+        world.init_resource::<Actions>();
+        world.init_resource::<PaddleSpeed>();
+        world.init_resource::<Events<InputEvent>>();
+
+        let mut update_stage = SystemStage::parallel();
+
+        update_stage.add_system(record_frame);
+
+        update_stage.run(&mut world);
+
+        // The speed was changed from the pause menu in the middle of the level.
+        world.resource_mut::<PaddleSpeed>().0 = 300.;
+
+        update_stage.run(&mut world);
+        update_stage.run(&mut world);
+
+        match world.resource::<ReplayState>() {
+            ReplayState::Recording(replay) => {
+                let speeds: Vec<_> = replay
+                    .frames
+                    .iter()
+                    .map(|frame| frame.paddle_speed)
+                    .collect();
+
+                assert_eq!(speeds[1..], [Some(300.), None]);
+            }
+            _ => panic!("The recording stopped on its own!"),
+        }
+    }
+
+    #[test]
+    fn playback_feeds_recorded_input() {
+        let mut world = World::new();
+
+        let mut replay = Replay::new(
+            0,
+            42,
+            Difficulty::default(),
+            PaddleSpeed::default(),
+            Run::default().level_start(),
+        );
+        replay.frames = vec![frame(Some(1.), false), frame(None, true)];
+
+        world.insert_resource(ReplayState::Playing(Playback::new(replay)));
+        world.init_resource::<Time>();
+        world.init_resource::<Actions>();
+        world.init_resource::<PaddleSpeed>();
+        world.init_resource::<InputSource>();
+        world.init_resource::<Events<InputEvent>>();
+
+        let mut update_stage = SystemStage::parallel();
+
+        update_stage.add_system(play_frame);
+
+        update_stage.run(&mut world);
+
+        assert_eq!(world.resource::<Actions>().player_movement, Some(1.));
+        assert_eq!(world.resource::<Time>().delta(), Duration::from_millis(16));
+        assert!(world.resource::<Events<InputEvent>>().is_empty());
+
+        update_stage.run(&mut world);
+
+        assert_eq!(world.resource::<Actions>().player_movement, None);
+        assert_eq!(world.resource::<Events<InputEvent>>().len(), 1);
+
+        match world.resource::<ReplayState>() {
+            ReplayState::Playing(playback) => assert!(playback.is_finished()),
+            _ => panic!("The playback stopped on its own!"),
+        }
+    }
+}
@@ -0,0 +1,399 @@
+//! Plays a replay in a headless copy of the game, separate from the game on the screen.
+//!
+//! Running the replay in its own [`App`] lets it go at any speed: every update of the simulation
+//! plays exactly one recorded frame, however many updates the viewer asks for in a single frame of
+//! its own. Seeking backwards restores the last snapshot before the target and plays the frames
+//! from there on.
+//!
+//! The simulation loads its level over several frames of the game, see
+//! [`ReplaySimulation::load`], so opening a replay doesn't freeze the screen.
+
+use std::{collections::BTreeMap, time::Duration};
+
+use anyhow::bail;
+use bevy::prelude::*;
+
+use crate::{
+    actions::{Actions, FollowCursor},
+    assets::TextureAssets,
+    ball::{Ball, BallBundle},
+    block::{Block, BlockBundle, BLOCK_TEXTURE_SIZE},
+    boost::{Boost, BoostBundle},
+    lives::Lives,
+    paddle::{Paddle, PaddleSpeed},
+    rng::GameRng,
+    run::LevelStats,
+    score::{Combo, Score},
+    time_attack::LevelClock,
+    ArkanoidSettings, GameState, HeadlessArkanoidPlugin,
+};
+
+use super::{Replay, ReplayState};
+
+/// How many frames apart the snapshots are taken.
+pub const SNAPSHOT_INTERVAL: usize = 300;
+/// How many frames of the game the simulation gets to load its level before giving up.
+const MAX_LOADING_UPDATES: usize = 1000;
+
+pub struct ReplaySimulation {
+    app: App,
+    replay: Replay,
+    settings: ArkanoidSettings,
+    /// When each frame of the replay ends, measured from the start of the level.
+    frame_ends: Vec<Duration>,
+    snapshots: BTreeMap<usize, Snapshot>,
+    /// How many updates the level has been loading for, `None` once it's loaded.
+    loading_updates: Option<usize>,
+    /// The frame to seek to once the level is loaded again.
+    pending_seek: Option<usize>,
+}
+
+impl ReplaySimulation {
+    /// Builds the game the replay is played in. It's loaded with [`Self::load`].
+    pub fn new(replay: Replay, settings: &ArkanoidSettings) -> Self {
+        let settings = ArkanoidSettings {
+            window: None,
+            audio: false,
+            replay: None,
+            record_replays: false,
+            level_folder: replay.level_folder(settings).to_string(),
+            ..settings.clone()
+        };
+
+        let frame_ends = replay
+            .frames
+            .iter()
+            .scan(Duration::ZERO, |end, frame| {
+                *end += frame.delta;
+                Some(*end)
+            })
+            .collect();
+
+        Self {
+            app: start(&replay, &settings),
+            replay,
+            settings,
+            frame_ends,
+            snapshots: BTreeMap::new(),
+            loading_updates: Some(0),
+            pending_seek: None,
+        }
+    }
+
+    /// Runs one update of the loading simulation, and returns whether the level is loaded. Call
+    /// it once a frame until it is, the levels are loaded in the background.
+    pub fn load(&mut self) -> anyhow::Result<bool> {
+        let updates = match self.loading_updates {
+            Some(updates) => updates,
+            None => return Ok(true),
+        };
+
+        if updates >= MAX_LOADING_UPDATES {
+            bail!("the level took too long to load");
+        }
+
+        self.app.update();
+        self.loading_updates = Some(updates + 1);
+
+        let playing =
+            *self.app.world.resource::<State<GameState>>().current() == GameState::Playing;
+
+        match self.app.world.resource::<ReplayState>() {
+            ReplayState::Playing(_) if playing => {}
+            ReplayState::Idle => bail!("the replay couldn't be started"),
+            _ => return Ok(false),
+        }
+
+        self.loading_updates = None;
+
+        if self.snapshots.is_empty() {
+            self.capture_snapshot();
+        }
+
+        if let Some(frame) = self.pending_seek.take() {
+            self.seek(frame);
+        }
+
+        Ok(true)
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.loading_updates.is_none()
+    }
+
+    pub fn replay(&self) -> &Replay {
+        &self.replay
+    }
+
+    /// The world the replay is played in.
+    pub fn world(&mut self) -> &mut World {
+        &mut self.app.world
+    }
+
+    /// The number of frames in the replay.
+    pub fn len(&self) -> usize {
+        self.replay.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.replay.frames.is_empty()
+    }
+
+    /// The index of the next frame to play.
+    pub fn frame(&self) -> usize {
+        match self.app.world.resource::<ReplayState>() {
+            ReplayState::Playing(playback) => playback.frame,
+            _ if !self.is_loaded() => self.pending_seek.unwrap_or(0),
+            _ => self.len(),
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.frame() >= self.len()
+    }
+
+    /// How far into the level the simulation is.
+    pub fn elapsed(&self) -> Duration {
+        self.start_of(self.frame())
+    }
+
+    /// How long the whole replay is.
+    pub fn duration(&self) -> Duration {
+        self.frame_ends.last().copied().unwrap_or_default()
+    }
+
+    /// When `frame` starts, measured from the start of the level.
+    pub fn start_of(&self, frame: usize) -> Duration {
+        match frame.min(self.len()).checked_sub(1) {
+            Some(previous) => self.frame_ends[previous],
+            None => Duration::ZERO,
+        }
+    }
+
+    /// The frame that's playing `time` into the level.
+    pub fn frame_at(&self, time: Duration) -> usize {
+        self.frame_ends.partition_point(|end| *end <= time)
+    }
+
+    /// Plays the next frame.
+    pub fn step(&mut self) {
+        if !self.is_loaded() || self.is_finished() {
+            return;
+        }
+
+        self.app.update();
+
+        let frame = self.frame();
+
+        if frame % SNAPSHOT_INTERVAL == 0 && !self.snapshots.contains_key(&frame) {
+            self.capture_snapshot();
+        }
+    }
+
+    /// Plays every frame that ends before `time` into the level.
+    pub fn advance_to(&mut self, time: Duration) {
+        while self.is_loaded() && !self.is_finished() && self.frame_ends[self.frame()] <= time {
+            self.step();
+        }
+    }
+
+    /// Moves the simulation to the start of `frame`. While the level is loading, it moves there
+    /// once it's loaded.
+    pub fn seek(&mut self, frame: usize) {
+        if !self.is_loaded() {
+            self.pending_seek = Some(frame);
+            return;
+        }
+
+        let first_snapshot = *self
+            .snapshots
+            .keys()
+            .next()
+            .expect("The first snapshot is taken when the simulation starts!");
+
+        let frame = frame.clamp(first_snapshot, self.len());
+        let current = self.frame();
+
+        let closer_snapshot = self
+            .snapshots
+            .range(..=frame)
+            .next_back()
+            .filter(|(snapshot_frame, _)| **snapshot_frame > current);
+
+        if frame < current || closer_snapshot.is_some() {
+            // The level only ends early when the playback went out of sync, there's nothing to
+            // restore the snapshot into anymore. The level is loaded again first.
+            if *self.app.world.resource::<State<GameState>>().current() != GameState::Playing {
+                self.app = start(&self.replay, &self.settings);
+                self.loading_updates = Some(0);
+                self.pending_seek = Some(frame);
+                return;
+            }
+
+            let (_, snapshot) = self
+                .snapshots
+                .range(..=frame)
+                .next_back()
+                .expect("There is a snapshot before every frame that can be seeked to!");
+
+            snapshot.restore(&mut self.app.world);
+        }
+
+        while self.frame() < frame {
+            self.step();
+        }
+    }
+
+    fn capture_snapshot(&mut self) {
+        if let Some(snapshot) = Snapshot::capture(&mut self.app.world) {
+            self.snapshots.insert(snapshot.frame, snapshot);
+        }
+    }
+}
+
+/// Builds a headless game that starts the replay as soon as its level is loaded.
+fn start(replay: &Replay, settings: &ArkanoidSettings) -> App {
+    let mut app = App::new();
+
+    app.add_plugin(HeadlessArkanoidPlugin {
+        settings: settings.clone(),
+    });
+
+    *app.world.resource_mut::<ReplayState>() = ReplayState::Pending(replay.clone());
+
+    app
+}
+
+/// Everything that changes while a level is played.
+struct Snapshot {
+    frame: usize,
+    clock: Time,
+    rng: GameRng,
+    player_movement: Option<f32>,
+    paddle_speed: PaddleSpeed,
+    level_stats: LevelStats,
+    /// Only time attack runs keep a level clock.
+    level_clock: Option<LevelClock>,
+    cursor: Option<Transform>,
+    paddle: Option<(Transform, Lives, Score, Combo)>,
+    balls: Vec<(Ball, Transform)>,
+    blocks: Vec<(Block, Transform)>,
+    boosts: Vec<(Boost, Transform)>,
+}
+
+impl Snapshot {
+    fn capture(world: &mut World) -> Option<Self> {
+        let (frame, clock) = match world.resource::<ReplayState>() {
+            ReplayState::Playing(playback) => (playback.frame, playback.clock.clone()),
+            _ => return None,
+        };
+
+        Some(Self {
+            frame,
+            clock,
+            rng: world.resource::<GameRng>().clone(),
+            player_movement: world.resource::<Actions>().player_movement,
+            paddle_speed: *world.resource::<PaddleSpeed>(),
+            level_stats: *world.resource::<LevelStats>(),
+            level_clock: world.get_resource::<LevelClock>().copied(),
+            cursor: world
+                .query_filtered::<&Transform, With<FollowCursor>>()
+                .iter(world)
+                .next()
+                .copied(),
+            paddle: world
+                .query_filtered::<(&Transform, &Lives, &Score, &Combo), With<Paddle>>()
+                .iter(world)
+                .next()
+                .map(|(transform, lives, score, combo)| {
+                    (*transform, *lives, score.clone(), *combo)
+                }),
+            balls: world
+                .query::<(&Ball, &Transform)>()
+                .iter(world)
+                .map(|(ball, transform)| (*ball, *transform))
+                .collect(),
+            blocks: world
+                .query::<(&Block, &Transform)>()
+                .iter(world)
+                .map(|(block, transform)| (*block, *transform))
+                .collect(),
+            boosts: world
+                .query::<(&Boost, &Transform)>()
+                .iter(world)
+                .map(|(boost, transform)| (*boost, *transform))
+                .collect(),
+        })
+    }
+
+    fn restore(&self, world: &mut World) {
+        if let ReplayState::Playing(playback) = world.resource_mut::<ReplayState>().as_mut() {
+            playback.frame = self.frame;
+            playback.clock = self.clock.clone();
+        }
+
+        *world.resource_mut::<GameRng>() = self.rng.clone();
+        world.resource_mut::<Actions>().player_movement = self.player_movement;
+        *world.resource_mut::<PaddleSpeed>() = self.paddle_speed;
+        *world.resource_mut::<LevelStats>() = self.level_stats;
+
+        if let Some(level_clock) = self.level_clock {
+            world.insert_resource(level_clock);
+        }
+
+        if let Some(cursor) = self.cursor {
+            for mut transform in world
+                .query_filtered::<&mut Transform, With<FollowCursor>>()
+                .iter_mut(world)
+            {
+                // The paddle jumps to the cursor when it moves, putting it back mustn't count.
+                *transform.bypass_change_detection() = cursor;
+            }
+        }
+
+        if let Some((paddle_transform, paddle_lives, paddle_score, paddle_combo)) = &self.paddle {
+            for (mut transform, mut lives, mut score, mut combo) in world
+                .query_filtered::<(&mut Transform, &mut Lives, &mut Score, &mut Combo), With<Paddle>>()
+                .iter_mut(world)
+            {
+                *transform = *paddle_transform;
+                *lives = *paddle_lives;
+                *score = paddle_score.clone();
+                *combo = *paddle_combo;
+            }
+        }
+
+        // Balls, blocks and boosts come and go during a level, so they're spawned again.
+        let stale = world
+            .query_filtered::<Entity, Or<(With<Ball>, With<Block>, With<Boost>)>>()
+            .iter(world)
+            .collect::<Vec<_>>();
+
+        for entity in stale {
+            despawn_with_children_recursive(world, entity);
+        }
+
+        let textures = world.resource::<TextureAssets>();
+        let ball_texture = textures.ball.clone();
+        let block_texture = textures.block.clone();
+
+        for (ball, transform) in &self.balls {
+            world.spawn(BallBundle::new(*ball, *transform, ball_texture.clone()));
+        }
+
+        for (block, transform) in &self.blocks {
+            world.spawn(
+                BlockBundle::new(
+                    block.block_type,
+                    &(BLOCK_TEXTURE_SIZE / 2.),
+                    block_texture.clone(),
+                )
+                .with_pos(transform.translation.truncate()),
+            );
+        }
+
+        for (boost, transform) in &self.boosts {
+            world.spawn(BoostBundle::new(*boost, *transform));
+        }
+    }
+}
@@ -0,0 +1,401 @@
+//! Watching replays and racing against them.
+
+use std::time::Duration;
+
+use bevy::{prelude::*, utils::HashMap};
+
+use crate::{
+    arena::Arena,
+    assets::TextureAssets,
+    ball::Ball,
+    block::{Block, Palette},
+    boost::Boost,
+    paddle::Paddle,
+    util::cleanup,
+    GameState,
+};
+
+use super::simulation::ReplaySimulation;
+
+pub const MIN_SPEED: f32 = 0.25;
+pub const MAX_SPEED: f32 = 4.;
+/// How far the left and right arrows seek.
+const SEEK_STEP: Duration = Duration::from_secs(5);
+/// How opaque the ghost paddle and ball are.
+const GHOST_ALPHA: f32 = 0.35;
+
+pub struct ReplayViewerPlugin;
+
+impl Plugin for ReplayViewerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_non_send_resource::<ReplayViewer>()
+            .init_non_send_resource::<Ghost>()
+            .add_system_set(
+                SystemSet::on_update(GameState::ReplayViewer)
+                    .with_system(load_viewer.label(ViewerSystem::Load))
+                    .with_system(
+                        viewer_controls
+                            .label(ViewerSystem::Controls)
+                            .after(ViewerSystem::Load),
+                    )
+                    .with_system(
+                        advance_viewer
+                            .label(ViewerSystem::Advance)
+                            .after(ViewerSystem::Controls),
+                    )
+                    .with_system(mirror_viewer.after(ViewerSystem::Advance)),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::ReplayViewer)
+                    .with_system(close_viewer)
+                    .with_system(cleanup::<Mirror>),
+            )
+            .add_system_set(SystemSet::on_update(GameState::Playing).with_system(advance_ghost))
+            .add_system_set(
+                SystemSet::on_exit(GameState::Playing)
+                    .with_system(remove_ghost)
+                    .with_system(cleanup::<Mirror>),
+            );
+    }
+}
+
+#[derive(SystemLabel, Debug, Hash, PartialEq, Eq, Copy, Clone)]
+pub enum ViewerSystem {
+    /// Loads the level of the replay, see [`ReplaySimulation::load`].
+    Load,
+    Controls,
+    /// Plays the frames of the replay that are due.
+    Advance,
+}
+
+/// The replay shown in [`GameState::ReplayViewer`].
+pub struct ReplayViewer {
+    pub simulation: Option<ReplaySimulation>,
+    /// How far into the replay the viewer is.
+    pub time: Duration,
+    pub speed: f32,
+    pub paused: bool,
+}
+
+impl Default for ReplayViewer {
+    fn default() -> Self {
+        Self {
+            simulation: None,
+            time: Duration::ZERO,
+            speed: 1.,
+            paused: false,
+        }
+    }
+}
+
+impl ReplayViewer {
+    pub fn open(&mut self, simulation: ReplaySimulation) {
+        *self = Self {
+            time: simulation.elapsed(),
+            simulation: Some(simulation),
+            ..default()
+        };
+    }
+
+    fn seek(&mut self, frame: usize) {
+        if let Some(simulation) = &mut self.simulation {
+            simulation.seek(frame);
+            self.time = simulation.elapsed();
+        }
+    }
+}
+
+/// A replay of the level being played, shown as a translucent paddle and ball.
+#[derive(Default)]
+pub struct Ghost {
+    pub simulation: Option<ReplaySimulation>,
+    /// How long the level has been played for.
+    time: Duration,
+}
+
+impl Ghost {
+    pub fn new(simulation: ReplaySimulation) -> Self {
+        Self {
+            simulation: Some(simulation),
+            time: Duration::ZERO,
+        }
+    }
+}
+
+/// Shows an entity of a [`ReplaySimulation`].
+#[derive(Component, Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct Mirror {
+    source: Entity,
+}
+
+/// Runs an update of the simulation a frame until its level is loaded. A replay that can't be
+/// loaded is closed, which goes back to the replay list.
+fn load_viewer(mut viewer: NonSendMut<ReplayViewer>) {
+    let viewer = viewer.as_mut();
+
+    if let Some(simulation) = &mut viewer.simulation {
+        match simulation.load() {
+            Ok(true) => viewer.time = viewer.time.max(simulation.elapsed()),
+            Ok(false) => {}
+            Err(error) => {
+                error!("Couldn't load the replay: {error:#}");
+                viewer.simulation = None;
+            }
+        }
+    }
+}
+
+/// Space pauses, up and down change the speed, left and right seek, comma and period step
+/// through single frames, home goes back to the start and escape goes back to the replay list.
+fn viewer_controls(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut viewer: NonSendMut<ReplayViewer>,
+    mut state: ResMut<State<GameState>>,
+) {
+    let frame = match &viewer.simulation {
+        Some(simulation) => simulation.frame(),
+        None => {
+            let _ = state.set(GameState::Replays);
+            return;
+        }
+    };
+
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        let _ = state.set(GameState::Replays);
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Space) {
+        viewer.paused = !viewer.paused;
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Up) {
+        viewer.speed = (viewer.speed * 2.).min(MAX_SPEED);
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Down) {
+        viewer.speed = (viewer.speed / 2.).max(MIN_SPEED);
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Period) {
+        viewer.paused = true;
+        viewer.seek(frame + 1);
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Comma) {
+        viewer.paused = true;
+        viewer.seek(frame.saturating_sub(1));
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Home) {
+        viewer.seek(0);
+    }
+
+    let seek_target = if keyboard_input.just_pressed(KeyCode::Right) {
+        Some(viewer.time + SEEK_STEP)
+    } else if keyboard_input.just_pressed(KeyCode::Left) {
+        Some(viewer.time.saturating_sub(SEEK_STEP))
+    } else {
+        None
+    };
+
+    if let Some(time) = seek_target {
+        let frame = viewer
+            .simulation
+            .as_ref()
+            .map_or(0, |simulation| simulation.frame_at(time));
+
+        viewer.seek(frame);
+    }
+}
+
+fn advance_viewer(time: Res<Time>, mut viewer: NonSendMut<ReplayViewer>) {
+    if viewer.paused {
+        return;
+    }
+
+    let viewer = viewer.as_mut();
+
+    if let Some(simulation) = &mut viewer.simulation {
+        if !simulation.is_loaded() {
+            return;
+        }
+
+        viewer.time += time.delta().mul_f32(viewer.speed);
+
+        simulation.advance_to(viewer.time);
+
+        if simulation.is_finished() {
+            viewer.time = simulation.elapsed();
+        }
+    }
+}
+
+fn mirror_viewer(
+    mut commands: Commands,
+    mut viewer: NonSendMut<ReplayViewer>,
+    mut arena: ResMut<Arena>,
+    textures: Res<TextureAssets>,
+    palette: Res<Palette>,
+    mut mirror_query: Query<(Entity, &Mirror, &mut Transform, &mut Sprite)>,
+) {
+    let simulation = match &mut viewer.simulation {
+        Some(simulation) => simulation,
+        None => return,
+    };
+
+    let simulated_arena = *simulation.world().resource::<Arena>();
+
+    if *arena != simulated_arena {
+        *arena = simulated_arena;
+    }
+
+    sync_mirrors(
+        &mut commands,
+        simulation.world(),
+        &textures,
+        *palette,
+        &mut mirror_query,
+        false,
+    );
+}
+
+fn close_viewer(mut viewer: NonSendMut<ReplayViewer>) {
+    *viewer = ReplayViewer::default();
+}
+
+/// Keeps the ghost as far into the level as the player is. It catches up once its level is
+/// loaded, and is removed if it can't be.
+fn advance_ghost(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut ghost: NonSendMut<Ghost>,
+    textures: Res<TextureAssets>,
+    palette: Res<Palette>,
+    mut mirror_query: Query<(Entity, &Mirror, &mut Transform, &mut Sprite)>,
+) {
+    let ghost = ghost.as_mut();
+
+    if let Some(simulation) = &mut ghost.simulation {
+        ghost.time += time.delta();
+
+        if let Err(error) = simulation.load() {
+            error!("Couldn't load the ghost: {error:#}");
+            ghost.simulation = None;
+            return;
+        }
+
+        simulation.advance_to(ghost.time);
+
+        sync_mirrors(
+            &mut commands,
+            simulation.world(),
+            &textures,
+            *palette,
+            &mut mirror_query,
+            true,
+        );
+    }
+}
+
+fn remove_ghost(mut ghost: NonSendMut<Ghost>) {
+    *ghost = Ghost::default();
+}
+
+/// Spawns, moves and despawns the mirrors so they match the simulated world. Ghosts only show the
+/// paddle and the ball.
+fn sync_mirrors(
+    commands: &mut Commands,
+    world: &mut World,
+    textures: &TextureAssets,
+    palette: Palette,
+    mirror_query: &mut Query<(Entity, &Mirror, &mut Transform, &mut Sprite)>,
+    ghost: bool,
+) {
+    let alpha = if ghost { GHOST_ALPHA } else { 1. };
+    let tint = |mut color: Color| *color.set_a(alpha);
+
+    // Boosts are drawn without an image, at a custom size.
+    let mut sources = HashMap::<Entity, (Transform, Color, Handle<Image>, Option<Vec2>)>::default();
+
+    for (entity, transform) in world
+        .query_filtered::<(Entity, &Transform), With<Paddle>>()
+        .iter(world)
+    {
+        sources.insert(
+            entity,
+            (
+                *transform,
+                tint(Color::WHITE),
+                textures.paddle.clone(),
+                None,
+            ),
+        );
+    }
+
+    for (entity, transform) in world
+        .query_filtered::<(Entity, &Transform), With<Ball>>()
+        .iter(world)
+    {
+        sources.insert(
+            entity,
+            (*transform, tint(Color::WHITE), textures.ball.clone(), None),
+        );
+    }
+
+    if !ghost {
+        for (entity, block, transform) in world.query::<(Entity, &Block, &Transform)>().iter(world)
+        {
+            sources.insert(
+                entity,
+                (
+                    *transform,
+                    tint(palette.color(block.block_type)),
+                    textures.block.clone(),
+                    None,
+                ),
+            );
+        }
+
+        for (entity, transform, sprite) in world
+            .query_filtered::<(Entity, &Transform, &Sprite), With<Boost>>()
+            .iter(world)
+        {
+            sources.insert(
+                entity,
+                (
+                    *transform,
+                    sprite.color,
+                    Handle::default(),
+                    sprite.custom_size,
+                ),
+            );
+        }
+    }
+
+    for (entity, mirror, mut transform, mut sprite) in mirror_query.iter_mut() {
+        match sources.remove(&mirror.source) {
+            Some((source_transform, color, ..)) => {
+                *transform = source_transform;
+                sprite.color = color;
+            }
+            None => commands.entity(entity).despawn_recursive(),
+        }
+    }
+
+    for (source, (transform, color, texture, custom_size)) in sources {
+        commands.spawn((
+            Mirror { source },
+            Name::new("Mirror"),
+            SpriteBundle {
+                sprite: Sprite {
+                    color,
+                    custom_size,
+                    ..default()
+                },
+                transform,
+                texture,
+                ..default()
+            },
+        ));
+    }
+}
@@ -0,0 +1,57 @@
+//! Randomness that can be reproduced from a seed.
+
+use std::ops::{Deref, DerefMut};
+
+use bevy::prelude::*;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+/// The source of every random decision the game logic makes.
+///
+/// Gameplay code has to draw from this instead of `rand::thread_rng`, otherwise replays can't
+/// reproduce the session. ChaCha is used because, unlike `StdRng`, its output is guaranteed not to
+/// change between versions of `rand`.
+#[derive(Resource, Clone, Debug)]
+pub struct GameRng {
+    seed: u64,
+    rng: ChaCha8Rng,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+
+    /// Creates a generator with a random seed.
+    pub fn from_entropy() -> Self {
+        Self::new(rand::random())
+    }
+
+    /// The seed the generator was created with.
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        Self::from_entropy()
+    }
+}
+
+impl Deref for GameRng {
+    type Target = ChaCha8Rng;
+
+    fn deref(&self) -> &Self::Target {
+        &self.rng
+    }
+}
+
+impl DerefMut for GameRng {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.rng
+    }
+}
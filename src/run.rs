@@ -0,0 +1,304 @@
+//! A run through the levels, from the menu until the game is over.
+//!
+//! The paddle is spawned anew for every level. It starts with the score and the lives the [`Run`]
+//! kept from the level before, and gives them back once the level ends.
+
+use std::{ops::AddAssign, time::Duration};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    difficulty::Difficulty, lives::Lives, paddle::Paddle, replay::ReplaySystem, score::Score,
+    settings::Settings, ArkanoidSettings, GameState,
+};
+
+pub struct RunPlugin;
+
+impl Plugin for RunPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Run>()
+            .init_resource::<NextRunMode>()
+            .init_resource::<LevelStats>()
+            .add_system_set(
+                SystemSet::on_exit(GameState::Menu)
+                    .with_system(start_run::<true>.label(RunSystem::Start)),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::LevelSelect)
+                    .with_system(start_run::<true>.label(RunSystem::Start)),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Daily)
+                    .with_system(start_run::<true>.label(RunSystem::Start)),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Replays)
+                    .with_system(start_run::<false>.label(RunSystem::Start)),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::GameOver)
+                    .with_system(restart_run.label(RunSystem::Start)),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::Playing)
+                    .with_system(start_level.before(ReplaySystem::Start)),
+            )
+            .add_system_set(SystemSet::on_update(GameState::Playing).with_system(track_time))
+            .add_system_set(
+                SystemSet::on_enter(GameState::Win)
+                    .with_system(finish_level.label(RunSystem::Finish)),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::GameOver)
+                    .with_system(finish_level.label(RunSystem::Finish)),
+            );
+    }
+}
+
+#[derive(SystemLabel, Debug, Hash, PartialEq, Eq, Copy, Clone)]
+pub enum RunSystem {
+    /// Starts a new [`Run`] on leaving the screens a run can be started from.
+    Start,
+    /// Takes the score and the lives back from the paddle once a level ends.
+    Finish,
+}
+
+/// What happened in a level, or in every level of a run.
+#[derive(Serialize, Deserialize, Copy, Clone, Default, Eq, PartialEq, Debug, Hash)]
+#[serde(default)]
+pub struct Stats {
+    pub blocks_broken: u32,
+    /// The most blocks broken in a row.
+    pub best_combo: u32,
+    pub lives_lost: u32,
+    pub power_ups_collected: u32,
+    /// The time spent playing, without the time spent paused.
+    pub time: Duration,
+}
+
+impl AddAssign for Stats {
+    fn add_assign(&mut self, other: Self) {
+        self.blocks_broken += other.blocks_broken;
+        self.best_combo = self.best_combo.max(other.best_combo);
+        self.lives_lost += other.lives_lost;
+        self.power_ups_collected += other.power_ups_collected;
+        self.time += other.time;
+    }
+}
+
+/// The [`Stats`] of the level that's being played. They only count towards the [`Run`] once
+/// the level ends, so restarting a level forgets them.
+#[derive(Resource, Copy, Clone, Default, Eq, PartialEq, Debug, Hash, Deref, DerefMut)]
+pub struct LevelStats(pub Stats);
+
+/// How a run is played.
+#[derive(Serialize, Deserialize, Default, Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum GameMode {
+    /// Any level on, for the score.
+    #[default]
+    Classic,
+    /// Every level from the first, against the clock, see [`crate::time_attack`].
+    TimeAttack,
+    /// A single level made from the seed of a day, see [`crate::daily`].
+    Daily {
+        seed: u64,
+        /// Whether this is the day's one attempt that goes into the history, rather than
+        /// practice.
+        scored: bool,
+    },
+    /// Two players taking turns, see [`crate::hot_seat`].
+    HotSeat,
+    /// Two players with a paddle each, sharing the ball, the score and the lives.
+    CoOp,
+    /// Two players against each other, one paddle at the bottom and a flipped one at the top.
+    /// Each one guards their own edge, with their own score and lives.
+    Versus,
+}
+
+impl GameMode {
+    /// Whether the run goes through the levels of the level pack.
+    pub fn uses_level_pack(self) -> bool {
+        !matches!(self, GameMode::Daily { .. })
+    }
+
+    /// How many paddles are in play at once.
+    pub fn paddle_count(self) -> usize {
+        match self {
+            GameMode::CoOp | GameMode::Versus => 2,
+            _ => 1,
+        }
+    }
+}
+
+/// The mode of the run that starts next, picked before leaving the menu. It goes back to
+/// [`GameMode::Classic`] once the run starts.
+#[derive(Resource, Copy, Clone, Default, Eq, PartialEq, Debug, Hash)]
+pub struct NextRunMode(pub GameMode);
+
+/// What a level starts with.
+#[derive(Serialize, Deserialize, Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct LevelStart {
+    pub score: u32,
+    pub lives: u32,
+}
+
+/// The score, the lives and the stats carried from one level to the next. A new run starts
+/// whenever the game starts from the menu, the replay list or the game over screen.
+#[derive(Resource, Serialize, Deserialize, Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct Run {
+    /// The score at the start of the current level.
+    pub score: u32,
+    /// The lives at the start of the current level.
+    pub lives: u32,
+    pub levels_cleared: u32,
+    /// The stats of the levels that ended.
+    pub stats: Stats,
+    #[serde(default)]
+    pub difficulty: Difficulty,
+    #[serde(default)]
+    pub mode: GameMode,
+    /// Whether the run is saved, so it can be continued after quitting. Races against a replay
+    /// and the runs of the other modes aren't.
+    pub saved: bool,
+}
+
+impl Default for Run {
+    fn default() -> Self {
+        Self::new(Difficulty::default(), Lives::default().lives(), false)
+    }
+}
+
+impl Run {
+    /// A run on `difficulty`, with the lives it gives when [`Difficulty::Normal`] starts with
+    /// `normal_lives`.
+    pub fn new(difficulty: Difficulty, normal_lives: u32, saved: bool) -> Self {
+        Self {
+            score: 0,
+            lives: difficulty.starting_lives(normal_lives),
+            levels_cleared: 0,
+            stats: Stats::default(),
+            difficulty,
+            mode: GameMode::Classic,
+            saved,
+        }
+    }
+
+    pub fn level_start(&self) -> LevelStart {
+        LevelStart {
+            score: self.score,
+            lives: self.lives,
+        }
+    }
+
+    pub fn set_level_start(&mut self, level_start: LevelStart) {
+        self.score = level_start.score;
+        self.lives = level_start.lives;
+    }
+}
+
+/// The run is played on the difficulty the player picked, which can still change on the level
+/// select screen. There are no player settings without a window, so headless runs are played on
+/// the default one. Daily runs are always played on the default one, so every player gets the
+/// same challenge.
+fn start_run<const SAVED: bool>(
+    mut run: ResMut<Run>,
+    mut next_mode: ResMut<NextRunMode>,
+    settings: Res<ArkanoidSettings>,
+    user_settings: Option<Res<Settings>>,
+) {
+    let mode = std::mem::take(&mut next_mode.0);
+    let difficulty = match (mode, user_settings) {
+        (GameMode::Daily { .. }, _) | (_, None) => Difficulty::default(),
+        (_, Some(settings)) => settings.difficulty,
+    };
+
+    *run = Run {
+        mode,
+        ..Run::new(
+            difficulty,
+            settings.starting_lives,
+            SAVED && mode == GameMode::Classic,
+        )
+    };
+}
+
+/// Retrying after a game over starts the same kind of run again.
+fn restart_run(mut run: ResMut<Run>, settings: Res<ArkanoidSettings>) {
+    *run = Run {
+        mode: run.mode,
+        ..Run::new(run.difficulty, settings.starting_lives, run.saved)
+    };
+}
+
+/// A replay that's played back sets its own difficulty after this.
+fn start_level(
+    mut level_stats: ResMut<LevelStats>,
+    mut difficulty: ResMut<Difficulty>,
+    run: Res<Run>,
+) {
+    *level_stats = LevelStats::default();
+    *difficulty = run.difficulty;
+}
+
+fn track_time(mut level_stats: ResMut<LevelStats>, time: Res<Time>) {
+    level_stats.time += time.delta();
+}
+
+/// Takes the score and the lives back from the paddle before it's cleaned up.
+fn finish_level(
+    mut run: ResMut<Run>,
+    level_stats: Res<LevelStats>,
+    state: Res<State<GameState>>,
+    paddle_query: Query<(&Score, &Lives), With<Paddle>>,
+) {
+    if let Ok((score, lives)) = paddle_query.get_single() {
+        run.score = score.0;
+        run.lives = lives.lives();
+    }
+
+    if *state.current() == GameState::Win {
+        run.levels_cleared += 1;
+    }
+
+    run.stats += level_stats.0;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finished_level_counts_towards_run() {
+        let mut world = World::new();
+
+        world.insert_resource(Run::new(Difficulty::Normal, 3, true));
+        world.insert_resource(LevelStats(Stats {
+            blocks_broken: 12,
+            lives_lost: 1,
+            time: Duration::from_secs(30),
+            ..default()
+        }));
+        world.insert_resource(State::new(GameState::Win));
+        world.spawn((Paddle, Score(1200), Lives::new(2)));
+
+        let mut update_stage = SystemStage::parallel();
+
+        update_stage.add_system(finish_level);
+
+        update_stage.run(&mut world);
+
+        let run = world.resource::<Run>();
+
+        assert_eq!(
+            run.level_start(),
+            LevelStart {
+                score: 1200,
+                lives: 2
+            }
+        );
+        assert_eq!(run.levels_cleared, 1);
+        assert_eq!(run.stats.blocks_broken, 12);
+        assert_eq!(run.stats.time, Duration::from_secs(30));
+    }
+}
@@ -0,0 +1,266 @@
+//! Saving the run, so it can be continued after quitting.
+//!
+//! The run is saved at the start of every level and once a level is won, and along with the
+//! blocks that are left when the player quits from the pause menu. It's removed once the run is
+//! over.
+
+use anyhow::{bail, Context};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    actions::InputSource,
+    assets::LevelAssets,
+    block::{Block, ResumedBlocks, SavedBlock},
+    level::{CurrentLevel, LevelSystem},
+    lives::Lives,
+    paddle::Paddle,
+    replay::ReplaySystem,
+    run::{LevelStats, Run, RunSystem},
+    score::Score,
+    storage::{self, Directory},
+    ArkanoidSettings, GameState,
+};
+
+/// Bumped whenever a change to the save format needs older saves to be migrated.
+pub const SAVE_FORMAT_VERSION: u32 = 1;
+const SAVE_FILE: &str = "run.json";
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SaveRunEvent>()
+            .init_resource::<ContinueRun>()
+            .add_system_set(
+                SystemSet::on_exit(GameState::Menu).with_system(
+                    continue_run
+                        .after(LevelSystem::Reset)
+                        .after(RunSystem::Start),
+                ),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::Playing)
+                    .with_system(save_level_start.after(ReplaySystem::Start)),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Paused)
+                    .with_system(save_left_level.label(SaveSystem::Save)),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::Win)
+                    .with_system(save_cleared_level.after(RunSystem::Finish)),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::GameOver).with_system(remove_finished_run),
+            );
+    }
+}
+
+#[derive(SystemLabel, Debug, Hash, PartialEq, Eq, Copy, Clone)]
+pub enum SaveSystem {
+    /// Handles the [`SaveRunEvent`]s. The level has to be around until this runs.
+    Save,
+}
+
+/// Saves the run along with the blocks of the level that's being left.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct SaveRunEvent;
+
+/// A saved run to continue once the game leaves the menu.
+#[derive(Resource, Clone, Default, PartialEq, Debug)]
+pub struct ContinueRun(pub Option<SavedRun>);
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct SavedRun {
+    pub format_version: u32,
+    /// The level folder the run was played from.
+    pub level_pack: String,
+    pub level: usize,
+    pub run: Run,
+    /// The blocks that were left, when the run was saved in the middle of a level.
+    #[serde(default)]
+    pub blocks: Option<Vec<SavedBlock>>,
+}
+
+impl SavedRun {
+    pub fn new(level_pack: &str, level: usize, run: Run, blocks: Option<Vec<SavedBlock>>) -> Self {
+        Self {
+            format_version: SAVE_FORMAT_VERSION,
+            level_pack: level_pack.to_string(),
+            level,
+            run,
+            blocks,
+        }
+    }
+
+    /// Loads the saved run, if there is one.
+    pub fn load() -> anyhow::Result<Option<Self>> {
+        match storage::try_load::<serde_json::Value>(Directory::Data, SAVE_FILE)? {
+            Some(value) => Self::from_json(value).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Loads the saved run of `level_pack`, if there is one that can be continued.
+    pub fn load_for(level_pack: &str, level_count: usize) -> Option<Self> {
+        match Self::load() {
+            Ok(Some(saved_run)) => (saved_run.level_pack == level_pack
+                && saved_run.level < level_count)
+                .then_some(saved_run),
+            Ok(None) => None,
+            Err(error) => {
+                warn!("Couldn't load the saved run: {error:#}");
+                None
+            }
+        }
+    }
+
+    /// Reads a save of any version up to [`SAVE_FORMAT_VERSION`].
+    fn from_json(value: serde_json::Value) -> anyhow::Result<Self> {
+        let format_version = value
+            .get("format_version")
+            .and_then(serde_json::Value::as_u64)
+            .context("the save has no format version")?;
+
+        // Older versions are migrated here, one version at a time.
+        match format_version {
+            1 => Ok(serde_json::from_value(value)?),
+            _ => bail!(
+                "the save format version is {format_version}, only versions up to {SAVE_FORMAT_VERSION} are supported"
+            ),
+        }
+    }
+
+    pub fn save(&self) {
+        if let Err(error) = storage::save(Directory::Data, SAVE_FILE, self) {
+            warn!("Couldn't save the run: {error:#}");
+        }
+    }
+
+    pub fn remove() {
+        if let Err(error) = storage::remove(Directory::Data, SAVE_FILE) {
+            warn!("Couldn't remove the saved run: {error:#}");
+        }
+    }
+}
+
+/// Whether the run that's being played should be saved. Replays that are played back aren't the
+/// player's own run.
+fn is_saved(run: &Run, input_source: InputSource) -> bool {
+    run.saved && input_source == InputSource::Devices
+}
+
+fn continue_run(
+    mut continue_run: ResMut<ContinueRun>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut run: ResMut<Run>,
+    mut resumed_blocks: ResMut<ResumedBlocks>,
+) {
+    if let Some(saved_run) = continue_run.0.take() {
+        current_level.0 = saved_run.level;
+        *run = saved_run.run;
+        resumed_blocks.0 = saved_run.blocks;
+    }
+}
+
+/// Saves the run as it was at the start of the level, so it survives the game being closed.
+fn save_level_start(
+    run: Res<Run>,
+    current_level: Res<CurrentLevel>,
+    resumed_blocks: Res<ResumedBlocks>,
+    input_source: Res<InputSource>,
+    settings: Res<ArkanoidSettings>,
+) {
+    if !is_saved(&run, *input_source) {
+        return;
+    }
+
+    SavedRun::new(
+        &settings.level_folder,
+        current_level.0,
+        *run,
+        resumed_blocks.0.clone(),
+    )
+    .save();
+}
+
+#[allow(clippy::too_many_arguments)]
+fn save_left_level(
+    mut save_events: EventReader<SaveRunEvent>,
+    run: Res<Run>,
+    level_stats: Res<LevelStats>,
+    current_level: Res<CurrentLevel>,
+    input_source: Res<InputSource>,
+    settings: Res<ArkanoidSettings>,
+    paddle_query: Query<(&Score, &Lives), With<Paddle>>,
+    block_query: Query<(&Block, &Transform)>,
+) {
+    if save_events.iter().count() == 0 || !is_saved(&run, *input_source) {
+        return;
+    }
+
+    let mut run = *run;
+    run.stats += level_stats.0;
+
+    if let Ok((score, lives)) = paddle_query.get_single() {
+        run.score = score.0;
+        run.lives = lives.lives();
+    }
+
+    let blocks = block_query
+        .iter()
+        .map(|(block, transform)| SavedBlock {
+            block_type: block.block_type,
+            position: transform.translation.truncate(),
+        })
+        .collect();
+
+    SavedRun::new(&settings.level_folder, current_level.0, run, Some(blocks)).save();
+}
+
+/// Moves the save on to the next level, or removes it once the last level is won.
+fn save_cleared_level(
+    run: Res<Run>,
+    current_level: Res<CurrentLevel>,
+    level_assets: Res<LevelAssets>,
+    input_source: Res<InputSource>,
+    settings: Res<ArkanoidSettings>,
+) {
+    if !is_saved(&run, *input_source) {
+        return;
+    }
+
+    let next_level = current_level.0 + 1;
+
+    if next_level < level_assets.levels.len() {
+        SavedRun::new(&settings.level_folder, next_level, *run, None).save();
+    } else {
+        SavedRun::remove();
+    }
+}
+
+fn remove_finished_run(run: Res<Run>, input_source: Res<InputSource>) {
+    if is_saved(&run, *input_source) {
+        SavedRun::remove();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::difficulty::Difficulty;
+
+    use super::*;
+
+    #[test]
+    fn saves_of_newer_versions_are_rejected() {
+        let saved_run = SavedRun::new("levels", 2, Run::new(Difficulty::Hard, 3, true), None);
+
+        let value = serde_json::to_value(&saved_run).unwrap();
+        assert_eq!(SavedRun::from_json(value).unwrap(), saved_run);
+
+        let mut value = serde_json::to_value(&saved_run).unwrap();
+        value["format_version"] = (SAVE_FORMAT_VERSION + 1).into();
+        assert!(SavedRun::from_json(value).is_err());
+    }
+}
@@ -1,21 +1,77 @@
 use bevy::prelude::*;
 
-use crate::{ui::in_game::ScoreUi, GameState};
+/// How many blocks in a row it takes to raise the multiplier by one.
+pub const BLOCKS_PER_MULTIPLIER: u32 = 4;
+pub const MAX_MULTIPLIER: u32 = 5;
 
 pub struct PointsPlugin;
 
 impl Plugin for PointsPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<Score>()
-            .add_system_set(SystemSet::on_update(GameState::Playing).with_system(update_points_ui));
+            .register_type::<Combo>()
+            .add_event::<ScoreEvent>();
     }
 }
 
 #[derive(Component, Reflect, Clone, Eq, PartialEq, Debug, Hash, Default, Deref, DerefMut)]
 pub struct Score(pub u32);
 
-fn update_points_ui(mut ui: Query<&mut Text, With<ScoreUi>>, score: Query<&Score>) {
-    let mut text = ui.single_mut();
+/// The blocks broken since the ball last touched the paddle or a life was lost.
+#[derive(Component, Reflect, Copy, Clone, Eq, PartialEq, Debug, Hash, Default)]
+pub struct Combo(pub u32);
 
-    text.sections[0].value = score.single().to_string();
+impl Combo {
+    /// Counts a broken block and returns the multiplier its points get.
+    pub fn hit(&mut self) -> u32 {
+        self.0 += 1;
+        self.multiplier()
+    }
+
+    pub fn multiplier(&self) -> u32 {
+        (1 + self.0.saturating_sub(1) / BLOCKS_PER_MULTIPLIER).min(MAX_MULTIPLIER)
+    }
+
+    pub fn reset(&mut self) {
+        self.0 = 0;
+    }
+}
+
+/// Sent for every block that's broken, with the points it was worth.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct ScoreEvent {
+    /// The points, with the multiplier already applied.
+    pub points: u32,
+    pub multiplier: u32,
+    /// Where the block was.
+    pub position: Vec2,
+    /// The player the points went to.
+    pub player: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn multiplier_grows_with_the_combo() {
+        let mut combo = Combo::default();
+
+        assert_eq!(combo.multiplier(), 1);
+
+        let multipliers = (0..BLOCKS_PER_MULTIPLIER * 2)
+            .map(|_| combo.hit())
+            .collect::<Vec<_>>();
+
+        assert_eq!(multipliers, [1, 1, 1, 1, 2, 2, 2, 2]);
+
+        for _ in 0..BLOCKS_PER_MULTIPLIER * MAX_MULTIPLIER {
+            combo.hit();
+        }
+
+        assert_eq!(combo.multiplier(), MAX_MULTIPLIER);
+
+        combo.reset();
+        assert_eq!(combo.hit(), 1);
+    }
 }
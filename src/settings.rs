@@ -0,0 +1,282 @@
+//! User settings that are kept between sessions.
+//!
+//! The [`Settings`] resource is what the settings screen edits and what's saved to the config
+//! file. The parts of the game it configures have resources of their own, which are kept in sync
+//! with it.
+
+use std::{fmt, time::Duration};
+
+use bevy::{prelude::*, window::WindowMode};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    actions::InputSource,
+    block::Palette,
+    difficulty::Difficulty,
+    paddle::{PaddleSpeed, DEFAULT_PADDLE_SPEED},
+    replay::ReplaySystem,
+    storage::{self, Directory},
+    GameState,
+};
+
+const SETTINGS_FILE: &str = "settings.json";
+/// How long the settings have to stay the same before they're saved.
+const SAVE_DELAY: Duration = Duration::from_millis(500);
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.world.contains_resource::<Settings>() {
+            app.insert_resource(Settings::load());
+        }
+
+        app.add_system(display_hotkeys.before(SettingsSystem::Apply))
+            .add_system(apply_display_settings.label(SettingsSystem::Apply))
+            .add_system(apply_gameplay_settings.label(SettingsSystem::Apply))
+            .add_system_set(
+                SystemSet::on_enter(GameState::Playing)
+                    .with_system(apply_level_settings.before(ReplaySystem::Start)),
+            )
+            .add_system(save_settings.after(SettingsSystem::Apply));
+    }
+}
+
+#[derive(SystemLabel, Debug, Hash, PartialEq, Eq, Copy, Clone)]
+pub enum SettingsSystem {
+    Apply,
+}
+
+#[derive(Resource, Serialize, Deserialize, Copy, Clone, PartialEq, Debug, Default)]
+#[serde(default)]
+pub struct Settings {
+    pub display: DisplaySettings,
+    pub audio: AudioSettings,
+    pub controls: ControlSettings,
+    pub palette: Palette,
+    /// The difficulty new runs are played on.
+    pub difficulty: Difficulty,
+}
+
+impl Settings {
+    pub fn load() -> Self {
+        storage::load(Directory::Config, SETTINGS_FILE)
+    }
+
+    pub fn save(&self) {
+        if let Err(error) = storage::save(Directory::Config, SETTINGS_FILE, self) {
+            warn!("Couldn't save the settings: {error:#}");
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct DisplaySettings {
+    pub mode: DisplayMode,
+    pub scaling: Scaling,
+}
+
+impl Default for DisplaySettings {
+    fn default() -> Self {
+        Self {
+            mode: DisplayMode::Windowed,
+            scaling: Scaling::Fit,
+        }
+    }
+}
+
+impl DisplaySettings {
+    pub fn window_descriptor(&self) -> WindowDescriptor {
+        WindowDescriptor {
+            title: "Arkanoid".to_string(),
+            // qHD
+            width: 960.0,
+            height: 540.0,
+            mode: self.mode.into(),
+            ..default()
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum DisplayMode {
+    Windowed,
+    BorderlessFullscreen,
+    /// Exclusive fullscreen, which changes the resolution of the monitor.
+    Fullscreen,
+}
+
+impl From<DisplayMode> for WindowMode {
+    fn from(val: DisplayMode) -> Self {
+        match val {
+            DisplayMode::Windowed => WindowMode::Windowed,
+            DisplayMode::BorderlessFullscreen => WindowMode::BorderlessFullscreen,
+            DisplayMode::Fullscreen => WindowMode::Fullscreen,
+        }
+    }
+}
+
+impl DisplayMode {
+    pub const ALL: [DisplayMode; 3] = [
+        DisplayMode::Windowed,
+        DisplayMode::BorderlessFullscreen,
+        DisplayMode::Fullscreen,
+    ];
+
+    pub fn toggled(self) -> Self {
+        match self {
+            DisplayMode::Windowed => DisplayMode::BorderlessFullscreen,
+            DisplayMode::BorderlessFullscreen | DisplayMode::Fullscreen => DisplayMode::Windowed,
+        }
+    }
+}
+
+impl fmt::Display for DisplayMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DisplayMode::Windowed => write!(f, "Windowed"),
+            DisplayMode::BorderlessFullscreen => write!(f, "Borderless"),
+            DisplayMode::Fullscreen => write!(f, "Fullscreen"),
+        }
+    }
+}
+
+/// How the arena is scaled to fill the window. The space left over is letterboxed.
+#[derive(Serialize, Deserialize, Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum Scaling {
+    /// Scale the arena to be as large as the window allows.
+    Fit,
+    /// Scale the arena by the largest whole number that fits, which keeps the pixels sharp. A
+    /// window smaller than the arena falls back to [`Scaling::Fit`].
+    Integer,
+}
+
+impl Scaling {
+    pub const ALL: [Scaling; 2] = [Scaling::Fit, Scaling::Integer];
+
+    pub fn toggled(self) -> Self {
+        match self {
+            Scaling::Fit => Scaling::Integer,
+            Scaling::Integer => Scaling::Fit,
+        }
+    }
+}
+
+impl fmt::Display for Scaling {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Scaling::Fit => write!(f, "Fit"),
+            Scaling::Integer => write!(f, "Integer"),
+        }
+    }
+}
+
+/// Volumes between 0 and 1. The sound effects are scaled by the master volume.
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct AudioSettings {
+    pub master: f32,
+    pub sfx: f32,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            master: 1.,
+            sfx: 1.,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Copy, Clone, PartialEq, Debug)]
+#[serde(default)]
+pub struct ControlSettings {
+    /// How far the paddle moves for the distance the mouse moves.
+    pub mouse_sensitivity: f32,
+    /// How fast the keyboard and the gamepads move the paddle, in pixels per second.
+    pub paddle_speed: f32,
+}
+
+impl Default for ControlSettings {
+    fn default() -> Self {
+        Self {
+            mouse_sensitivity: 1.,
+            paddle_speed: DEFAULT_PADDLE_SPEED,
+        }
+    }
+}
+
+/// F11 toggles fullscreen and F10 toggles the scaling mode.
+fn display_hotkeys(keyboard_input: Res<Input<KeyCode>>, mut settings: ResMut<Settings>) {
+    if keyboard_input.just_pressed(KeyCode::F11) {
+        settings.display.mode = settings.display.mode.toggled();
+    }
+
+    if keyboard_input.just_pressed(KeyCode::F10) {
+        settings.display.scaling = settings.display.scaling.toggled();
+    }
+}
+
+fn apply_display_settings(settings: Res<Settings>, mut windows: ResMut<Windows>) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    if let Some(window) = windows.get_primary_mut() {
+        let mode = settings.display.mode.into();
+
+        if window.mode() != mode {
+            window.set_mode(mode);
+        }
+    }
+}
+
+/// Applies the settings that may change in the middle of a level. A replay that's being played
+/// back brings its own paddle speed, so it's left alone until the playback ends.
+fn apply_gameplay_settings(
+    settings: Res<Settings>,
+    input_source: Res<InputSource>,
+    mut paddle_speed: ResMut<PaddleSpeed>,
+    mut palette: ResMut<Palette>,
+) {
+    if !settings.is_changed() {
+        return;
+    }
+
+    if *palette != settings.palette {
+        *palette = settings.palette;
+    }
+
+    if *input_source == InputSource::Devices && paddle_speed.0 != settings.controls.paddle_speed {
+        paddle_speed.0 = settings.controls.paddle_speed;
+    }
+}
+
+/// Puts the paddle speed of the settings back at the start of every level, after a replay brought
+/// its own. Changing it in the middle of a level, from the pause menu, goes through
+/// [`apply_gameplay_settings`], and the replay records it on the frame it changed in. The
+/// difficulty is kept by the [`crate::run::Run`] instead.
+fn apply_level_settings(settings: Res<Settings>, mut paddle_speed: ResMut<PaddleSpeed>) {
+    paddle_speed.0 = settings.controls.paddle_speed;
+}
+
+/// Saves the settings once they stopped changing for [`SAVE_DELAY`], so adjusting a setting
+/// over many frames writes the file once at the end.
+fn save_settings(
+    settings: Res<Settings>,
+    time: Res<Time>,
+    mut last_change: Local<Option<Duration>>,
+) {
+    // The settings were just loaded from the disk, there's nothing new to save.
+    if settings.is_changed() && !settings.is_added() {
+        *last_change = Some(time.elapsed());
+    }
+
+    if let Some(changed) = *last_change {
+        if time.elapsed() - changed >= SAVE_DELAY {
+            settings.save();
+            *last_change = None;
+        }
+    }
+}
@@ -0,0 +1,101 @@
+//! Reading and writing files that outlive a single session.
+
+use std::{fs, path::PathBuf};
+
+use anyhow::Context;
+use bevy::prelude::*;
+use serde::{de::DeserializeOwned, Serialize};
+
+/// The name of the directory the game keeps its files in.
+const APP_DIRECTORY: &str = "arkanoid";
+
+/// Where a file is kept.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub enum Directory {
+    /// User settings.
+    Config,
+    /// Files the game produces while it's played, like replays.
+    Data,
+}
+
+impl Directory {
+    /// Returns the path of the directory, if the platform has one.
+    pub fn path(self) -> Option<PathBuf> {
+        let base = match self {
+            Directory::Config => dirs::config_dir(),
+            Directory::Data => dirs::data_dir(),
+        };
+
+        base.map(|dir| dir.join(APP_DIRECTORY))
+    }
+}
+
+/// Loads `file_name` from `directory`.
+///
+/// A missing or unreadable file is not fatal, the default value is returned instead.
+pub fn load<T: DeserializeOwned + Default>(directory: Directory, file_name: &str) -> T {
+    match try_load(directory, file_name) {
+        Ok(Some(value)) => value,
+        Ok(None) => T::default(),
+        Err(error) => {
+            warn!("Couldn't load {file_name}, using defaults: {error:#}");
+            T::default()
+        }
+    }
+}
+
+/// Loads `file_name` from `directory`, or returns `None` when there's no such file.
+pub fn try_load<T: DeserializeOwned>(
+    directory: Directory,
+    file_name: &str,
+) -> anyhow::Result<Option<T>> {
+    let path = match directory.path() {
+        Some(dir) => dir.join(file_name),
+        None => return Ok(None),
+    };
+
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let bytes = fs::read(&path).with_context(|| format!("reading {}", path.display()))?;
+
+    Ok(Some(serde_json::from_slice(&bytes)?))
+}
+
+/// Saves `value` as `file_name` in `directory` and returns the path it was saved to.
+///
+/// `file_name` can point into a subdirectory, which is created when it's missing.
+pub fn save<T: Serialize>(
+    directory: Directory,
+    file_name: &str,
+    value: &T,
+) -> anyhow::Result<PathBuf> {
+    let path = directory
+        .path()
+        .context("no such directory on this platform")?
+        .join(file_name);
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+    }
+
+    fs::write(&path, serde_json::to_vec_pretty(value)?)
+        .with_context(|| format!("writing {}", path.display()))?;
+
+    Ok(path)
+}
+
+/// Removes `file_name` from `directory`. A file that doesn't exist is already removed.
+pub fn remove(directory: Directory, file_name: &str) -> anyhow::Result<()> {
+    let path = match directory.path() {
+        Some(dir) => dir.join(file_name),
+        None => return Ok(()),
+    };
+
+    if path.exists() {
+        fs::remove_file(&path).with_context(|| format!("removing {}", path.display()))?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,307 @@
+//! Time attack runs, which play every level from the first one against the clock.
+//!
+//! Every level is timed from the first time the ball is released until its last breakable block
+//! is broken, and the clock stands still while the game is paused and between levels. The run
+//! time at the end of every level is a split, which is compared with the same split of the
+//! fastest run so far. Finished runs go on their own table, apart from the high scores.
+
+use std::{collections::BTreeMap, time::Duration};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    actions::InputSource,
+    assets::LevelAssets,
+    ball::{Ball, BallState},
+    difficulty::Difficulty,
+    high_score::{self, HIGH_SCORE_COUNT},
+    level::CurrentLevel,
+    paddle::Paddle,
+    run::{GameMode, Run, RunSystem},
+    score::Score,
+    storage::{self, Directory},
+    ArkanoidSettings, GameState,
+};
+
+const TIME_ATTACK_FILE: &str = "time_attack.json";
+
+pub struct TimeAttackPlugin;
+
+impl Plugin for TimeAttackPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.world.contains_resource::<TimeAttackRecords>() {
+            app.insert_resource(TimeAttackRecords::load());
+        }
+
+        app.init_resource::<LevelClock>()
+            .init_resource::<Splits>()
+            .init_resource::<SplitResult>()
+            .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(reset_clock))
+            .add_system_set(SystemSet::on_update(GameState::Playing).with_system(tick_clock))
+            .add_system_set(
+                SystemSet::on_enter(GameState::Win).with_system(
+                    record_split
+                        .label(TimeAttackSystem::Split)
+                        .before(RunSystem::Finish),
+                ),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::GameOver).with_system(restart_from_first_level),
+            );
+    }
+}
+
+#[derive(SystemLabel, Debug, Hash, PartialEq, Eq, Copy, Clone)]
+pub enum TimeAttackSystem {
+    /// Sets the [`SplitResult`] once a level of a time attack run is won.
+    Split,
+}
+
+/// The time spent on the level that's being played.
+#[derive(Resource, Copy, Clone, Default, Eq, PartialEq, Debug, Hash)]
+pub struct LevelClock {
+    pub time: Duration,
+    /// Whether the ball was released yet. The clock keeps running once it was, even while the
+    /// ball is glued to the paddle again after a life is lost.
+    pub running: bool,
+}
+
+/// The run time at the end of every level won in the time attack run, first level first.
+#[derive(Resource, Clone, Default, Eq, PartialEq, Debug, Hash)]
+pub struct Splits(pub Vec<Duration>);
+
+impl Splits {
+    /// The run time when the level after the first `levels_cleared` levels started.
+    pub fn start_of(&self, levels_cleared: u32) -> Duration {
+        (levels_cleared as usize)
+            .checked_sub(1)
+            .and_then(|level| self.0.get(level))
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+/// How the split of the level that was just won compares with the fastest run.
+#[derive(Resource, Copy, Clone, Default, Eq, PartialEq, Debug, Hash)]
+pub struct SplitResult {
+    pub split: Duration,
+    /// The same split of the fastest run, if there was one.
+    pub best: Option<Duration>,
+    /// The place the run took on the table, counted from 0, once the last level is won.
+    pub place: Option<usize>,
+}
+
+/// A finished time attack run.
+#[derive(Serialize, Deserialize, Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct RunTime {
+    pub time: Duration,
+    pub score: u32,
+    /// When the run was finished, in seconds since the Unix epoch.
+    pub date: u64,
+}
+
+impl RunTime {
+    /// The day the run was finished on, as `YYYY-MM-DD` in the player's time zone.
+    pub fn date_string(&self) -> String {
+        high_score::date_string(self.date)
+    }
+}
+
+/// The fastest runs of a level pack on a difficulty.
+#[derive(Serialize, Deserialize, Clone, Default, Eq, PartialEq, Debug, Hash)]
+#[serde(default)]
+pub struct TimeAttackTable {
+    /// The splits of the fastest run, which every run is compared with.
+    pub best_splits: Vec<Duration>,
+    /// Fastest first.
+    pub times: Vec<RunTime>,
+}
+
+impl TimeAttackTable {
+    /// Puts a finished run on the table and returns its place, counted from 0. Ties go to the
+    /// run that was finished first.
+    pub fn insert(&mut self, splits: &[Duration], run_time: RunTime) -> Option<usize> {
+        let place = self
+            .times
+            .iter()
+            .position(|other| other.time > run_time.time)
+            .unwrap_or(self.times.len());
+
+        if place >= HIGH_SCORE_COUNT {
+            return None;
+        }
+
+        if place == 0 {
+            self.best_splits = splits.to_vec();
+        }
+
+        self.times.insert(place, run_time);
+        self.times.truncate(HIGH_SCORE_COUNT);
+
+        Some(place)
+    }
+}
+
+/// The time attack tables of every level pack and difficulty, keyed by the level folder.
+#[derive(Resource, Serialize, Deserialize, Clone, Default, PartialEq, Debug)]
+#[serde(default)]
+pub struct TimeAttackRecords {
+    tables: BTreeMap<String, BTreeMap<Difficulty, TimeAttackTable>>,
+}
+
+impl TimeAttackRecords {
+    pub fn load() -> Self {
+        storage::load(Directory::Data, TIME_ATTACK_FILE)
+    }
+
+    pub fn save(&self) {
+        if let Err(error) = storage::save(Directory::Data, TIME_ATTACK_FILE, self) {
+            warn!("Couldn't save the time attack records: {error:#}");
+        }
+    }
+
+    pub fn times(&self, level_pack: &str, difficulty: Difficulty) -> &[RunTime] {
+        self.tables
+            .get(level_pack)
+            .and_then(|tables| tables.get(&difficulty))
+            .map_or(&[], |table| table.times.as_slice())
+    }
+
+    /// The split of the fastest run at the end of `level`, counted from 0.
+    pub fn best_split(
+        &self,
+        level_pack: &str,
+        difficulty: Difficulty,
+        level: usize,
+    ) -> Option<Duration> {
+        self.tables
+            .get(level_pack)
+            .and_then(|tables| tables.get(&difficulty))
+            .and_then(|table| table.best_splits.get(level))
+            .copied()
+    }
+
+    pub fn table_mut(&mut self, level_pack: &str, difficulty: Difficulty) -> &mut TimeAttackTable {
+        self.tables
+            .entry(level_pack.to_string())
+            .or_default()
+            .entry(difficulty)
+            .or_default()
+    }
+}
+
+fn reset_clock(mut clock: ResMut<LevelClock>) {
+    *clock = LevelClock::default();
+}
+
+/// Only runs while the level is played, so the clock stops while the game is paused.
+fn tick_clock(mut clock: ResMut<LevelClock>, ball_query: Query<&Ball>, time: Res<Time>) {
+    if !clock.running {
+        clock.running = ball_query.iter().any(|ball| ball.state == BallState::Free);
+    }
+
+    if clock.running {
+        clock.time += time.delta();
+    }
+}
+
+/// Runs before the [`Run`] counts the level as cleared, and takes the score from the paddle
+/// before it's cleaned up.
+#[allow(clippy::too_many_arguments)]
+fn record_split(
+    run: Res<Run>,
+    clock: Res<LevelClock>,
+    current_level: Res<CurrentLevel>,
+    level_assets: Res<LevelAssets>,
+    input_source: Res<InputSource>,
+    settings: Res<ArkanoidSettings>,
+    paddle_query: Query<&Score, With<Paddle>>,
+    mut splits: ResMut<Splits>,
+    mut split_result: ResMut<SplitResult>,
+    mut records: ResMut<TimeAttackRecords>,
+) {
+    if run.mode != GameMode::TimeAttack {
+        return;
+    }
+
+    // A new run has cleared fewer levels than there are splits left from the last one.
+    let levels_cleared = run.levels_cleared as usize;
+    splits.0.truncate(levels_cleared);
+
+    let split = splits.start_of(run.levels_cleared) + clock.time;
+    splits.0.push(split);
+
+    *split_result = SplitResult {
+        split,
+        best: records.best_split(&settings.level_folder, run.difficulty, levels_cleared),
+        place: None,
+    };
+
+    let is_last_level = current_level.0 + 1 >= level_assets.levels.len();
+
+    // Only the player's own runs go on the table.
+    if !is_last_level || *input_source != InputSource::Devices {
+        return;
+    }
+
+    let score = paddle_query.get_single().map_or(run.score, |score| score.0);
+
+    split_result.place = records
+        .table_mut(&settings.level_folder, run.difficulty)
+        .insert(
+            &splits.0,
+            RunTime {
+                time: split,
+                score,
+                date: high_score::now(),
+            },
+        );
+    records.save();
+}
+
+/// Retrying a time attack run starts it over from the first level, since only whole runs are
+/// timed.
+fn restart_from_first_level(run: Res<Run>, mut current_level: ResMut<CurrentLevel>) {
+    if run.mode == GameMode::TimeAttack {
+        current_level.0 = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_time(seconds: u64) -> RunTime {
+        RunTime {
+            time: Duration::from_secs(seconds),
+            score: 0,
+            date: 0,
+        }
+    }
+
+    #[test]
+    fn fastest_run_sets_the_splits_to_beat() {
+        let mut records = TimeAttackRecords::default();
+        let table = records.table_mut("levels", Difficulty::Normal);
+
+        let splits = [Duration::from_secs(40), Duration::from_secs(100)];
+
+        assert_eq!(table.insert(&splits, run_time(100)), Some(0));
+        assert_eq!(table.insert(&[], run_time(120)), Some(1));
+        assert_eq!(table.insert(&[], run_time(100)), Some(1));
+        assert_eq!(table.best_splits, splits);
+
+        assert_eq!(
+            records.best_split("levels", Difficulty::Normal, 1),
+            Some(Duration::from_secs(100))
+        );
+        assert_eq!(records.best_split("levels", Difficulty::Hard, 1), None);
+        assert_eq!(records.times("levels", Difficulty::Normal).len(), 3);
+
+        let splits = Splits(splits.to_vec());
+
+        assert_eq!(splits.start_of(0), Duration::ZERO);
+        assert_eq!(splits.start_of(2), Duration::from_secs(100));
+    }
+}
@@ -1,8 +1,11 @@
 use bevy::prelude::*;
 
+use crate::actions::UiNavigation;
+
 pub const DEFAULT_NORMAL_BUTTON_COLOR: Color = Color::rgb(0.15, 0.15, 0.15);
 pub const DEFAULT_HOVERED_BUTTON_COLOR: Color = Color::rgb(0.25, 0.25, 0.25);
 pub const DEFAULT_PRESSED_BUTTON_COLOR: Color = Color::rgb(0.35, 0.35, 0.35);
+pub const DEFAULT_FOCUSED_BUTTON_COLOR: Color = Color::rgb(0.2, 0.25, 0.4);
 
 pub struct ButtonPlugin;
 
@@ -10,8 +13,14 @@ impl Plugin for ButtonPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<ButtonInteraction>()
             .register_type::<StyledButton>()
+            .register_type::<Focused>()
             .add_system(styled_button_system.label(ButtonSystem::StyledButton))
-            .add_system(update_button_interaction.label(ButtonSystem::UpdateButtonInteraction));
+            .add_system(update_button_interaction.label(ButtonSystem::UpdateButtonInteraction))
+            .add_system(
+                navigate_buttons
+                    .label(ButtonSystem::UpdateButtonInteraction)
+                    .after(update_button_interaction),
+            );
     }
 }
 
@@ -28,11 +37,18 @@ pub struct ButtonInteraction {
     pub previous_interaction_state: Interaction,
 }
 
+/// Marks the button that [`UiNavigation`] moves away from and presses. There's at most one.
+///
+/// Screens insert it into the button that should be focused when they're opened.
+#[derive(Component, Reflect, Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct Focused;
+
 #[derive(Component, Reflect, Copy, Clone, PartialEq, Debug)]
 pub struct StyledButton {
     pub normal_color: Color,
     pub hovered_color: Color,
     pub pressed_color: Color,
+    pub focused_color: Color,
 }
 
 impl Default for StyledButton {
@@ -41,6 +57,7 @@ impl Default for StyledButton {
             normal_color: DEFAULT_NORMAL_BUTTON_COLOR,
             hovered_color: DEFAULT_HOVERED_BUTTON_COLOR,
             pressed_color: DEFAULT_PRESSED_BUTTON_COLOR,
+            focused_color: DEFAULT_FOCUSED_BUTTON_COLOR,
         }
     }
 }
@@ -77,32 +94,47 @@ impl Default for ArkanoidButtonBundle {
 }
 
 pub fn styled_button_system(
-    mut button_query: Query<
-        (&StyledButton, &Interaction, &mut BackgroundColor),
-        Changed<Interaction>,
-    >,
+    mut button_query: Query<(
+        &StyledButton,
+        &Interaction,
+        Option<&Focused>,
+        &mut BackgroundColor,
+    )>,
 ) {
-    for (button, interaction, mut background_color) in button_query.iter_mut() {
-        match interaction {
-            Interaction::Clicked => {
-                *background_color = button.pressed_color.into();
-            }
-            Interaction::Hovered => {
-                *background_color = button.hovered_color.into();
-            }
-            Interaction::None => {
-                *background_color = button.normal_color.into();
-            }
+    for (button, interaction, focused, mut background_color) in button_query.iter_mut() {
+        let color = match interaction {
+            Interaction::Clicked => button.pressed_color,
+            Interaction::Hovered => button.hovered_color,
+            Interaction::None if focused.is_some() => button.focused_color,
+            Interaction::None => button.normal_color,
+        };
+
+        if background_color.0 != color {
+            *background_color = color.into();
         }
     }
 }
 
 fn update_button_interaction(
-    mut interaction_query: Query<(&mut ButtonInteraction, &Interaction), Changed<Interaction>>,
+    mut interaction_query: Query<(
+        &mut ButtonInteraction,
+        &Interaction,
+        ChangeTrackers<Interaction>,
+    )>,
 ) {
-    for (mut button_interaction, interaction) in interaction_query.iter_mut() {
-        button_interaction.just_released = *interaction != Interaction::Clicked
-            && button_interaction.previous_interaction_state == Interaction::Clicked;
+    for (mut button_interaction, interaction, interaction_tracker) in interaction_query.iter_mut() {
+        if !interaction_tracker.is_changed() {
+            // A press from the navigation only lasts for a frame.
+            if button_interaction.just_pressed || button_interaction.just_released {
+                button_interaction.just_pressed = false;
+                button_interaction.just_released = false;
+            }
+
+            continue;
+        }
+
+        button_interaction.just_pressed = *interaction == Interaction::Clicked
+            && button_interaction.previous_interaction_state != Interaction::Clicked;
 
         button_interaction.just_released = *interaction != Interaction::Clicked
             && button_interaction.previous_interaction_state == Interaction::Clicked;
@@ -110,3 +142,129 @@ fn update_button_interaction(
         button_interaction.previous_interaction_state = *interaction;
     }
 }
+
+/// Moves the [`Focused`] marker between the buttons and presses the focused button on
+/// [`UiNavigation::Confirm`]. The arrows go to the closest button in their direction, the Tab key
+/// goes through the buttons in reading order, and hovering a button with the mouse focuses it.
+fn navigate_buttons(
+    mut commands: Commands,
+    mut navigation_events: EventReader<UiNavigation>,
+    hovered_query: Query<(Entity, &Interaction), (Changed<Interaction>, With<ButtonInteraction>)>,
+    mut button_query: Query<(
+        Entity,
+        &GlobalTransform,
+        &ComputedVisibility,
+        &mut ButtonInteraction,
+        Option<&Focused>,
+    )>,
+) {
+    let previous_focus = button_query
+        .iter()
+        .find(|(_, _, _, _, focused)| focused.is_some())
+        .map(|(entity, ..)| entity);
+
+    let mut focus = hovered_query
+        .iter()
+        .find(|(_, interaction)| **interaction == Interaction::Hovered)
+        .map(|(entity, _)| entity)
+        .or(previous_focus);
+
+    let mut buttons = button_query
+        .iter()
+        .filter(|(_, _, visibility, _, _)| visibility.is_visible())
+        .map(|(entity, transform, ..)| (entity, transform.translation().truncate()))
+        .collect::<Vec<_>>();
+
+    // In reading order, the UI is laid out from the top of the window down.
+    buttons.sort_by(|(_, a), (_, b)| a.y.total_cmp(&b.y).then(a.x.total_cmp(&b.x)));
+
+    for navigation in navigation_events.iter() {
+        let current =
+            focus.and_then(|focus| buttons.iter().position(|(entity, _)| *entity == focus));
+
+        let next = match (navigation, current) {
+            (UiNavigation::Confirm, _) => {
+                if let Some(Ok((_, _, _, mut button_interaction, _))) =
+                    focus.map(|focus| button_query.get_mut(focus))
+                {
+                    button_interaction.just_released = true;
+                }
+
+                continue;
+            }
+            (UiNavigation::Previous, None) => buttons.last(),
+            (_, None) => buttons.first(),
+            (UiNavigation::Next, Some(current)) => buttons.get((current + 1) % buttons.len()),
+            (UiNavigation::Previous, Some(current)) => {
+                buttons.get((current + buttons.len() - 1) % buttons.len())
+            }
+            (UiNavigation::Up, Some(current)) => closest_button(&buttons, current, Vec2::NEG_Y),
+            (UiNavigation::Down, Some(current)) => closest_button(&buttons, current, Vec2::Y),
+            (UiNavigation::Left, Some(current)) => closest_button(&buttons, current, Vec2::NEG_X),
+            (UiNavigation::Right, Some(current)) => closest_button(&buttons, current, Vec2::X),
+        };
+
+        if let Some((entity, _)) = next {
+            focus = Some(*entity);
+        }
+    }
+
+    if focus == previous_focus {
+        return;
+    }
+
+    if let Some(previous_focus) = previous_focus {
+        commands.entity(previous_focus).remove::<Focused>();
+    }
+
+    if let Some(focus) = focus {
+        // The button may be despawned along with its screen by now.
+        commands.add(move |world: &mut World| {
+            if let Some(mut entity) = world.get_entity_mut(focus) {
+                entity.insert(Focused);
+            }
+        });
+    }
+}
+
+/// The button closest to `buttons[current]` in `direction`. Buttons off to the side count as further
+/// away than the ones straight ahead.
+fn closest_button(
+    buttons: &[(Entity, Vec2)],
+    current: usize,
+    direction: Vec2,
+) -> Option<&(Entity, Vec2)> {
+    let position = buttons[current].1;
+
+    buttons
+        .iter()
+        .filter_map(|button| {
+            let offset = button.1 - position;
+            let distance = offset.dot(direction);
+
+            (distance > 0.).then(|| (button, distance + 2. * offset.perp_dot(direction).abs()))
+        })
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(button, _)| button)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn closest_button_prefers_straight_ahead() {
+        let buttons = [
+            (Entity::from_raw(0), Vec2::new(0., 0.)),
+            (Entity::from_raw(1), Vec2::new(0., 100.)),
+            (Entity::from_raw(2), Vec2::new(60., 60.)),
+            (Entity::from_raw(3), Vec2::new(100., 0.)),
+        ];
+
+        let closest = |direction| closest_button(&buttons, 0, direction).map(|(entity, _)| *entity);
+
+        assert_eq!(closest(Vec2::Y), Some(Entity::from_raw(1)));
+        assert_eq!(closest(Vec2::X), Some(Entity::from_raw(3)));
+        assert_eq!(closest(Vec2::NEG_Y), None);
+    }
+}
@@ -0,0 +1,345 @@
+//! The screen the controls are rebound on.
+
+use bevy::prelude::*;
+
+use crate::{
+    actions::{ActionsSystem, Binding, GameControl, InputMap},
+    assets::FontAssets,
+    util::cleanup,
+    GameState,
+};
+
+use super::{
+    button::{ArkanoidButtonBundle, ButtonInteraction, ButtonSystem, Focused},
+    set_state_button,
+};
+
+const WARNING_COLOR: Color = Color::rgb(1., 0.8, 0.2);
+
+pub struct ControlsUiPlugin;
+
+impl Plugin for ControlsUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<ControlsUi>()
+            .register_type::<GoToMenuButton>()
+            .register_type::<ResetControlsButton>()
+            .init_resource::<Rebinding>()
+            .add_system_set(SystemSet::on_enter(GameState::Controls).with_system(spawn_ui))
+            .add_system_set(
+                SystemSet::on_update(GameState::Controls)
+                    .with_system(capture_binding.before(ActionsSystem::Input)),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::Controls)
+                    .with_system(set_state_button::<GoToMenuButton, { GameState::Menu }>)
+                    .after(ButtonSystem::UpdateButtonInteraction)
+                    .with_system(control_buttons)
+                    .after(ButtonSystem::UpdateButtonInteraction)
+                    .with_system(reset_controls)
+                    .after(ButtonSystem::UpdateButtonInteraction)
+                    .with_system(update_ui),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Controls)
+                    .with_system(cleanup::<ControlsUi>)
+                    .with_system(stop_rebinding),
+            );
+    }
+}
+
+#[derive(Component, Reflect)]
+struct ControlsUi;
+
+#[derive(Component, Reflect)]
+struct GoToMenuButton;
+
+#[derive(Component, Reflect)]
+struct ResetControlsButton;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+enum RebindMode {
+    /// The pressed key replaces the bindings of the control.
+    Replace,
+    /// The pressed key is bound next to the existing bindings.
+    Add,
+}
+
+#[derive(Component, Copy, Clone, Eq, PartialEq, Debug, Hash)]
+struct ControlButton {
+    control: GameControl,
+    mode: RebindMode,
+}
+
+/// Shows the bindings of a control.
+#[derive(Component, Copy, Clone, Eq, PartialEq, Debug, Hash)]
+struct BindingsText(GameControl);
+
+/// Tells the player which key is being waited for, or which keys are bound twice.
+#[derive(Component, Copy, Clone, Eq, PartialEq, Debug, Hash)]
+struct StatusText;
+
+/// The control waiting for the player to press a key.
+#[derive(Resource, Default, Copy, Clone, Eq, PartialEq, Debug, Hash)]
+struct Rebinding(Option<ControlButton>);
+
+fn spawn_ui(mut commands: Commands, fonts: Res<FontAssets>) {
+    let text_style = TextStyle {
+        font: fonts.title_font.clone(),
+        font_size: 24.,
+        color: Color::WHITE,
+    };
+
+    let small_button = |margin: UiRect| ArkanoidButtonBundle {
+        button_bundle: ButtonBundle {
+            style: Style {
+                size: Size::new(Val::Px(110.), Val::Px(40.)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                margin,
+                ..default()
+            },
+            ..default()
+        },
+        ..default()
+    };
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            ..default()
+        })
+        .insert(ControlsUi)
+        .insert(Name::new("ControlsUI"))
+        .with_children(|parent| {
+            parent.spawn(
+                TextBundle::from_section(
+                    "Controls",
+                    TextStyle {
+                        font: fonts.title_font.clone(),
+                        font_size: 64.,
+                        color: Color::WHITE,
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::bottom(Val::Px(16.)),
+                    ..default()
+                }),
+            );
+
+            for control in GameControl::ALL {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            align_items: AlignItems::Center,
+                            margin: UiRect::bottom(Val::Px(8.)),
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn(
+                            TextBundle::from_section(control.to_string(), text_style.clone())
+                                .with_style(Style {
+                                    size: Size::new(Val::Px(180.), Val::Auto),
+                                    ..default()
+                                }),
+                        );
+
+                        parent
+                            .spawn(TextBundle::from_section("", text_style.clone()).with_style(
+                                Style {
+                                    size: Size::new(Val::Px(280.), Val::Auto),
+                                    ..default()
+                                },
+                            ))
+                            .insert(BindingsText(control));
+
+                        for (mode, label) in
+                            [(RebindMode::Replace, "Rebind"), (RebindMode::Add, "Add")]
+                        {
+                            parent
+                                .spawn(small_button(UiRect::left(Val::Px(16.))))
+                                .insert(ControlButton { control, mode })
+                                .with_children(|parent| {
+                                    parent
+                                        .spawn(TextBundle::from_section(label, text_style.clone()));
+                                });
+                        }
+                    });
+            }
+
+            parent
+                .spawn(
+                    TextBundle::from_section(
+                        "",
+                        TextStyle {
+                            color: WARNING_COLOR,
+                            ..text_style.clone()
+                        },
+                    )
+                    .with_style(Style {
+                        margin: UiRect::vertical(Val::Px(16.)),
+                        ..default()
+                    }),
+                )
+                .insert(StatusText);
+
+            parent.spawn(NodeBundle::default()).with_children(|parent| {
+                parent
+                    .spawn(ArkanoidButtonBundle::default())
+                    .insert(ResetControlsButton)
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section("Defaults", text_style.clone()));
+                    });
+
+                parent
+                    .spawn(ArkanoidButtonBundle {
+                        button_bundle: ButtonBundle {
+                            style: Style {
+                                size: Size::new(Val::Px(150.), Val::Px(65.)),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                margin: UiRect::left(Val::Px(16.)),
+                                ..default()
+                            },
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .insert(GoToMenuButton)
+                    .insert(Focused)
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section("Go to menu", text_style.clone()));
+                    });
+            });
+        });
+}
+
+fn control_buttons(
+    button_query: Query<(&ButtonInteraction, &ControlButton), Changed<ButtonInteraction>>,
+    mut rebinding: ResMut<Rebinding>,
+) {
+    for (button_interaction, button) in button_query.iter() {
+        if button_interaction.just_released {
+            rebinding.0 = Some(*button);
+        }
+    }
+}
+
+fn reset_controls(
+    button_query: Query<
+        &ButtonInteraction,
+        (Changed<ButtonInteraction>, With<ResetControlsButton>),
+    >,
+    mut input_map: ResMut<InputMap>,
+    mut rebinding: ResMut<Rebinding>,
+) {
+    if let Some(button_interaction) = button_query.iter().next() {
+        if button_interaction.just_released {
+            *input_map = InputMap::default();
+            rebinding.0 = None;
+        }
+    }
+}
+
+/// Binds the next key, mouse button or gamepad button the player presses. Escape cancels.
+///
+/// Keys and gamepad buttons are used up, so they don't also press the focused button of the menu.
+fn capture_binding(
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    mouse_button_input: Res<Input<MouseButton>>,
+    mut gamepad_button_input: ResMut<Input<GamepadButton>>,
+    mut input_map: ResMut<InputMap>,
+    mut rebinding: ResMut<Rebinding>,
+) {
+    let button = match rebinding.0 {
+        Some(button) => button,
+        None => return,
+    };
+
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        keyboard_input.reset(KeyCode::Escape);
+        rebinding.0 = None;
+        return;
+    }
+
+    let binding = if let Some(key) = keyboard_input.get_just_pressed().next().copied() {
+        keyboard_input.reset(key);
+        Binding::Key(key)
+    } else if let Some(mouse_button) = mouse_button_input.get_just_pressed().next() {
+        Binding::Mouse(*mouse_button)
+    } else if let Some(gamepad_button) = gamepad_button_input.get_just_pressed().next().copied() {
+        gamepad_button_input.reset(gamepad_button);
+        Binding::Gamepad(gamepad_button.button_type)
+    } else {
+        return;
+    };
+
+    match button.mode {
+        RebindMode::Replace => input_map.rebind(button.control, binding),
+        RebindMode::Add => input_map.bind(button.control, binding),
+    }
+
+    rebinding.0 = None;
+}
+
+fn update_ui(
+    input_map: Res<InputMap>,
+    rebinding: Res<Rebinding>,
+    added_query: Query<(), Added<BindingsText>>,
+    mut bindings_query: Query<(&mut Text, &BindingsText), Without<StatusText>>,
+    mut status_query: Query<&mut Text, With<StatusText>>,
+) {
+    if !input_map.is_changed() && !rebinding.is_changed() && added_query.is_empty() {
+        return;
+    }
+
+    for (mut text, bindings_text) in bindings_query.iter_mut() {
+        let bindings = input_map.bindings(bindings_text.0);
+
+        text.sections[0].value = if bindings.is_empty() {
+            "Unbound".to_string()
+        } else {
+            bindings
+                .iter()
+                .map(Binding::to_string)
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+    }
+
+    let status = match rebinding.0 {
+        Some(button) => format!(
+            "Press a key, a mouse button or a gamepad button for {} (Escape cancels)",
+            button.control
+        ),
+        None => input_map
+            .conflicts()
+            .iter()
+            .map(|(binding, controls)| {
+                let controls = controls
+                    .iter()
+                    .map(GameControl::to_string)
+                    .collect::<Vec<_>>()
+                    .join(" and ");
+
+                format!("{binding} is bound to both {controls}")
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+    };
+
+    for mut text in status_query.iter_mut() {
+        text.sections[0].value = status.clone();
+    }
+}
+
+fn stop_rebinding(mut rebinding: ResMut<Rebinding>) {
+    rebinding.0 = None;
+}
@@ -0,0 +1,218 @@
+//! Today's daily challenge, and the days before it to practice again.
+
+use bevy::prelude::*;
+
+use crate::{
+    assets::FontAssets,
+    daily::{seed_date_string, todays_seed, DailyHistory, DailyResult},
+    run::{GameMode, NextRunMode},
+    util::cleanup,
+    GameState,
+};
+
+use super::{
+    button::{ArkanoidButtonBundle, ButtonInteraction, ButtonSystem, Focused},
+    set_state_button,
+};
+
+const DETAIL_COLOR: Color = Color::rgb(0.6, 0.6, 0.6);
+/// How many of the days before are listed.
+const SHOWN_DAYS: usize = 5;
+
+pub struct DailyUiPlugin;
+
+impl Plugin for DailyUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<DailyUi>()
+            .register_type::<GoToMenuButton>()
+            .add_system_set(SystemSet::on_enter(GameState::Daily).with_system(spawn_ui))
+            .add_system_set(
+                SystemSet::on_update(GameState::Daily)
+                    .with_system(set_state_button::<GoToMenuButton, { GameState::Menu }>)
+                    .after(ButtonSystem::UpdateButtonInteraction)
+                    .with_system(play_daily)
+                    .after(ButtonSystem::UpdateButtonInteraction),
+            )
+            .add_system_set(SystemSet::on_exit(GameState::Daily).with_system(cleanup::<DailyUi>));
+    }
+}
+
+#[derive(Component, Reflect)]
+struct DailyUi;
+
+#[derive(Component, Reflect)]
+struct GoToMenuButton;
+
+/// Plays the level of the day `seed` was made from.
+#[derive(Component)]
+struct PlayDailyButton {
+    seed: u64,
+    scored: bool,
+}
+
+fn result_string(result: &DailyResult) -> String {
+    let cleared = if result.cleared {
+        "Cleared"
+    } else {
+        "Not cleared"
+    };
+
+    format!("{}   {}", result.score, cleared)
+}
+
+fn spawn_ui(mut commands: Commands, fonts: Res<FontAssets>, history: Res<DailyHistory>) {
+    let text_style = TextStyle {
+        font: fonts.title_font.clone(),
+        font_size: 24.,
+        color: Color::WHITE,
+    };
+
+    let detail_style = TextStyle {
+        font_size: 20.,
+        color: DETAIL_COLOR,
+        ..text_style.clone()
+    };
+
+    let seed = todays_seed();
+    let today = history.result(seed);
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            ..default()
+        })
+        .insert(DailyUi)
+        .insert(Name::new("DailyUI"))
+        .with_children(|parent| {
+            parent.spawn(
+                TextBundle::from_section(
+                    "Daily",
+                    TextStyle {
+                        font: fonts.title_font.clone(),
+                        font_size: 64.,
+                        color: Color::WHITE,
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::bottom(Val::Px(8.)),
+                    ..default()
+                }),
+            );
+
+            parent.spawn(TextBundle::from_section(
+                format!("{}   Seed {seed}", seed_date_string(seed)),
+                text_style.clone(),
+            ));
+
+            let today_string = match today {
+                Some(result) => result_string(result),
+                None => "One scored attempt a day".to_string(),
+            };
+
+            parent.spawn(TextBundle::from_section(today_string, detail_style.clone()));
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        margin: UiRect::vertical(Val::Px(16.)),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent
+                        .spawn(ArkanoidButtonBundle::default())
+                        .insert(GoToMenuButton)
+                        .with_children(|parent| {
+                            parent
+                                .spawn(TextBundle::from_section("Go to menu", text_style.clone()));
+                        });
+
+                    parent
+                        .spawn(ArkanoidButtonBundle {
+                            button_bundle: ButtonBundle {
+                                style: Style {
+                                    size: Size::new(Val::Px(150.), Val::Px(65.)),
+                                    justify_content: JustifyContent::Center,
+                                    align_items: AlignItems::Center,
+                                    margin: UiRect::left(Val::Px(16.)),
+                                    ..default()
+                                },
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .insert(PlayDailyButton {
+                            seed,
+                            scored: today.is_none(),
+                        })
+                        .insert(Focused)
+                        .with_children(|parent| {
+                            let label = if today.is_none() { "Play" } else { "Practice" };
+
+                            parent.spawn(TextBundle::from_section(label, text_style.clone()));
+                        });
+                });
+
+            let days_before = history
+                .results()
+                .iter()
+                .filter(|result| result.seed != seed)
+                .take(SHOWN_DAYS);
+
+            for result in days_before {
+                parent
+                    .spawn(ArkanoidButtonBundle {
+                        button_bundle: ButtonBundle {
+                            style: Style {
+                                size: Size::new(Val::Px(480.), Val::Px(36.)),
+                                justify_content: JustifyContent::SpaceBetween,
+                                align_items: AlignItems::Center,
+                                padding: UiRect::horizontal(Val::Px(16.)),
+                                margin: UiRect::bottom(Val::Px(8.)),
+                                ..default()
+                            },
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .insert(PlayDailyButton {
+                        seed: result.seed,
+                        scored: false,
+                    })
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            seed_date_string(result.seed),
+                            detail_style.clone(),
+                        ));
+                        parent.spawn(TextBundle::from_section(
+                            result_string(result),
+                            detail_style.clone(),
+                        ));
+                    });
+            }
+        });
+}
+
+fn play_daily(
+    button_query: Query<(&ButtonInteraction, &PlayDailyButton), Changed<ButtonInteraction>>,
+    mut next_run_mode: ResMut<NextRunMode>,
+    mut state: ResMut<State<GameState>>,
+) {
+    for (button_interaction, button) in button_query.iter() {
+        if button_interaction.just_released {
+            next_run_mode.0 = GameMode::Daily {
+                seed: button.seed,
+                scored: button.scored,
+            };
+            let _ = state.set(GameState::Playing);
+            return;
+        }
+    }
+}
@@ -1,9 +1,20 @@
 use bevy::prelude::*;
 
-use crate::{assets::FontAssets, util::cleanup, GameState};
+use crate::{
+    assets::FontAssets,
+    high_score::{HighScoreSystem, PendingHighScore},
+    hot_seat::{HotSeat, HotSeatSystem},
+    lives::Lives,
+    paddle::Player,
+    run::{GameMode, Run},
+    score::Score,
+    util::cleanup,
+    GameState,
+};
 
 use super::{
-    button::{ArkanoidButtonBundle, ButtonSystem},
+    button::{ArkanoidButtonBundle, ButtonSystem, Focused},
+    initials::{enter_initials, spawn_name_entry, InitialsEnteredEvent},
     set_state_button,
 };
 
@@ -13,13 +24,21 @@ impl Plugin for GameOverPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<GameOverUi>()
             .register_type::<GoToMenuButton>()
-            .add_system_set(SystemSet::on_enter(GameState::GameOver).with_system(spawn_ui))
+            .add_system_set(
+                SystemSet::on_enter(GameState::GameOver).with_system(
+                    spawn_ui
+                        .after(HotSeatSystem::EndTurn)
+                        .after(HighScoreSystem::Check),
+                ),
+            )
             .add_system_set(
                 SystemSet::on_update(GameState::GameOver)
                     .with_system(set_state_button::<GoToMenuButton, { GameState::Menu }>)
                     .after(ButtonSystem::UpdateButtonInteraction)
                     .with_system(set_state_button::<RetryButton, { GameState::Playing }>)
-                    .after(ButtonSystem::UpdateButtonInteraction),
+                    .after(ButtonSystem::UpdateButtonInteraction)
+                    .with_system(enter_initials)
+                    .with_system(show_buttons.after(enter_initials)),
             )
             .add_system_set(
                 SystemSet::on_exit(GameState::GameOver).with_system(cleanup::<GameOverUi>),
@@ -36,7 +55,72 @@ struct GoToMenuButton;
 #[derive(Component, Reflect)]
 struct RetryButton;
 
-fn spawn_ui(mut commands: Commands, fonts: Res<FontAssets>) {
+/// Holds the buttons, which only show up once the initials are entered.
+#[derive(Component)]
+struct ButtonRow;
+
+/// The player who won a versus run, counted from 0: the last one with lives left, or else the
+/// best score. `None` on a draw.
+fn versus_winner(results: &[(usize, u32, u32)]) -> Option<usize> {
+    let alive: Vec<_> = results.iter().filter(|(_, _, lives)| *lives > 0).collect();
+
+    if let [(player, _, _)] = alive.as_slice() {
+        return Some(*player);
+    }
+
+    let best = results.iter().map(|(_, score, _)| *score).max()?;
+    let mut best_players = results.iter().filter(|(_, score, _)| *score == best);
+
+    match (best_players.next(), best_players.next()) {
+        (Some((player, _, _)), None) => Some(*player),
+        _ => None,
+    }
+}
+
+/// A hot-seat run shows the score of every player, and a versus run shows who won as well.
+fn spawn_ui(
+    mut commands: Commands,
+    fonts: Res<FontAssets>,
+    pending: Res<PendingHighScore>,
+    hot_seat: Res<HotSeat>,
+    run: Res<Run>,
+    paddle_query: Query<(&Score, &Lives, &Player)>,
+) {
+    // The score and the lives left of every player in a versus run.
+    let mut results: Vec<_> = paddle_query
+        .iter()
+        .map(|(score, lives, player)| (player.0, score.0, lives.lives()))
+        .collect();
+    results.sort_unstable();
+
+    let title = if run.mode != GameMode::Versus {
+        "Game Over!".to_string()
+    } else if let Some(player) = versus_winner(&results) {
+        format!("Player {} wins!", player + 1)
+    } else {
+        "Draw!".to_string()
+    };
+
+    let scores: Vec<(usize, u32)> = if run.mode == GameMode::Versus {
+        results
+            .iter()
+            .map(|(player, score, _)| (*player, *score))
+            .collect()
+    } else {
+        hot_seat
+            .seats
+            .iter()
+            .enumerate()
+            .map(|(player, seat)| (player, seat.run.score))
+            .collect()
+    };
+
+    let text_style = TextStyle {
+        font: fonts.title_font.clone(),
+        font_size: 24.,
+        color: Color::WHITE,
+    };
+
     commands
         .spawn(NodeBundle {
             transform: Transform::from_translation(Vec3::new(0., 0., 1.)),
@@ -54,7 +138,7 @@ fn spawn_ui(mut commands: Commands, fonts: Res<FontAssets>) {
         .with_children(|parent| {
             parent
                 .spawn(TextBundle::from_section(
-                    "Game Over!",
+                    title,
                     TextStyle {
                         font: fonts.title_font.clone(),
                         font_size: 72.,
@@ -63,44 +147,101 @@ fn spawn_ui(mut commands: Commands, fonts: Res<FontAssets>) {
                 ))
                 .insert(Name::new("GameOverUITitle"));
 
-            parent
-                .spawn(NodeBundle {
-                    style: Style {
-                        margin: UiRect::top(Val::Px(64.)),
-                        justify_content: JustifyContent::SpaceBetween,
-                        size: Size::new(Val::Px(256. + 64.), Val::Auto),
+            for (player, score) in scores {
+                parent.spawn(
+                    TextBundle::from_section(
+                        format!("Player {}: {score}", player + 1),
+                        text_style.clone(),
+                    )
+                    .with_style(Style {
+                        margin: UiRect::top(Val::Px(16.)),
                         ..default()
-                    },
+                    }),
+                );
+            }
+
+            if let Some(high_score) = &pending.0 {
+                spawn_name_entry(parent, high_score, &text_style);
+            }
+
+            let mut button_row = parent.spawn(NodeBundle {
+                style: Style {
+                    margin: UiRect::top(Val::Px(64.)),
+                    justify_content: JustifyContent::SpaceBetween,
+                    size: Size::new(Val::Px(256. + 64.), Val::Auto),
                     ..default()
-                })
-                .with_children(|parent| {
-                    parent
-                        .spawn(ArkanoidButtonBundle::default())
-                        .insert(GoToMenuButton)
-                        .with_children(|parent| {
-                            parent.spawn(TextBundle::from_section(
-                                "Go to menu",
-                                TextStyle {
-                                    font: fonts.title_font.clone(),
-                                    font_size: 24.,
-                                    color: Color::WHITE,
-                                },
-                            ));
-                        });
-
-                    parent
-                        .spawn(ArkanoidButtonBundle::default())
-                        .insert(RetryButton)
-                        .with_children(|parent| {
-                            parent.spawn(TextBundle::from_section(
-                                "Retry",
-                                TextStyle {
-                                    font: fonts.title_font.clone(),
-                                    font_size: 24.,
-                                    color: Color::WHITE,
-                                },
-                            ));
-                        });
-                });
+                },
+                ..default()
+            });
+
+            button_row.insert(ButtonRow);
+
+            if pending.0.is_none() {
+                button_row.with_children(|parent| spawn_buttons(parent, &fonts));
+            }
+        });
+}
+
+fn spawn_buttons(parent: &mut ChildBuilder, fonts: &FontAssets) {
+    parent
+        .spawn(ArkanoidButtonBundle::default())
+        .insert(GoToMenuButton)
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Go to menu",
+                TextStyle {
+                    font: fonts.title_font.clone(),
+                    font_size: 24.,
+                    color: Color::WHITE,
+                },
+            ));
+        });
+
+    parent
+        .spawn(ArkanoidButtonBundle::default())
+        .insert(RetryButton)
+        .insert(Focused)
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                "Retry",
+                TextStyle {
+                    font: fonts.title_font.clone(),
+                    font_size: 24.,
+                    color: Color::WHITE,
+                },
+            ));
         });
 }
+
+/// The buttons show up once the initials are entered.
+fn show_buttons(
+    mut commands: Commands,
+    fonts: Res<FontAssets>,
+    mut entered_events: EventReader<InitialsEnteredEvent>,
+    button_row_query: Query<Entity, With<ButtonRow>>,
+) {
+    if entered_events.iter().count() == 0 {
+        return;
+    }
+
+    for entity in button_row_query.iter() {
+        commands
+            .entity(entity)
+            .with_children(|parent| spawn_buttons(parent, &fonts));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versus_winner_test() {
+        // Whoever still has lives wins, whatever the score.
+        assert_eq!(versus_winner(&[(0, 100, 0), (1, 50, 2)]), Some(1));
+        // Clearing the blocks leaves both players with lives, so the best score wins.
+        assert_eq!(versus_winner(&[(0, 900, 1), (1, 500, 3)]), Some(0));
+        assert_eq!(versus_winner(&[(0, 500, 1), (1, 500, 3)]), None);
+        assert_eq!(versus_winner(&[]), None);
+    }
+}
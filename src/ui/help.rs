@@ -6,7 +6,10 @@ use crate::{
     GameState,
 };
 
-use super::{button::ArkanoidButtonBundle, set_state_button};
+use super::{
+    button::{ArkanoidButtonBundle, ButtonSystem, Focused},
+    set_state_button,
+};
 
 pub struct HelpPlugin;
 
@@ -17,7 +20,8 @@ impl Plugin for HelpPlugin {
             .add_system_set(SystemSet::on_enter(GameState::Help).with_system(setup_help_ui))
             .add_system_set(
                 SystemSet::on_update(GameState::Help)
-                    .with_system(set_state_button::<GoToMenuButton, { GameState::Menu }>),
+                    .with_system(set_state_button::<GoToMenuButton, { GameState::Menu }>)
+                    .after(ButtonSystem::UpdateButtonInteraction),
             )
             .add_system_set(SystemSet::on_exit(GameState::Help).with_system(cleanup::<HelpUi>));
     }
@@ -235,6 +239,7 @@ fn setup_help_ui(mut commands: Commands, images: Res<TextureAssets>, fonts: Res<
             parent
                 .spawn(ArkanoidButtonBundle::default())
                 .insert(GoToMenuButton)
+                .insert(Focused)
                 .with_children(|parent| {
                     parent.spawn(TextBundle::from_section(
                         "Go to menu",
@@ -0,0 +1,389 @@
+//! The high score tables and the time attack tables of the level pack that's being played, one
+//! for every difficulty.
+
+use std::fmt;
+
+use bevy::{ecs::system::EntityCommands, prelude::*};
+
+use crate::{
+    assets::FontAssets, difficulty::Difficulty, high_score::HighScores, settings::Settings,
+    time_attack::TimeAttackRecords, util::cleanup, ArkanoidSettings, GameState,
+};
+
+use super::{
+    button::{ArkanoidButtonBundle, ButtonInteraction, ButtonSystem, Focused},
+    format_time, set_state_button,
+    settings::cycle,
+};
+
+const HEADER_COLOR: Color = Color::rgb(0.6, 0.6, 0.6);
+/// The widths of the place, initials, score, level and date columns.
+const SCORE_COLUMN_WIDTHS: [f32; 5] = [50., 90., 130., 110., 160.];
+/// The widths of the place, time, score and date columns.
+const TIME_COLUMN_WIDTHS: [f32; 4] = [50., 150., 130., 160.];
+
+pub struct HighScoresUiPlugin;
+
+impl Plugin for HighScoresUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<HighScoresUi>()
+            .register_type::<GoToMenuButton>()
+            .add_system_set(SystemSet::on_enter(GameState::HighScores).with_system(spawn_ui))
+            .add_system_set(
+                SystemSet::on_update(GameState::HighScores)
+                    .with_system(set_state_button::<GoToMenuButton, { GameState::Menu }>)
+                    .after(ButtonSystem::UpdateButtonInteraction)
+                    .with_system(switch_table)
+                    .after(ButtonSystem::UpdateButtonInteraction),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::HighScores).with_system(cleanup::<HighScoresUi>),
+            );
+    }
+}
+
+#[derive(Component, Reflect)]
+struct HighScoresUi;
+
+#[derive(Component, Reflect)]
+struct GoToMenuButton;
+
+/// Switches to the table of the next difficulty.
+#[derive(Component)]
+struct DifficultyButton;
+
+/// Switches between the high scores and the time attack times.
+#[derive(Component)]
+struct KindButton;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+enum TableKind {
+    Scores,
+    Times,
+}
+
+impl TableKind {
+    const ALL: [TableKind; 2] = [TableKind::Scores, TableKind::Times];
+}
+
+impl fmt::Display for TableKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TableKind::Scores => write!(f, "Scores"),
+            TableKind::Times => write!(f, "Times"),
+        }
+    }
+}
+
+/// Holds the rows of the table that's shown.
+#[derive(Component)]
+struct TableRows {
+    kind: TableKind,
+    difficulty: Difficulty,
+}
+
+fn spawn_row(
+    parent: &mut ChildBuilder,
+    cells: Vec<String>,
+    widths: &[f32],
+    text_style: &TextStyle,
+) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                margin: UiRect::bottom(Val::Px(4.)),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            for (cell, width) in cells.into_iter().zip(widths) {
+                parent.spawn(
+                    TextBundle::from_section(cell, text_style.clone()).with_style(Style {
+                        size: Size::new(Val::Px(*width), Val::Auto),
+                        ..default()
+                    }),
+                );
+            }
+        });
+}
+
+fn spawn_rows(
+    parent: &mut ChildBuilder,
+    table_rows: &TableRows,
+    high_scores: &HighScores,
+    records: &TimeAttackRecords,
+    level_pack: &str,
+    fonts: &FontAssets,
+) {
+    // Small enough to fit a full table on the screen.
+    let row_style = TextStyle {
+        font: fonts.title_font.clone(),
+        font_size: 20.,
+        color: Color::WHITE,
+    };
+
+    let header_style = TextStyle {
+        color: HEADER_COLOR,
+        ..row_style.clone()
+    };
+
+    let (header, widths, rows, empty): (_, &[f32], Vec<Vec<String>>, _) = match table_rows.kind {
+        TableKind::Scores => (
+            vec!["#", "Name", "Score", "Level", "Date"],
+            &SCORE_COLUMN_WIDTHS[..],
+            high_scores
+                .table(level_pack, table_rows.difficulty)
+                .iter()
+                .enumerate()
+                .map(|(place, high_score)| {
+                    vec![
+                        (place + 1).to_string(),
+                        high_score.initials.clone(),
+                        high_score.score.to_string(),
+                        high_score.level.to_string(),
+                        high_score.date_string(),
+                    ]
+                })
+                .collect(),
+            "No high scores yet.",
+        ),
+        TableKind::Times => (
+            vec!["#", "Time", "Score", "Date"],
+            &TIME_COLUMN_WIDTHS[..],
+            records
+                .times(level_pack, table_rows.difficulty)
+                .iter()
+                .enumerate()
+                .map(|(place, run_time)| {
+                    vec![
+                        (place + 1).to_string(),
+                        format_time(run_time.time),
+                        run_time.score.to_string(),
+                        run_time.date_string(),
+                    ]
+                })
+                .collect(),
+            "No times yet.",
+        ),
+    };
+
+    if rows.is_empty() {
+        parent.spawn(
+            TextBundle::from_section(
+                empty,
+                TextStyle {
+                    font_size: 24.,
+                    ..row_style
+                },
+            )
+            .with_style(Style {
+                margin: UiRect::vertical(Val::Px(16.)),
+                ..default()
+            }),
+        );
+
+        return;
+    }
+
+    spawn_row(
+        parent,
+        header.into_iter().map(str::to_string).collect(),
+        widths,
+        &header_style,
+    );
+
+    for row in rows {
+        spawn_row(parent, row, widths, &row_style);
+    }
+}
+
+/// A button next to the go to menu button, labeled with what it switches to.
+fn spawn_switch_button<'w, 's, 'a>(
+    parent: &'a mut ChildBuilder<'w, 's, '_>,
+    label: String,
+    text_style: &TextStyle,
+) -> EntityCommands<'w, 's, 'a> {
+    let mut button = parent.spawn(ArkanoidButtonBundle {
+        button_bundle: ButtonBundle {
+            style: Style {
+                size: Size::new(Val::Px(150.), Val::Px(65.)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                margin: UiRect::left(Val::Px(16.)),
+                ..default()
+            },
+            ..default()
+        },
+        ..default()
+    });
+
+    button.with_children(|parent| {
+        parent.spawn(TextBundle::from_section(label, text_style.clone()));
+    });
+
+    button
+}
+
+fn spawn_ui(
+    mut commands: Commands,
+    fonts: Res<FontAssets>,
+    high_scores: Res<HighScores>,
+    records: Res<TimeAttackRecords>,
+    settings: Res<ArkanoidSettings>,
+    user_settings: Res<Settings>,
+) {
+    let text_style = TextStyle {
+        font: fonts.title_font.clone(),
+        font_size: 24.,
+        color: Color::WHITE,
+    };
+
+    let table_rows = TableRows {
+        kind: TableKind::Scores,
+        difficulty: user_settings.difficulty,
+    };
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            ..default()
+        })
+        .insert(HighScoresUi)
+        .insert(Name::new("HighScoresUI"))
+        .with_children(|parent| {
+            parent.spawn(
+                TextBundle::from_section(
+                    "High scores",
+                    TextStyle {
+                        font: fonts.title_font.clone(),
+                        font_size: 64.,
+                        color: Color::WHITE,
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::bottom(Val::Px(8.)),
+                    ..default()
+                }),
+            );
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        flex_direction: FlexDirection::Column,
+                        align_items: AlignItems::Center,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    spawn_rows(
+                        parent,
+                        &table_rows,
+                        &high_scores,
+                        &records,
+                        &settings.level_folder,
+                        &fonts,
+                    )
+                })
+                .insert(table_rows);
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        margin: UiRect::top(Val::Px(16.)),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent
+                        .spawn(ArkanoidButtonBundle::default())
+                        .insert(GoToMenuButton)
+                        .insert(Focused)
+                        .with_children(|parent| {
+                            parent
+                                .spawn(TextBundle::from_section("Go to menu", text_style.clone()));
+                        });
+
+                    spawn_switch_button(parent, user_settings.difficulty.to_string(), &text_style)
+                        .insert(DifficultyButton);
+                    spawn_switch_button(parent, TableKind::Scores.to_string(), &text_style)
+                        .insert(KindButton);
+                });
+        });
+}
+
+fn set_label(children: &Children, text_query: &mut Query<&mut Text>, label: String) {
+    for child in children.iter() {
+        if let Ok(mut text) = text_query.get_mut(*child) {
+            text.sections[0].value = label.clone();
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn switch_table(
+    mut commands: Commands,
+    difficulty_button_query: Query<
+        (&ButtonInteraction, &Children),
+        (Changed<ButtonInteraction>, With<DifficultyButton>),
+    >,
+    kind_button_query: Query<
+        (&ButtonInteraction, &Children),
+        (Changed<ButtonInteraction>, With<KindButton>),
+    >,
+    mut text_query: Query<&mut Text>,
+    mut rows_query: Query<(Entity, &mut TableRows)>,
+    fonts: Res<FontAssets>,
+    high_scores: Res<HighScores>,
+    records: Res<TimeAttackRecords>,
+    settings: Res<ArkanoidSettings>,
+) {
+    let (rows_entity, mut table_rows) = match rows_query.get_single_mut() {
+        Ok(rows) => rows,
+        Err(_) => return,
+    };
+
+    let mut switched = false;
+
+    for (button_interaction, children) in difficulty_button_query.iter() {
+        if button_interaction.just_released {
+            cycle(&mut table_rows.difficulty, &Difficulty::ALL, 1);
+            set_label(children, &mut text_query, table_rows.difficulty.to_string());
+            switched = true;
+        }
+    }
+
+    for (button_interaction, children) in kind_button_query.iter() {
+        if button_interaction.just_released {
+            cycle(&mut table_rows.kind, &TableKind::ALL, 1);
+            set_label(children, &mut text_query, table_rows.kind.to_string());
+            switched = true;
+        }
+    }
+
+    if !switched {
+        return;
+    }
+
+    let mut rows = commands.entity(rows_entity);
+
+    rows.despawn_descendants();
+    rows.with_children(|parent| {
+        spawn_rows(
+            parent,
+            &table_rows,
+            &high_scores,
+            &records,
+            &settings.level_folder,
+            &fonts,
+        )
+    });
+}
@@ -1,8 +1,32 @@
 //! Defines the in-game UI layout.
 
+use std::time::Duration;
+
 use bevy::prelude::*;
 
-use crate::{assets::FontAssets, util::cleanup, GameState};
+use crate::{
+    assets::{FontAssets, TextureAssets},
+    daily::seed_date_string,
+    hot_seat::HotSeat,
+    lives::Lives,
+    paddle::Player,
+    run::{GameMode, Run},
+    score::{Score, ScoreEvent},
+    time_attack::{LevelClock, Splits, TimeAttackRecords},
+    util::cleanup,
+    ArkanoidSettings, GameState,
+};
+
+use super::format_time;
+
+/// The most life icons shown, more lives are shown as one icon and a counter.
+const MAX_LIFE_ICONS: u32 = 5;
+/// How long a score popup stays on screen, in seconds.
+const POPUP_LIFETIME: f32 = 0.8;
+/// How fast a score popup rises, in pixels per second.
+const POPUP_SPEED: f32 = 60.;
+const POPUP_COLOR: Color = Color::rgb(1., 0.8, 0.2);
+const BEST_SPLIT_COLOR: Color = Color::rgb(0.6, 0.6, 0.6);
 
 pub struct InGameUiPlugin;
 
@@ -10,9 +34,21 @@ impl Plugin for InGameUiPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<InGameUi>()
             .register_type::<LivesUi>()
+            .register_type::<ScoreUi>()
+            .register_type::<ClockUi>()
             .add_system_set(SystemSet::on_enter(GameState::Playing).with_system(setup_ui))
             .add_system_set(
-                SystemSet::on_exit(GameState::Playing).with_system(cleanup::<InGameUi>),
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(update_score_ui)
+                    .with_system(update_clock_ui)
+                    .with_system(display_lives)
+                    .with_system(spawn_score_popups)
+                    .with_system(move_score_popups),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Playing)
+                    .with_system(cleanup::<InGameUi>)
+                    .with_system(cleanup::<ScorePopup>),
             );
     }
 }
@@ -20,14 +56,34 @@ impl Plugin for InGameUiPlugin {
 #[derive(Component, Reflect, Copy, Clone, PartialEq, Debug, Default)]
 pub struct InGameUi;
 
+/// The lives of the player, counted from 0.
 #[derive(Component, Reflect, Copy, Clone, PartialEq, Debug, Default)]
-pub struct LivesUi;
+pub struct LivesUi(pub usize);
 
-/// A marker component for the score UI element.
+/// The score of the player, counted from 0.
 #[derive(Component, Reflect, Copy, Clone, PartialEq, Debug, Default)]
-pub struct ScoreUi;
+pub struct ScoreUi(pub usize);
+
+/// The run time of a time attack run.
+#[derive(Component, Reflect, Copy, Clone, PartialEq, Debug, Default)]
+pub struct ClockUi;
+
+/// In versus runs, the score and the lives of every player are shown on the side of their paddle.
+fn setup_ui(
+    mut commands: Commands,
+    fonts: Res<FontAssets>,
+    run: Res<Run>,
+    hot_seat: Res<HotSeat>,
+    records: Res<TimeAttackRecords>,
+    settings: Res<ArkanoidSettings>,
+) {
+    // From the top of the screen to the bottom.
+    let players: &[usize] = if run.mode == GameMode::Versus {
+        &[1, 0]
+    } else {
+        &[0]
+    };
 
-fn setup_ui(mut commands: Commands, fonts: Res<FontAssets>) {
     commands
         .spawn(NodeBundle {
             style: Style {
@@ -45,23 +101,26 @@ fn setup_ui(mut commands: Commands, fonts: Res<FontAssets>) {
                 .spawn(NodeBundle {
                     style: Style {
                         flex_direction: FlexDirection::Column,
+                        justify_content: JustifyContent::SpaceBetween,
                         ..Default::default()
                     },
                     ..default()
                 })
                 .insert(Name::new("LeftSection"))
                 .with_children(|parent| {
-                    parent
-                        .spawn(TextBundle::from_section(
-                            "0",
-                            TextStyle {
-                                font: fonts.title_font.clone(),
-                                font_size: 40.0,
-                                color: Color::rgb(0.9, 0.9, 0.9),
-                            },
-                        ))
-                        .insert(Name::new("Score"))
-                        .insert(ScoreUi);
+                    for &player in players {
+                        parent
+                            .spawn(TextBundle::from_section(
+                                "0",
+                                TextStyle {
+                                    font: fonts.title_font.clone(),
+                                    font_size: 40.0,
+                                    color: Color::rgb(0.9, 0.9, 0.9),
+                                },
+                            ))
+                            .insert(Name::new("Score"))
+                            .insert(ScoreUi(player));
+                    }
                 });
 
             parent
@@ -72,29 +131,216 @@ fn setup_ui(mut commands: Commands, fonts: Res<FontAssets>) {
                     },
                     ..default()
                 })
-                .insert(Name::new("MiddleSection"));
+                .insert(Name::new("MiddleSection"))
+                .with_children(|parent| {
+                    if let GameMode::Daily { seed, scored } = run.mode {
+                        let label = if scored { "Daily" } else { "Practice" };
+
+                        parent.spawn(TextBundle::from_section(
+                            format!("{label} {}", seed_date_string(seed)),
+                            TextStyle {
+                                font: fonts.title_font.clone(),
+                                font_size: 24.0,
+                                color: Color::rgb(0.9, 0.9, 0.9),
+                            },
+                        ));
+                    }
+
+                    if hot_seat.is_active() {
+                        parent.spawn(TextBundle::from_section(
+                            format!("Player {}", hot_seat.current + 1),
+                            TextStyle {
+                                font: fonts.title_font.clone(),
+                                font_size: 24.0,
+                                color: Color::rgb(0.9, 0.9, 0.9),
+                            },
+                        ));
+                    }
+
+                    if run.mode != GameMode::TimeAttack {
+                        return;
+                    }
+
+                    parent
+                        .spawn(TextBundle::from_section(
+                            format_time(Duration::ZERO),
+                            TextStyle {
+                                font: fonts.title_font.clone(),
+                                font_size: 40.0,
+                                color: Color::rgb(0.9, 0.9, 0.9),
+                            },
+                        ))
+                        .insert(Name::new("Clock"))
+                        .insert(ClockUi);
+
+                    // The time to beat by the end of the level.
+                    let best_split = records.best_split(
+                        &settings.level_folder,
+                        run.difficulty,
+                        run.levels_cleared as usize,
+                    );
+
+                    if let Some(best_split) = best_split {
+                        parent.spawn(TextBundle::from_section(
+                            format!("Best {}", format_time(best_split)),
+                            TextStyle {
+                                font: fonts.title_font.clone(),
+                                font_size: 20.0,
+                                color: BEST_SPLIT_COLOR,
+                            },
+                        ));
+                    }
+                });
 
             parent
                 .spawn(NodeBundle {
                     style: Style {
                         flex_direction: FlexDirection::Column,
+                        justify_content: JustifyContent::SpaceBetween,
                         ..Default::default()
                     },
                     ..default()
                 })
                 .insert(Name::new("RightSection"))
                 .with_children(|right_section| {
-                    right_section
-                        .spawn(NodeBundle {
-                            style: Style {
-                                flex_direction: FlexDirection::RowReverse,
-                                size: Size::new(Val::Percent(100.0), Val::Px(32.0)),
-                                ..Default::default()
-                            },
-                            ..default()
-                        })
-                        .insert(LivesUi)
-                        .insert(Name::new("Lives"));
+                    for &player in players {
+                        right_section
+                            .spawn(NodeBundle {
+                                style: Style {
+                                    flex_direction: FlexDirection::RowReverse,
+                                    size: Size::new(Val::Percent(100.0), Val::Px(32.0)),
+                                    ..Default::default()
+                                },
+                                ..default()
+                            })
+                            .insert(LivesUi(player))
+                            .insert(Name::new("Lives"));
+                    }
                 });
         });
 }
+
+fn update_score_ui(mut ui: Query<(&mut Text, &ScoreUi)>, score_query: Query<(&Score, &Player)>) {
+    for (mut text, score_ui) in ui.iter_mut() {
+        if let Some((score, _)) = score_query
+            .iter()
+            .find(|(_, player)| player.0 == score_ui.0)
+        {
+            text.sections[0].value = score.to_string();
+        }
+    }
+}
+
+fn update_clock_ui(
+    mut ui: Query<&mut Text, With<ClockUi>>,
+    clock: Res<LevelClock>,
+    splits: Res<Splits>,
+    run: Res<Run>,
+) {
+    if let Ok(mut text) = ui.get_single_mut() {
+        text.sections[0].value = format_time(splits.start_of(run.levels_cleared) + clock.time);
+    }
+}
+
+fn display_lives(
+    mut commands: Commands,
+    lives_query: Query<(&Lives, &Player), Changed<Lives>>,
+    ui_query: Query<(Entity, &LivesUi)>,
+    texture_assets: Res<TextureAssets>,
+    fonts: Res<FontAssets>,
+) {
+    for (lives, player) in lives_query.iter() {
+        for (ui, _) in ui_query
+            .iter()
+            .filter(|(_, lives_ui)| lives_ui.0 == player.0)
+        {
+            commands.entity(ui).despawn_descendants();
+
+            commands.entity(ui).with_children(|parent| {
+                // The lives are laid out from the right, so the counter goes first.
+                let icons = if lives.lives() > MAX_LIFE_ICONS {
+                    parent.spawn(TextBundle::from_section(
+                        format!("\u{D7}{}", lives.lives()),
+                        TextStyle {
+                            font: fonts.title_font.clone(),
+                            font_size: 28.0,
+                            color: Color::rgb(0.9, 0.9, 0.9),
+                        },
+                    ));
+
+                    1
+                } else {
+                    lives.lives()
+                };
+
+                for _ in 0..icons {
+                    parent.spawn(ImageBundle {
+                        style: Style {
+                            margin: UiRect::left(Val::Px(8.)),
+                            ..default()
+                        },
+                        image: texture_assets.ball_small.clone().into(),
+                        ..default()
+                    });
+                }
+            });
+        }
+    }
+}
+
+/// The points a block was worth, rising from where it was and fading out.
+#[derive(Component, Copy, Clone, PartialEq, Debug)]
+struct ScorePopup {
+    age: f32,
+}
+
+fn spawn_score_popups(
+    mut commands: Commands,
+    fonts: Res<FontAssets>,
+    mut score_events: EventReader<ScoreEvent>,
+) {
+    for event in score_events.iter() {
+        let text = if event.multiplier > 1 {
+            format!("+{} \u{D7}{}", event.points, event.multiplier)
+        } else {
+            format!("+{}", event.points)
+        };
+
+        commands
+            .spawn(Text2dBundle {
+                text: Text::from_section(
+                    text,
+                    TextStyle {
+                        font: fonts.title_font.clone(),
+                        font_size: 20.,
+                        color: POPUP_COLOR,
+                    },
+                )
+                .with_alignment(TextAlignment::CENTER),
+                transform: Transform::from_translation(event.position.extend(10.)),
+                ..default()
+            })
+            .insert(ScorePopup { age: 0. });
+    }
+}
+
+fn move_score_popups(
+    mut commands: Commands,
+    mut popup_query: Query<(Entity, &mut ScorePopup, &mut Transform, &mut Text)>,
+    time: Res<Time>,
+) {
+    for (entity, mut popup, mut transform, mut text) in popup_query.iter_mut() {
+        popup.age += time.delta_seconds();
+
+        if popup.age >= POPUP_LIFETIME {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        transform.translation.y += POPUP_SPEED * time.delta_seconds();
+        text.sections[0]
+            .style
+            .color
+            .set_a(1. - popup.age / POPUP_LIFETIME);
+    }
+}
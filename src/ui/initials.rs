@@ -0,0 +1,196 @@
+//! The initials entry shown when a run ends with a score that made it onto the high score table.
+
+use bevy::prelude::*;
+
+use crate::{
+    actions::UiNavigation,
+    high_score::{HighScore, HighScores, PendingHighScore, INITIALS_LEN},
+    ArkanoidSettings,
+};
+
+/// The letters the initials are picked from with the arrow keys or a gamepad.
+const INITIALS_ALPHABET: &[char] = &[
+    'A', 'B', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K', 'L', 'M', 'N', 'O', 'P', 'Q', 'R', 'S',
+    'T', 'U', 'V', 'W', 'X', 'Y', 'Z', '0', '1', '2', '3', '4', '5', '6', '7', '8', '9',
+];
+const SELECTED_LETTER_COLOR: Color = Color::rgb(1., 0.8, 0.2);
+
+pub struct InitialsPlugin;
+
+impl Plugin for InitialsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<InitialsEnteredEvent>();
+    }
+}
+
+/// Sent by [`enter_initials`] once the pending score is on the table, so the screen can move on.
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+pub struct InitialsEnteredEvent;
+
+/// Asks for the initials when the score made it onto the high score table.
+#[derive(Component)]
+pub struct NameEntry;
+
+/// The initials being entered, one text section per letter.
+#[derive(Component, Copy, Clone, Eq, PartialEq, Debug, Hash)]
+struct Initials {
+    letters: [char; INITIALS_LEN],
+    /// The letter being changed.
+    cursor: usize,
+}
+
+impl Default for Initials {
+    fn default() -> Self {
+        Self {
+            letters: [INITIALS_ALPHABET[0]; INITIALS_LEN],
+            cursor: 0,
+        }
+    }
+}
+
+impl Initials {
+    fn type_letter(&mut self, letter: char) {
+        self.letters[self.cursor] = letter;
+        self.move_cursor(1);
+    }
+
+    /// Picks the next (1) or the previous (-1) letter of the alphabet.
+    fn cycle_letter(&mut self, steps: i32) {
+        let current = INITIALS_ALPHABET
+            .iter()
+            .position(|letter| *letter == self.letters[self.cursor])
+            .unwrap_or(0) as i32;
+
+        let next = (current + steps).rem_euclid(INITIALS_ALPHABET.len() as i32);
+
+        self.letters[self.cursor] = INITIALS_ALPHABET[next as usize];
+    }
+
+    fn move_cursor(&mut self, steps: i32) {
+        self.cursor = (self.cursor as i32 + steps).clamp(0, INITIALS_LEN as i32 - 1) as usize;
+    }
+
+    fn is_on_last_letter(&self) -> bool {
+        self.cursor == INITIALS_LEN - 1
+    }
+}
+
+/// The prompt and the letters of the initials, for [`enter_initials`] to fill in.
+pub fn spawn_name_entry(parent: &mut ChildBuilder, high_score: &HighScore, text_style: &TextStyle) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                margin: UiRect::top(Val::Px(32.)),
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            ..default()
+        })
+        .insert(NameEntry)
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                format!("New high score: {}! Enter your initials", high_score.score),
+                text_style.clone(),
+            ));
+
+            let initials = Initials::default();
+
+            parent
+                .spawn(
+                    TextBundle::from_sections(initials.letters.map(|letter| {
+                        TextSection::new(
+                            letter,
+                            TextStyle {
+                                font_size: 48.,
+                                ..text_style.clone()
+                            },
+                        )
+                    }))
+                    .with_style(Style {
+                        margin: UiRect::top(Val::Px(16.)),
+                        ..default()
+                    }),
+                )
+                .insert(initials);
+        });
+}
+
+/// Letters and digits are typed in, Backspace goes back a letter. The arrow keys or a gamepad
+/// pick the letters one by one, and confirming on the last letter saves the score.
+#[allow(clippy::too_many_arguments)]
+pub fn enter_initials(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut character_events: EventReader<ReceivedCharacter>,
+    mut navigation_events: EventReader<UiNavigation>,
+    mut initials_query: Query<(&mut Initials, &mut Text)>,
+    name_entry_query: Query<Entity, With<NameEntry>>,
+    mut entered_events: EventWriter<InitialsEnteredEvent>,
+    mut pending: ResMut<PendingHighScore>,
+    mut high_scores: ResMut<HighScores>,
+    settings: Res<ArkanoidSettings>,
+) {
+    let (mut initials, mut text) = match initials_query.get_single_mut() {
+        Ok(initials) => initials,
+        Err(_) => {
+            // The button press that brought up the entry mustn't pick its first letter.
+            character_events.clear();
+            navigation_events.clear();
+            return;
+        }
+    };
+
+    let mut done = false;
+
+    for event in character_events.iter() {
+        if event.char.is_ascii_alphanumeric() {
+            initials.type_letter(event.char.to_ascii_uppercase());
+        }
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Back) {
+        initials.move_cursor(-1);
+    }
+
+    for navigation in navigation_events.iter() {
+        match navigation {
+            UiNavigation::Up => initials.cycle_letter(1),
+            UiNavigation::Down => initials.cycle_letter(-1),
+            UiNavigation::Left | UiNavigation::Previous => initials.move_cursor(-1),
+            UiNavigation::Right | UiNavigation::Next => initials.move_cursor(1),
+            UiNavigation::Confirm if initials.is_on_last_letter() => done = true,
+            UiNavigation::Confirm => initials.move_cursor(1),
+        }
+    }
+
+    if initials.is_changed() {
+        for (i, section) in text.sections.iter_mut().enumerate() {
+            section.value = initials.letters[i].to_string();
+            section.style.color = if i == initials.cursor {
+                SELECTED_LETTER_COLOR
+            } else {
+                Color::WHITE
+            };
+        }
+    }
+
+    if !done {
+        return;
+    }
+
+    let initials = initials.letters.iter().collect::<String>();
+
+    if let Some(place) = pending.submit(&initials, &mut high_scores, &settings) {
+        info!(
+            "{initials} took place {} on the high score table",
+            place + 1
+        );
+    }
+
+    for entity in name_entry_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    entered_events.send(InitialsEnteredEvent);
+}
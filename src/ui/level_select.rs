@@ -0,0 +1,315 @@
+//! The grid of levels a run can be started from. Locked levels show up, but can't be picked.
+
+use bevy::prelude::*;
+
+use crate::{
+    assets::{FontAssets, LevelAssets},
+    difficulty::Difficulty,
+    level::{CurrentLevel, LevelAsset},
+    progress::{Progress, MAX_STARS},
+    settings::Settings,
+    util::cleanup,
+    ArkanoidSettings, GameState,
+};
+
+use super::{
+    button::{ArkanoidButtonBundle, ButtonInteraction, ButtonSystem, Focused},
+    set_state_button,
+    settings::cycle,
+};
+
+const LEVELS_PER_ROW: usize = 3;
+const LOCKED_COLOR: Color = Color::rgb(0.08, 0.08, 0.08);
+const DETAIL_COLOR: Color = Color::rgb(0.6, 0.6, 0.6);
+const STAR_COLOR: Color = Color::rgb(1., 0.8, 0.2);
+const MISSING_STAR_COLOR: Color = Color::rgb(0.3, 0.3, 0.3);
+
+pub struct LevelSelectUiPlugin;
+
+impl Plugin for LevelSelectUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<LevelSelectUi>()
+            .register_type::<GoToMenuButton>()
+            .register_type::<LevelButton>()
+            .register_type::<DifficultyButton>()
+            .add_system_set(SystemSet::on_enter(GameState::LevelSelect).with_system(spawn_ui))
+            .add_system_set(
+                SystemSet::on_update(GameState::LevelSelect)
+                    .with_system(set_state_button::<GoToMenuButton, { GameState::Menu }>)
+                    .after(ButtonSystem::UpdateButtonInteraction)
+                    .with_system(select_level)
+                    .after(ButtonSystem::UpdateButtonInteraction)
+                    .with_system(switch_difficulty)
+                    .after(ButtonSystem::UpdateButtonInteraction),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::LevelSelect).with_system(cleanup::<LevelSelectUi>),
+            );
+    }
+}
+
+#[derive(Component, Reflect)]
+struct LevelSelectUi;
+
+#[derive(Component, Reflect)]
+struct GoToMenuButton;
+
+/// Picks the difficulty of the run, which is saved with the settings.
+#[derive(Component, Reflect)]
+struct DifficultyButton;
+
+/// Starts the run from the level, counted from 0.
+#[derive(Component, Reflect)]
+struct LevelButton(usize);
+
+/// One dot per star, the ones that weren't earned are greyed out.
+pub(super) fn star_sections(stars: u8, text_style: &TextStyle) -> Vec<TextSection> {
+    (0..MAX_STARS)
+        .map(|star| {
+            TextSection::new(
+                "\u{25CF}",
+                TextStyle {
+                    color: if star < stars {
+                        STAR_COLOR
+                    } else {
+                        MISSING_STAR_COLOR
+                    },
+                    ..text_style.clone()
+                },
+            )
+        })
+        .collect()
+}
+
+fn level_button_style() -> Style {
+    Style {
+        size: Size::new(Val::Px(200.), Val::Px(80.)),
+        justify_content: JustifyContent::Center,
+        align_items: AlignItems::Center,
+        flex_direction: FlexDirection::Column,
+        margin: UiRect::all(Val::Px(8.)),
+        ..default()
+    }
+}
+
+fn spawn_ui(
+    mut commands: Commands,
+    fonts: Res<FontAssets>,
+    level_assets: Res<LevelAssets>,
+    levels: Res<Assets<LevelAsset>>,
+    progress: Res<Progress>,
+    settings: Res<ArkanoidSettings>,
+    user_settings: Res<Settings>,
+) {
+    let text_style = TextStyle {
+        font: fonts.title_font.clone(),
+        font_size: 24.,
+        color: Color::WHITE,
+    };
+
+    let detail_style = TextStyle {
+        font_size: 16.,
+        color: DETAIL_COLOR,
+        ..text_style.clone()
+    };
+
+    let level_pack = &settings.level_folder;
+
+    // The furthest level that can be played is where the player most likely wants to go on.
+    let focused_level = (0..level_assets.levels.len())
+        .rev()
+        .find(|level| progress.is_unlocked(level_pack, *level));
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            ..default()
+        })
+        .insert(LevelSelectUi)
+        .insert(Name::new("LevelSelectUI"))
+        .with_children(|parent| {
+            parent.spawn(
+                TextBundle::from_section(
+                    "Levels",
+                    TextStyle {
+                        font: fonts.title_font.clone(),
+                        font_size: 64.,
+                        color: Color::WHITE,
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::bottom(Val::Px(16.)),
+                    ..default()
+                }),
+            );
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(LEVELS_PER_ROW as f32 * (200. + 16.)), Val::Auto),
+                        flex_wrap: FlexWrap::Wrap,
+                        justify_content: JustifyContent::Center,
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    for (level, handle) in level_assets.levels.iter().enumerate() {
+                        let name = levels.get(handle).map_or_else(
+                            || format!("Level {}", level + 1),
+                            |asset| asset.display_name(level),
+                        );
+
+                        if !progress.is_unlocked(level_pack, level) {
+                            parent
+                                .spawn(NodeBundle {
+                                    style: level_button_style(),
+                                    background_color: LOCKED_COLOR.into(),
+                                    ..default()
+                                })
+                                .with_children(|parent| {
+                                    parent.spawn(TextBundle::from_section(
+                                        name,
+                                        TextStyle {
+                                            color: MISSING_STAR_COLOR,
+                                            ..text_style.clone()
+                                        },
+                                    ));
+                                    parent.spawn(TextBundle::from_section(
+                                        "Locked",
+                                        detail_style.clone(),
+                                    ));
+                                });
+
+                            continue;
+                        }
+
+                        let record = progress.record(level_pack, level);
+
+                        let mut level_button = parent.spawn(ArkanoidButtonBundle {
+                            button_bundle: ButtonBundle {
+                                style: level_button_style(),
+                                ..default()
+                            },
+                            ..default()
+                        });
+
+                        if focused_level == Some(level) {
+                            level_button.insert(Focused);
+                        }
+
+                        level_button
+                            .insert(LevelButton(level))
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle::from_section(name, text_style.clone()));
+
+                                let best_score = if record.is_cleared() {
+                                    format!("Best: {}", record.best_score)
+                                } else {
+                                    "Not cleared".to_string()
+                                };
+
+                                parent.spawn(TextBundle::from_section(
+                                    best_score,
+                                    detail_style.clone(),
+                                ));
+                                parent.spawn(TextBundle::from_sections(star_sections(
+                                    record.stars,
+                                    &detail_style,
+                                )));
+                            });
+                    }
+                });
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        margin: UiRect::top(Val::Px(16.)),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    let mut go_to_menu_button = parent.spawn(ArkanoidButtonBundle::default());
+
+                    if focused_level.is_none() {
+                        go_to_menu_button.insert(Focused);
+                    }
+
+                    go_to_menu_button
+                        .insert(GoToMenuButton)
+                        .with_children(|parent| {
+                            parent
+                                .spawn(TextBundle::from_section("Go to menu", text_style.clone()));
+                        });
+
+                    parent
+                        .spawn(ArkanoidButtonBundle {
+                            button_bundle: ButtonBundle {
+                                style: Style {
+                                    size: Size::new(Val::Px(260.), Val::Px(65.)),
+                                    justify_content: JustifyContent::Center,
+                                    align_items: AlignItems::Center,
+                                    margin: UiRect::left(Val::Px(16.)),
+                                    ..default()
+                                },
+                                ..default()
+                            },
+                            ..default()
+                        })
+                        .insert(DifficultyButton)
+                        .with_children(|parent| {
+                            parent.spawn(TextBundle::from_section(
+                                format!("Difficulty: {}", user_settings.difficulty),
+                                text_style.clone(),
+                            ));
+                        });
+                });
+        });
+}
+
+fn switch_difficulty(
+    button_query: Query<
+        (&ButtonInteraction, &Children),
+        (Changed<ButtonInteraction>, With<DifficultyButton>),
+    >,
+    mut text_query: Query<&mut Text>,
+    mut user_settings: ResMut<Settings>,
+) {
+    let (button_interaction, children) = match button_query.get_single() {
+        Ok(button) => button,
+        Err(_) => return,
+    };
+
+    if !button_interaction.just_released {
+        return;
+    }
+
+    cycle(&mut user_settings.difficulty, &Difficulty::ALL, 1);
+
+    for child in children.iter() {
+        if let Ok(mut text) = text_query.get_mut(*child) {
+            text.sections[0].value = format!("Difficulty: {}", user_settings.difficulty);
+        }
+    }
+}
+
+fn select_level(
+    button_query: Query<(&ButtonInteraction, &LevelButton), Changed<ButtonInteraction>>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut state: ResMut<State<GameState>>,
+) {
+    for (button_interaction, level_button) in button_query.iter() {
+        if button_interaction.just_released {
+            current_level.0 = level_button.0;
+            let _ = state.set(GameState::Playing);
+            return;
+        }
+    }
+}
@@ -1,8 +1,14 @@
-use crate::{assets::FontAssets, util::cleanup, GameState};
-use bevy::prelude::*;
+use crate::{
+    assets::{FontAssets, LevelAssets},
+    run::{GameMode, NextRunMode},
+    save::{ContinueRun, SavedRun},
+    util::cleanup,
+    ArkanoidSettings, GameState,
+};
+use bevy::{ecs::system::EntityCommands, prelude::*};
 
 use super::{
-    button::{ArkanoidButtonBundle, ButtonSystem},
+    button::{ArkanoidButtonBundle, ButtonInteraction, ButtonSystem, Focused},
     set_state_button,
 };
 
@@ -11,14 +17,44 @@ pub struct MenuPlugin;
 impl Plugin for MenuPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<Menu>()
+            .register_type::<ContinueButton>()
             .register_type::<PlayButton>()
+            .register_type::<TimeAttackButton>()
+            .register_type::<DailyButton>()
+            .register_type::<TwoPlayersButton>()
+            .register_type::<CoOpButton>()
+            .register_type::<VersusButton>()
             .register_type::<HelpButton>()
+            .register_type::<ReplaysButton>()
+            .register_type::<ControlsButton>()
+            .register_type::<SettingsButton>()
+            .register_type::<HighScoresButton>()
             .add_system_set(SystemSet::on_enter(GameState::Menu).with_system(spawn_menu))
             .add_system_set(
                 SystemSet::on_update(GameState::Menu)
-                    .with_system(set_state_button::<PlayButton, { GameState::Playing }>)
+                    .with_system(continue_button)
+                    .after(ButtonSystem::UpdateButtonInteraction)
+                    .with_system(set_state_button::<PlayButton, { GameState::LevelSelect }>)
+                    .after(ButtonSystem::UpdateButtonInteraction)
+                    .with_system(time_attack_button)
+                    .after(ButtonSystem::UpdateButtonInteraction)
+                    .with_system(set_state_button::<DailyButton, { GameState::Daily }>)
+                    .after(ButtonSystem::UpdateButtonInteraction)
+                    .with_system(two_players_button)
+                    .after(ButtonSystem::UpdateButtonInteraction)
+                    .with_system(co_op_button)
+                    .after(ButtonSystem::UpdateButtonInteraction)
+                    .with_system(versus_button)
                     .after(ButtonSystem::UpdateButtonInteraction)
                     .with_system(set_state_button::<HelpButton, { GameState::Help }>)
+                    .after(ButtonSystem::UpdateButtonInteraction)
+                    .with_system(set_state_button::<ReplaysButton, { GameState::Replays }>)
+                    .after(ButtonSystem::UpdateButtonInteraction)
+                    .with_system(set_state_button::<ControlsButton, { GameState::Controls }>)
+                    .after(ButtonSystem::UpdateButtonInteraction)
+                    .with_system(set_state_button::<SettingsButton, { GameState::Settings }>)
+                    .after(ButtonSystem::UpdateButtonInteraction)
+                    .with_system(set_state_button::<HighScoresButton, { GameState::HighScores }>)
                     .after(ButtonSystem::UpdateButtonInteraction),
             )
             .add_system_set(SystemSet::on_exit(GameState::Menu).with_system(cleanup::<Menu>));
@@ -28,13 +64,91 @@ impl Plugin for MenuPlugin {
 #[derive(Component, Reflect)]
 pub struct Menu;
 
+/// Continues the saved run, only shown when there is one.
+#[derive(Component, Reflect)]
+pub struct ContinueButton;
+
 #[derive(Component, Reflect)]
 pub struct PlayButton;
 
+/// Starts a time attack run from the first level.
+#[derive(Component, Reflect)]
+pub struct TimeAttackButton;
+
+#[derive(Component, Reflect)]
+pub struct DailyButton;
+
+/// Starts a hot-seat run from the first level.
+#[derive(Component, Reflect)]
+pub struct TwoPlayersButton;
+
+/// Starts a co-op run from the first level.
+#[derive(Component, Reflect)]
+pub struct CoOpButton;
+
+/// Starts a versus run from the first level.
+#[derive(Component, Reflect)]
+pub struct VersusButton;
+
 #[derive(Component, Reflect)]
 pub struct HelpButton;
 
-fn spawn_menu(mut commands: Commands, fonts: Res<FontAssets>) {
+#[derive(Component, Reflect)]
+pub struct ReplaysButton;
+
+#[derive(Component, Reflect)]
+pub struct ControlsButton;
+
+#[derive(Component, Reflect)]
+pub struct SettingsButton;
+
+#[derive(Component, Reflect)]
+pub struct HighScoresButton;
+
+/// The buttons below the continue button, laid out three to a row: the modes a run can be played
+/// in first, then the rest.
+fn spawn_menu_button<'w, 's, 'a>(
+    parent: &'a mut ChildBuilder<'w, 's, '_>,
+    fonts: &FontAssets,
+    label: &str,
+) -> EntityCommands<'w, 's, 'a> {
+    let mut button = parent.spawn(ArkanoidButtonBundle {
+        button_bundle: ButtonBundle {
+            style: Style {
+                size: Size::new(Val::Px(150.), Val::Px(65.)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                margin: UiRect::all(Val::Px(8.)),
+                ..default()
+            },
+            ..default()
+        },
+        ..default()
+    });
+
+    button.with_children(|parent| {
+        parent.spawn(TextBundle::from_section(
+            label,
+            TextStyle {
+                font: fonts.title_font.clone(),
+                font_size: 24.,
+                color: Color::WHITE,
+            },
+        ));
+    });
+
+    button
+}
+
+fn spawn_menu(
+    mut commands: Commands,
+    fonts: Res<FontAssets>,
+    level_assets: Res<LevelAssets>,
+    settings: Res<ArkanoidSettings>,
+) {
+    let can_continue =
+        SavedRun::load_for(&settings.level_folder, level_assets.levels.len()).is_some();
+
     commands
         .spawn(NodeBundle {
             style: Style {
@@ -63,44 +177,142 @@ fn spawn_menu(mut commands: Commands, fonts: Res<FontAssets>) {
                 }),
             );
 
-            parent
-                .spawn(ArkanoidButtonBundle::default())
-                .insert(PlayButton)
-                .with_children(|parent| {
-                    parent.spawn(TextBundle::from_section(
-                        "Play",
-                        TextStyle {
-                            font: fonts.title_font.clone(),
-                            font_size: 24.,
-                            color: Color::WHITE,
+            if can_continue {
+                parent
+                    .spawn(ArkanoidButtonBundle {
+                        button_bundle: ButtonBundle {
+                            style: Style {
+                                size: Size::new(Val::Px(150.), Val::Px(65.)),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                margin: UiRect::bottom(Val::Px(16.)),
+                                ..default()
+                            },
+                            ..default()
                         },
-                    ));
-                });
+                        ..default()
+                    })
+                    .insert(ContinueButton)
+                    .insert(Focused)
+                    .with_children(|parent| {
+                        parent.spawn(TextBundle::from_section(
+                            "Continue",
+                            TextStyle {
+                                font: fonts.title_font.clone(),
+                                font_size: 24.,
+                                color: Color::WHITE,
+                            },
+                        ));
+                    });
+            }
+
+            parent.spawn(NodeBundle::default()).with_children(|parent| {
+                let mut play_button = spawn_menu_button(parent, &fonts, "Play");
+
+                if !can_continue {
+                    play_button.insert(Focused);
+                }
+
+                play_button.insert(PlayButton);
+
+                spawn_menu_button(parent, &fonts, "Time attack").insert(TimeAttackButton);
+                spawn_menu_button(parent, &fonts, "Daily").insert(DailyButton);
+            });
+
+            parent.spawn(NodeBundle::default()).with_children(|parent| {
+                spawn_menu_button(parent, &fonts, "2 players").insert(TwoPlayersButton);
+                spawn_menu_button(parent, &fonts, "Co-op").insert(CoOpButton);
+                spawn_menu_button(parent, &fonts, "Versus").insert(VersusButton);
+            });
 
             parent
-                .spawn(ArkanoidButtonBundle {
-                    button_bundle: ButtonBundle {
-                        style: Style {
-                            size: Size::new(Val::Px(150.), Val::Px(65.)),
-                            justify_content: JustifyContent::Center,
-                            align_items: AlignItems::Center,
-                            margin: UiRect::top(Val::Px(16.)),
-                            ..default()
-                        },
+                .spawn(NodeBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(3. * (150. + 16.)), Val::Auto),
+                        flex_wrap: FlexWrap::Wrap,
+                        justify_content: JustifyContent::Center,
                         ..default()
                     },
                     ..default()
                 })
-                .insert(HelpButton)
                 .with_children(|parent| {
-                    parent.spawn(TextBundle::from_section(
-                        "Help",
-                        TextStyle {
-                            font: fonts.title_font.clone(),
-                            font_size: 24.,
-                            color: Color::WHITE,
-                        },
-                    ));
+                    spawn_menu_button(parent, &fonts, "Help").insert(HelpButton);
+                    spawn_menu_button(parent, &fonts, "Controls").insert(ControlsButton);
+                    spawn_menu_button(parent, &fonts, "Replays").insert(ReplaysButton);
+                    spawn_menu_button(parent, &fonts, "High scores").insert(HighScoresButton);
+                    spawn_menu_button(parent, &fonts, "Settings").insert(SettingsButton);
                 });
         });
 }
+
+fn continue_button(
+    button_query: Query<&ButtonInteraction, (Changed<ButtonInteraction>, With<ContinueButton>)>,
+    level_assets: Res<LevelAssets>,
+    settings: Res<ArkanoidSettings>,
+    mut continue_run: ResMut<ContinueRun>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if let Some(button_interaction) = button_query.iter().next() {
+        if !button_interaction.just_released {
+            return;
+        }
+
+        continue_run.0 = SavedRun::load_for(&settings.level_folder, level_assets.levels.len());
+
+        if continue_run.0.is_some() {
+            let _ = state.set(GameState::Playing);
+        }
+    }
+}
+
+fn time_attack_button(
+    button_query: Query<&ButtonInteraction, (Changed<ButtonInteraction>, With<TimeAttackButton>)>,
+    mut next_run_mode: ResMut<NextRunMode>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if let Some(button_interaction) = button_query.iter().next() {
+        if button_interaction.just_released {
+            next_run_mode.0 = GameMode::TimeAttack;
+            let _ = state.set(GameState::Playing);
+        }
+    }
+}
+
+fn two_players_button(
+    button_query: Query<&ButtonInteraction, (Changed<ButtonInteraction>, With<TwoPlayersButton>)>,
+    mut next_run_mode: ResMut<NextRunMode>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if let Some(button_interaction) = button_query.iter().next() {
+        if button_interaction.just_released {
+            next_run_mode.0 = GameMode::HotSeat;
+            let _ = state.set(GameState::Playing);
+        }
+    }
+}
+
+fn co_op_button(
+    button_query: Query<&ButtonInteraction, (Changed<ButtonInteraction>, With<CoOpButton>)>,
+    mut next_run_mode: ResMut<NextRunMode>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if let Some(button_interaction) = button_query.iter().next() {
+        if button_interaction.just_released {
+            next_run_mode.0 = GameMode::CoOp;
+            let _ = state.set(GameState::Playing);
+        }
+    }
+}
+
+fn versus_button(
+    button_query: Query<&ButtonInteraction, (Changed<ButtonInteraction>, With<VersusButton>)>,
+    mut next_run_mode: ResMut<NextRunMode>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if let Some(button_interaction) = button_query.iter().next() {
+        if button_interaction.just_released {
+            next_run_mode.0 = GameMode::Versus;
+            let _ = state.set(GameState::Playing);
+        }
+    }
+}
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use bevy::prelude::*;
 
 use crate::GameState;
@@ -5,10 +7,19 @@ use crate::GameState;
 use self::button::ButtonInteraction;
 
 pub mod button;
+pub mod controls;
+pub mod daily;
 pub mod game_over;
 pub mod help;
+pub mod high_scores;
 pub mod in_game;
+pub mod initials;
+pub mod level_select;
 pub mod menu;
+pub mod pause;
+pub mod replays;
+pub mod settings;
+pub mod turn_change;
 pub mod win;
 
 pub struct UiPlugin;
@@ -19,14 +30,23 @@ impl Plugin for UiPlugin {
             .add_plugin(in_game::InGameUiPlugin)
             .add_plugin(menu::MenuPlugin)
             .add_plugin(help::HelpPlugin)
+            .add_plugin(initials::InitialsPlugin)
             .add_plugin(game_over::GameOverPlugin)
-            .add_plugin(win::WinUiPlugin);
+            .add_plugin(win::WinUiPlugin)
+            .add_plugin(replays::ReplaysUiPlugin)
+            .add_plugin(controls::ControlsUiPlugin)
+            .add_plugin(pause::PauseUiPlugin)
+            .add_plugin(settings::SettingsUiPlugin)
+            .add_plugin(high_scores::HighScoresUiPlugin)
+            .add_plugin(level_select::LevelSelectUiPlugin)
+            .add_plugin(daily::DailyUiPlugin)
+            .add_plugin(turn_change::TurnChangeUiPlugin);
     }
 }
 
 pub fn set_state_button<B: Component, const STATE: GameState>(
     mut state: ResMut<State<GameState>>,
-    mut query: Query<&ButtonInteraction, (Changed<Interaction>, With<B>)>,
+    mut query: Query<&ButtonInteraction, (Changed<ButtonInteraction>, With<B>)>,
 ) {
     if let Some(button_interaction) = query.iter_mut().next() {
         if button_interaction.just_released {
@@ -34,3 +54,10 @@ pub fn set_state_button<B: Component, const STATE: GameState>(
         }
     }
 }
+
+/// Formats a time as `M:SS.S`.
+pub fn format_time(time: Duration) -> String {
+    let seconds = time.as_secs_f32();
+
+    format!("{}:{:04.1}", (seconds / 60.) as u32, seconds % 60.)
+}
@@ -0,0 +1,238 @@
+//! Pausing the game and the overlay shown while it's paused.
+
+use bevy::{prelude::*, window::WindowFocused};
+
+use crate::{
+    assets::FontAssets,
+    block::Block,
+    paddle::Paddle,
+    save::{SaveRunEvent, SaveSystem},
+    util::cleanup,
+    GameState,
+};
+
+use super::button::{ArkanoidButtonBundle, ButtonInteraction, ButtonSystem, Focused};
+
+const OVERLAY_COLOR: Color = Color::rgba(0., 0., 0., 0.6);
+
+pub struct PauseUiPlugin;
+
+impl Plugin for PauseUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<PauseUi>()
+            .add_system_set(
+                SystemSet::on_update(GameState::Playing)
+                    .with_system(pause_on_key)
+                    .with_system(pause_on_focus_loss),
+            )
+            .add_system_set(SystemSet::on_enter(GameState::Paused).with_system(spawn_ui))
+            .add_system_set(
+                SystemSet::on_update(GameState::Paused)
+                    .with_system(resume_on_key)
+                    .with_system(
+                        pause_buttons
+                            .after(ButtonSystem::UpdateButtonInteraction)
+                            .before(SaveSystem::Save),
+                    ),
+            )
+            .add_system_set(SystemSet::on_pause(GameState::Paused).with_system(hide_ui))
+            .add_system_set(SystemSet::on_resume(GameState::Paused).with_system(show_ui))
+            .add_system_set(SystemSet::on_exit(GameState::Paused).with_system(cleanup::<PauseUi>));
+    }
+}
+
+#[derive(Component, Reflect, Copy, Clone, PartialEq, Debug)]
+struct PauseUi;
+
+#[derive(Component, Copy, Clone, Eq, PartialEq, Debug, Hash)]
+enum PauseButton {
+    Resume,
+    Restart,
+    Settings,
+    Quit,
+}
+
+/// Whether Escape or the start button of a gamepad was just pressed. The press is used up, so it
+/// doesn't also resume the game right after pausing it.
+fn take_pause_press(
+    keyboard_input: &mut Input<KeyCode>,
+    gamepads: &Gamepads,
+    gamepad_button_input: &mut Input<GamepadButton>,
+) -> bool {
+    let mut pressed = keyboard_input.clear_just_pressed(KeyCode::Escape);
+
+    for gamepad in gamepads.iter() {
+        let start = GamepadButton::new(gamepad, GamepadButtonType::Start);
+
+        pressed |= gamepad_button_input.clear_just_pressed(start);
+    }
+
+    pressed
+}
+
+fn pause_on_key(
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    mut gamepad_button_input: ResMut<Input<GamepadButton>>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if take_pause_press(&mut keyboard_input, &gamepads, &mut gamepad_button_input) {
+        let _ = state.push(GameState::Paused);
+    }
+}
+
+fn pause_on_focus_loss(
+    mut focus_events: EventReader<WindowFocused>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if focus_events.iter().any(|event| !event.focused) {
+        let _ = state.push(GameState::Paused);
+    }
+}
+
+fn resume_on_key(
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    mut gamepad_button_input: ResMut<Input<GamepadButton>>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if take_pause_press(&mut keyboard_input, &gamepads, &mut gamepad_button_input) {
+        let _ = state.pop();
+    }
+}
+
+/// Restarting or quitting leaves the level without going through the win or game over screens,
+/// which are where the paddle and the blocks are usually cleaned up. Quitting saves
+/// the run first, so it can be continued from the menu.
+fn pause_buttons(
+    mut commands: Commands,
+    button_query: Query<(&ButtonInteraction, &PauseButton), Changed<ButtonInteraction>>,
+    level_query: Query<Entity, Or<(With<Paddle>, With<Block>)>>,
+    mut state: ResMut<State<GameState>>,
+    mut save_events: EventWriter<SaveRunEvent>,
+) {
+    for (button_interaction, button) in button_query.iter() {
+        if !button_interaction.just_released {
+            continue;
+        }
+
+        if *button == PauseButton::Quit {
+            save_events.send(SaveRunEvent);
+        }
+
+        if matches!(button, PauseButton::Restart | PauseButton::Quit) {
+            for entity in level_query.iter() {
+                commands.entity(entity).despawn_recursive();
+            }
+        }
+
+        let _ = match button {
+            PauseButton::Resume => state.pop(),
+            PauseButton::Restart => state.replace(GameState::Playing),
+            PauseButton::Settings => state.push(GameState::Settings),
+            PauseButton::Quit => state.replace(GameState::Menu),
+        };
+
+        return;
+    }
+}
+
+/// The settings screen opens on top of the pause menu, which mustn't keep the focus.
+fn hide_ui(
+    mut commands: Commands,
+    mut ui_query: Query<&mut Visibility, With<PauseUi>>,
+    button_query: Query<Entity, (With<PauseButton>, With<Focused>)>,
+) {
+    for mut visibility in ui_query.iter_mut() {
+        visibility.is_visible = false;
+    }
+
+    for entity in button_query.iter() {
+        commands.entity(entity).remove::<Focused>();
+    }
+}
+
+fn show_ui(
+    mut commands: Commands,
+    mut ui_query: Query<&mut Visibility, With<PauseUi>>,
+    button_query: Query<(Entity, &PauseButton)>,
+) {
+    for mut visibility in ui_query.iter_mut() {
+        visibility.is_visible = true;
+    }
+
+    for (entity, button) in button_query.iter() {
+        if *button == PauseButton::Settings {
+            commands.entity(entity).insert(Focused);
+        }
+    }
+}
+
+fn spawn_ui(mut commands: Commands, fonts: Res<FontAssets>) {
+    let text_style = TextStyle {
+        font: fonts.title_font.clone(),
+        font_size: 24.,
+        color: Color::WHITE,
+    };
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                position_type: PositionType::Absolute,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            background_color: OVERLAY_COLOR.into(),
+            ..default()
+        })
+        .insert(PauseUi)
+        .insert(Name::new("PauseUI"))
+        .with_children(|parent| {
+            parent.spawn(
+                TextBundle::from_section(
+                    "Paused",
+                    TextStyle {
+                        font: fonts.title_font.clone(),
+                        font_size: 64.,
+                        color: Color::WHITE,
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::bottom(Val::Px(32.)),
+                    ..default()
+                }),
+            );
+
+            for (button, label) in [
+                (PauseButton::Resume, "Resume"),
+                (PauseButton::Restart, "Restart level"),
+                (PauseButton::Settings, "Settings"),
+                (PauseButton::Quit, "Quit to menu"),
+            ] {
+                let mut button_commands = parent.spawn(ArkanoidButtonBundle {
+                    button_bundle: ButtonBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(200.), Val::Px(65.)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            margin: UiRect::bottom(Val::Px(16.)),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    ..default()
+                });
+
+                if button == PauseButton::Resume {
+                    button_commands.insert(Focused);
+                }
+
+                button_commands.insert(button).with_children(|parent| {
+                    parent.spawn(TextBundle::from_section(label, text_style.clone()));
+                });
+            }
+        });
+}
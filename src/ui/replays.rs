@@ -0,0 +1,361 @@
+//! The list of saved replays and the overlay of the replay viewer.
+
+use std::path::PathBuf;
+
+use bevy::prelude::*;
+
+use crate::{
+    assets::FontAssets,
+    level::CurrentLevel,
+    lives::Lives,
+    paddle::Paddle,
+    replay::{
+        saved_replays,
+        simulation::ReplaySimulation,
+        viewer::{Ghost, ReplayViewer, ViewerSystem},
+        Replay,
+    },
+    score::Score,
+    util::cleanup,
+    ArkanoidSettings, GameState,
+};
+
+use super::{
+    button::{ArkanoidButtonBundle, ButtonInteraction, ButtonSystem, Focused},
+    format_time, set_state_button,
+};
+
+/// How many of the newest replays are listed.
+const MAX_LISTED_REPLAYS: usize = 6;
+
+pub struct ReplaysUiPlugin;
+
+impl Plugin for ReplaysUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<ReplaysUi>()
+            .register_type::<GoToMenuButton>()
+            .add_system_set(SystemSet::on_enter(GameState::Replays).with_system(spawn_replay_list))
+            .add_system_set(
+                SystemSet::on_update(GameState::Replays)
+                    .with_system(set_state_button::<GoToMenuButton, { GameState::Menu }>)
+                    .after(ButtonSystem::UpdateButtonInteraction)
+                    .with_system(open_replay)
+                    .after(ButtonSystem::UpdateButtonInteraction),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Replays).with_system(cleanup::<ReplaysUi>),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::ReplayViewer).with_system(spawn_viewer_overlay),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::ReplayViewer)
+                    .with_system(update_viewer_overlay.after(ViewerSystem::Advance)),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::ReplayViewer).with_system(cleanup::<ViewerOverlay>),
+            );
+    }
+}
+
+#[derive(Component, Reflect)]
+struct ReplaysUi;
+
+#[derive(Component, Reflect)]
+struct GoToMenuButton;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+enum ReplayAction {
+    /// Open the replay in the viewer.
+    Watch,
+    /// Play the level of the replay with the replay as a ghost.
+    Race,
+}
+
+#[derive(Component, Clone, PartialEq, Debug)]
+struct ReplayButton {
+    path: PathBuf,
+    action: ReplayAction,
+}
+
+#[derive(Component, Copy, Clone, PartialEq, Debug)]
+struct ViewerOverlay;
+
+#[derive(Component, Copy, Clone, PartialEq, Debug)]
+struct ViewerStatusText;
+
+fn spawn_replay_list(mut commands: Commands, fonts: Res<FontAssets>) {
+    let text_style = TextStyle {
+        font: fonts.title_font.clone(),
+        font_size: 24.,
+        color: Color::WHITE,
+    };
+
+    let replays = saved_replays()
+        .into_iter()
+        .filter_map(|path| match Replay::load(&path) {
+            Ok(replay) => Some((path, replay)),
+            Err(error) => {
+                warn!("Skipping the replay {}: {error:#}", path.display());
+                None
+            }
+        })
+        .take(MAX_LISTED_REPLAYS)
+        .collect::<Vec<_>>();
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            ..default()
+        })
+        .insert(ReplaysUi)
+        .insert(Name::new("ReplaysUI"))
+        .with_children(|parent| {
+            parent.spawn(
+                TextBundle::from_section(
+                    "Replays",
+                    TextStyle {
+                        font: fonts.title_font.clone(),
+                        font_size: 64.,
+                        color: Color::WHITE,
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::bottom(Val::Px(32.)),
+                    ..default()
+                }),
+            );
+
+            if replays.is_empty() {
+                parent.spawn(
+                    TextBundle::from_section(
+                        "Play a level to record your first replay.",
+                        text_style.clone(),
+                    )
+                    .with_style(Style {
+                        margin: UiRect::bottom(Val::Px(32.)),
+                        ..default()
+                    }),
+                );
+            }
+
+            let has_replays = !replays.is_empty();
+
+            for (index, (path, replay)) in replays.into_iter().enumerate() {
+                let duration = replay.frames.iter().map(|frame| frame.delta).sum();
+
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            align_items: AlignItems::Center,
+                            margin: UiRect::bottom(Val::Px(8.)),
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn(
+                            TextBundle::from_section(
+                                format!("Level {}  {}", replay.level + 1, format_time(duration)),
+                                text_style.clone(),
+                            )
+                            .with_style(Style {
+                                size: Size::new(Val::Px(200.), Val::Auto),
+                                ..default()
+                            }),
+                        );
+
+                        for (action, label) in
+                            [(ReplayAction::Watch, "Watch"), (ReplayAction::Race, "Race")]
+                        {
+                            let mut button = parent.spawn(ArkanoidButtonBundle {
+                                button_bundle: ButtonBundle {
+                                    style: Style {
+                                        size: Size::new(Val::Px(110.), Val::Px(40.)),
+                                        justify_content: JustifyContent::Center,
+                                        align_items: AlignItems::Center,
+                                        margin: UiRect::left(Val::Px(16.)),
+                                        ..default()
+                                    },
+                                    ..default()
+                                },
+                                ..default()
+                            });
+
+                            button
+                                .insert(ReplayButton {
+                                    path: path.clone(),
+                                    action,
+                                })
+                                .with_children(|parent| {
+                                    parent
+                                        .spawn(TextBundle::from_section(label, text_style.clone()));
+                                });
+
+                            // The newest replay is the one most likely to be watched.
+                            if index == 0 && action == ReplayAction::Watch {
+                                button.insert(Focused);
+                            }
+                        }
+                    });
+            }
+
+            let mut go_to_menu_button = parent.spawn(ArkanoidButtonBundle {
+                button_bundle: ButtonBundle {
+                    style: Style {
+                        size: Size::new(Val::Px(150.), Val::Px(65.)),
+                        justify_content: JustifyContent::Center,
+                        align_items: AlignItems::Center,
+                        margin: UiRect::top(Val::Px(24.)),
+                        ..default()
+                    },
+                    ..default()
+                },
+                ..default()
+            });
+
+            if !has_replays {
+                go_to_menu_button.insert(Focused);
+            }
+
+            go_to_menu_button
+                .insert(GoToMenuButton)
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section("Go to menu", text_style.clone()));
+                });
+        });
+}
+
+fn open_replay(
+    button_query: Query<(&ButtonInteraction, &ReplayButton), Changed<ButtonInteraction>>,
+    settings: Res<ArkanoidSettings>,
+    mut viewer: NonSendMut<ReplayViewer>,
+    mut ghost: NonSendMut<Ghost>,
+    mut current_level: ResMut<CurrentLevel>,
+    mut state: ResMut<State<GameState>>,
+) {
+    for (button_interaction, button) in button_query.iter() {
+        if !button_interaction.just_released {
+            continue;
+        }
+
+        let simulation =
+            Replay::load(&button.path).map(|replay| ReplaySimulation::new(replay, &settings));
+
+        let simulation = match simulation {
+            Ok(simulation) => simulation,
+            Err(error) => {
+                error!(
+                    "Couldn't open the replay {}: {error:#}",
+                    button.path.display()
+                );
+                continue;
+            }
+        };
+
+        match button.action {
+            ReplayAction::Watch => {
+                viewer.open(simulation);
+                let _ = state.set(GameState::ReplayViewer);
+            }
+            ReplayAction::Race => {
+                // The level is played on the levels of the game, the ghost on its own.
+                if simulation.replay().level_folder(&settings) != settings.level_folder {
+                    error!(
+                        "Couldn't race the replay {}, it was recorded on the levels in {}",
+                        button.path.display(),
+                        simulation.replay().level_folder(&settings)
+                    );
+                    continue;
+                }
+
+                current_level.0 = simulation.replay().level;
+                *ghost = Ghost::new(simulation);
+                let _ = state.set(GameState::Playing);
+            }
+        }
+
+        return;
+    }
+}
+
+fn spawn_viewer_overlay(mut commands: Commands, fonts: Res<FontAssets>) {
+    let text_style = TextStyle {
+        font: fonts.title_font.clone(),
+        font_size: 24.,
+        color: Color::rgb(0.9, 0.9, 0.9),
+    };
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                justify_content: JustifyContent::SpaceBetween,
+                flex_direction: FlexDirection::Column,
+                padding: UiRect::all(Val::Px(20.)),
+                ..default()
+            },
+            ..default()
+        })
+        .insert(ViewerOverlay)
+        .insert(Name::new("ViewerOverlay"))
+        .with_children(|parent| {
+            parent
+                .spawn(TextBundle::from_section("", text_style.clone()))
+                .insert(ViewerStatusText);
+
+            parent.spawn(TextBundle::from_section(
+                "Space: pause   Up/Down: speed   Left/Right: seek   Comma/Period: step   Home: restart   Esc: back",
+                TextStyle {
+                    font_size: 16.,
+                    ..text_style
+                },
+            ));
+        });
+}
+
+fn update_viewer_overlay(
+    mut viewer: NonSendMut<ReplayViewer>,
+    mut text_query: Query<&mut Text, With<ViewerStatusText>>,
+) {
+    let (speed, paused) = (viewer.speed, viewer.paused);
+
+    let simulation = match &mut viewer.simulation {
+        Some(simulation) => simulation,
+        None => return,
+    };
+
+    let world = simulation.world();
+    let paddle = world
+        .query_filtered::<(&Score, &Lives), With<Paddle>>()
+        .iter(world)
+        .next()
+        .map(|(score, lives)| (score.0, lives.lives()));
+
+    let mut status = format!(
+        "Level {}   {} / {}   {speed}x",
+        simulation.replay().level + 1,
+        format_time(simulation.elapsed()),
+        format_time(simulation.duration()),
+    );
+
+    if !simulation.is_loaded() {
+        status += "   Loading";
+    } else if paused {
+        status += "   Paused";
+    }
+
+    if let Some((score, lives)) = paddle {
+        status += &format!("\nScore {score}   Lives {lives}");
+    }
+
+    for mut text in text_query.iter_mut() {
+        text.sections[0].value = status.clone();
+    }
+}
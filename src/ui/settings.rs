@@ -0,0 +1,357 @@
+//! The screen the [`Settings`] are changed on.
+
+use std::fmt;
+
+use bevy::prelude::*;
+
+use crate::{
+    assets::FontAssets,
+    block::Palette,
+    difficulty::Difficulty,
+    settings::{DisplayMode, Scaling, Settings},
+    util::cleanup,
+    GameState,
+};
+
+use super::button::{ArkanoidButtonBundle, ButtonInteraction, ButtonSystem, Focused};
+
+/// Covers the pause menu when the settings are opened from it.
+const BACKGROUND_COLOR: Color = Color::rgba(0., 0., 0., 0.85);
+
+pub struct SettingsUiPlugin;
+
+impl Plugin for SettingsUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<SettingsUi>()
+            .register_type::<BackButton>()
+            .add_system_set(SystemSet::on_enter(GameState::Settings).with_system(spawn_ui))
+            .add_system_set(
+                SystemSet::on_update(GameState::Settings)
+                    .with_system(back_on_key)
+                    .with_system(setting_buttons.after(ButtonSystem::UpdateButtonInteraction))
+                    .with_system(back_button.after(ButtonSystem::UpdateButtonInteraction))
+                    .with_system(update_ui.after(setting_buttons)),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Settings).with_system(cleanup::<SettingsUi>),
+            );
+    }
+}
+
+#[derive(Component, Reflect)]
+struct SettingsUi;
+
+#[derive(Component, Reflect)]
+struct BackButton;
+
+#[derive(Copy, Clone, Eq, PartialEq, Debug, Hash)]
+enum Setting {
+    MasterVolume,
+    SfxVolume,
+    MouseSensitivity,
+    PaddleSpeed,
+    DisplayMode,
+    Scaling,
+    Palette,
+    Difficulty,
+}
+
+impl Setting {
+    const ALL: [Setting; 8] = [
+        Setting::MasterVolume,
+        Setting::SfxVolume,
+        Setting::MouseSensitivity,
+        Setting::PaddleSpeed,
+        Setting::DisplayMode,
+        Setting::Scaling,
+        Setting::Palette,
+        Setting::Difficulty,
+    ];
+
+    /// Moves the setting `steps` steps up or down. Numbers stop at their limits, choices wrap
+    /// around.
+    fn adjust(self, settings: &mut Settings, steps: i32) {
+        match self {
+            Setting::MasterVolume => step(&mut settings.audio.master, 0.1, steps, 0., 1.),
+            Setting::SfxVolume => step(&mut settings.audio.sfx, 0.1, steps, 0., 1.),
+            Setting::MouseSensitivity => step(
+                &mut settings.controls.mouse_sensitivity,
+                0.1,
+                steps,
+                0.5,
+                2.,
+            ),
+            Setting::PaddleSpeed => {
+                step(&mut settings.controls.paddle_speed, 50., steps, 200., 1000.)
+            }
+            Setting::DisplayMode => cycle(&mut settings.display.mode, &DisplayMode::ALL, steps),
+            Setting::Scaling => cycle(&mut settings.display.scaling, &Scaling::ALL, steps),
+            Setting::Palette => cycle(&mut settings.palette, &Palette::ALL, steps),
+            Setting::Difficulty => cycle(&mut settings.difficulty, &Difficulty::ALL, steps),
+        }
+    }
+
+    fn value(self, settings: &Settings) -> String {
+        let percent = |volume: f32| format!("{:.0}%", volume * 100.);
+
+        match self {
+            Setting::MasterVolume => percent(settings.audio.master),
+            Setting::SfxVolume => percent(settings.audio.sfx),
+            Setting::MouseSensitivity => format!("{:.1}x", settings.controls.mouse_sensitivity),
+            Setting::PaddleSpeed => format!("{:.0}", settings.controls.paddle_speed),
+            Setting::DisplayMode => settings.display.mode.to_string(),
+            Setting::Scaling => settings.display.scaling.to_string(),
+            Setting::Palette => settings.palette.to_string(),
+            Setting::Difficulty => settings.difficulty.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Setting {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Setting::MasterVolume => write!(f, "Master volume"),
+            Setting::SfxVolume => write!(f, "Sound effects"),
+            Setting::MouseSensitivity => write!(f, "Mouse sensitivity"),
+            Setting::PaddleSpeed => write!(f, "Paddle speed"),
+            Setting::DisplayMode => write!(f, "Window mode"),
+            Setting::Scaling => write!(f, "Scaling"),
+            Setting::Palette => write!(f, "Block colors"),
+            Setting::Difficulty => write!(f, "Difficulty"),
+        }
+    }
+}
+
+/// Snaps the value to a multiple of `size`, so repeated steps don't pile up rounding errors.
+fn step(value: &mut f32, size: f32, steps: i32, min: f32, max: f32) {
+    *value = (((*value / size).round() + steps as f32) * size).clamp(min, max);
+}
+
+pub(super) fn cycle<T: Copy + PartialEq>(value: &mut T, all: &[T], steps: i32) {
+    let current = all.iter().position(|option| option == value).unwrap_or(0) as i32;
+
+    *value = all[(current + steps).rem_euclid(all.len() as i32) as usize];
+}
+
+/// Moves a setting down (-1) or up (1).
+#[derive(Component, Copy, Clone, Eq, PartialEq, Debug, Hash)]
+struct SettingButton {
+    setting: Setting,
+    steps: i32,
+}
+
+/// Shows the value of a setting.
+#[derive(Component, Copy, Clone, Eq, PartialEq, Debug, Hash)]
+struct SettingText(Setting);
+
+fn spawn_ui(mut commands: Commands, fonts: Res<FontAssets>) {
+    let text_style = TextStyle {
+        font: fonts.title_font.clone(),
+        font_size: 24.,
+        color: Color::WHITE,
+    };
+
+    let small_button = || ArkanoidButtonBundle {
+        button_bundle: ButtonBundle {
+            style: Style {
+                size: Size::new(Val::Px(32.), Val::Px(32.)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            ..default()
+        },
+        ..default()
+    };
+
+    commands
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                position_type: PositionType::Absolute,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            background_color: BACKGROUND_COLOR.into(),
+            ..default()
+        })
+        .insert(SettingsUi)
+        .insert(Name::new("SettingsUI"))
+        .with_children(|parent| {
+            parent.spawn(
+                TextBundle::from_section(
+                    "Settings",
+                    TextStyle {
+                        font: fonts.title_font.clone(),
+                        font_size: 64.,
+                        color: Color::WHITE,
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::bottom(Val::Px(16.)),
+                    ..default()
+                }),
+            );
+
+            for setting in Setting::ALL {
+                parent
+                    .spawn(NodeBundle {
+                        style: Style {
+                            align_items: AlignItems::Center,
+                            margin: UiRect::bottom(Val::Px(4.)),
+                            ..default()
+                        },
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        parent.spawn(
+                            TextBundle::from_section(setting.to_string(), text_style.clone())
+                                .with_style(Style {
+                                    size: Size::new(Val::Px(260.), Val::Auto),
+                                    ..default()
+                                }),
+                        );
+
+                        parent
+                            .spawn(small_button())
+                            .insert(SettingButton { setting, steps: -1 })
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle::from_section("<", text_style.clone()));
+                            });
+
+                        parent
+                            .spawn(NodeBundle {
+                                style: Style {
+                                    size: Size::new(Val::Px(180.), Val::Auto),
+                                    justify_content: JustifyContent::Center,
+                                    ..default()
+                                },
+                                ..default()
+                            })
+                            .with_children(|parent| {
+                                parent
+                                    .spawn(TextBundle::from_section("", text_style.clone()))
+                                    .insert(SettingText(setting));
+                            });
+
+                        parent
+                            .spawn(small_button())
+                            .insert(SettingButton { setting, steps: 1 })
+                            .with_children(|parent| {
+                                parent.spawn(TextBundle::from_section(">", text_style.clone()));
+                            });
+                    });
+            }
+
+            parent
+                .spawn(ArkanoidButtonBundle {
+                    button_bundle: ButtonBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(150.), Val::Px(65.)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            margin: UiRect::top(Val::Px(16.)),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    ..default()
+                })
+                .insert(BackButton)
+                .insert(Focused)
+                .with_children(|parent| {
+                    parent.spawn(TextBundle::from_section("Back", text_style.clone()));
+                });
+        });
+}
+
+/// Goes back to the pause menu when the settings were opened from it, or to the main menu.
+fn leave(state: &mut State<GameState>) {
+    let _ = if state.inactives().is_empty() {
+        state.set(GameState::Menu)
+    } else {
+        state.pop()
+    };
+}
+
+/// Escape or the east button of a gamepad go back. The press is used up, so it doesn't also
+/// resume the game from the pause menu.
+fn back_on_key(
+    mut keyboard_input: ResMut<Input<KeyCode>>,
+    gamepads: Res<Gamepads>,
+    mut gamepad_button_input: ResMut<Input<GamepadButton>>,
+    mut state: ResMut<State<GameState>>,
+) {
+    let mut pressed = keyboard_input.clear_just_pressed(KeyCode::Escape);
+
+    for gamepad in gamepads.iter() {
+        pressed |= gamepad_button_input
+            .clear_just_pressed(GamepadButton::new(gamepad, GamepadButtonType::East));
+    }
+
+    if pressed {
+        leave(&mut state);
+    }
+}
+
+fn back_button(
+    button_query: Query<&ButtonInteraction, (Changed<ButtonInteraction>, With<BackButton>)>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if let Some(button_interaction) = button_query.iter().next() {
+        if button_interaction.just_released {
+            leave(&mut state);
+        }
+    }
+}
+
+fn setting_buttons(
+    button_query: Query<(&ButtonInteraction, &SettingButton), Changed<ButtonInteraction>>,
+    mut settings: ResMut<Settings>,
+) {
+    for (button_interaction, button) in button_query.iter() {
+        if button_interaction.just_released {
+            button.setting.adjust(&mut settings, button.steps);
+        }
+    }
+}
+
+fn update_ui(
+    settings: Res<Settings>,
+    added_query: Query<(), Added<SettingText>>,
+    mut text_query: Query<(&mut Text, &SettingText)>,
+) {
+    if !settings.is_changed() && added_query.is_empty() {
+        return;
+    }
+
+    for (mut text, setting_text) in text_query.iter_mut() {
+        text.sections[0].value = setting_text.0.value(&settings);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adjusting_stops_numbers_and_wraps_choices() {
+        let mut settings = Settings::default();
+
+        Setting::MasterVolume.adjust(&mut settings, 1);
+        assert_eq!(settings.audio.master, 1.);
+
+        for _ in 0..3 {
+            Setting::MasterVolume.adjust(&mut settings, -1);
+        }
+        assert_eq!(Setting::MasterVolume.value(&settings), "70%");
+
+        Setting::DisplayMode.adjust(&mut settings, -1);
+        assert_eq!(settings.display.mode, DisplayMode::Fullscreen);
+
+        Setting::DisplayMode.adjust(&mut settings, 1);
+        assert_eq!(settings.display.mode, DisplayMode::Windowed);
+    }
+}
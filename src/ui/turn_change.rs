@@ -0,0 +1,145 @@
+//! The banner between two turns of a hot-seat run, so the controls can change hands.
+
+use bevy::prelude::*;
+
+use crate::{
+    assets::{FontAssets, LevelAssets},
+    hot_seat::{HotSeat, HotSeatSystem},
+    level::{CurrentLevel, LevelAsset},
+    run::Run,
+    util::cleanup,
+    GameState,
+};
+
+use super::{
+    button::{ArkanoidButtonBundle, ButtonSystem, Focused},
+    set_state_button,
+};
+
+const DETAIL_COLOR: Color = Color::rgb(0.6, 0.6, 0.6);
+
+pub struct TurnChangeUiPlugin;
+
+impl Plugin for TurnChangeUiPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<TurnChangeUi>()
+            .register_type::<GoToMenuButton>()
+            .register_type::<ReadyButton>()
+            .add_system_set(
+                SystemSet::on_enter(GameState::TurnChange)
+                    .with_system(spawn_ui.after(HotSeatSystem::EndTurn)),
+            )
+            .add_system_set(
+                SystemSet::on_update(GameState::TurnChange)
+                    .with_system(set_state_button::<GoToMenuButton, { GameState::Menu }>)
+                    .after(ButtonSystem::UpdateButtonInteraction)
+                    .with_system(set_state_button::<ReadyButton, { GameState::Playing }>)
+                    .after(ButtonSystem::UpdateButtonInteraction),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::TurnChange).with_system(cleanup::<TurnChangeUi>),
+            );
+    }
+}
+
+#[derive(Component, Reflect)]
+struct TurnChangeUi;
+
+#[derive(Component, Reflect)]
+struct GoToMenuButton;
+
+#[derive(Component, Reflect)]
+struct ReadyButton;
+
+/// Runs once the turn was passed, so it shows the player who plays next.
+fn spawn_ui(
+    mut commands: Commands,
+    fonts: Res<FontAssets>,
+    hot_seat: Res<HotSeat>,
+    run: Res<Run>,
+    current_level: Res<CurrentLevel>,
+    level_assets: Res<LevelAssets>,
+    levels: Res<Assets<LevelAsset>>,
+) {
+    let text_style = TextStyle {
+        font: fonts.title_font.clone(),
+        font_size: 24.,
+        color: Color::WHITE,
+    };
+
+    let level_name = level_assets
+        .levels
+        .get(current_level.0)
+        .and_then(|handle| levels.get(handle))
+        .map_or_else(
+            || format!("Level {}", current_level.0 + 1),
+            |asset| asset.display_name(current_level.0),
+        );
+
+    commands
+        .spawn(NodeBundle {
+            transform: Transform::from_translation(Vec3::new(0., 0., 1.)),
+            style: Style {
+                size: Size::new(Val::Percent(100.), Val::Percent(100.)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                flex_direction: FlexDirection::Column,
+                ..default()
+            },
+            ..default()
+        })
+        .insert(TurnChangeUi)
+        .insert(Name::new("TurnChangeUI"))
+        .with_children(|parent| {
+            parent.spawn(
+                TextBundle::from_section(
+                    format!("Player {} ready", hot_seat.current + 1),
+                    TextStyle {
+                        font: fonts.title_font.clone(),
+                        font_size: 64.,
+                        color: Color::WHITE,
+                    },
+                )
+                .with_style(Style {
+                    margin: UiRect::bottom(Val::Px(16.)),
+                    ..default()
+                }),
+            );
+
+            parent.spawn(TextBundle::from_section(
+                format!("{level_name}   Score {}   Lives {}", run.score, run.lives),
+                TextStyle {
+                    color: DETAIL_COLOR,
+                    ..text_style.clone()
+                },
+            ));
+
+            parent
+                .spawn(NodeBundle {
+                    style: Style {
+                        margin: UiRect::top(Val::Px(64.)),
+                        justify_content: JustifyContent::SpaceBetween,
+                        size: Size::new(Val::Px(256. + 64.), Val::Auto),
+                        ..default()
+                    },
+                    ..default()
+                })
+                .with_children(|parent| {
+                    parent
+                        .spawn(ArkanoidButtonBundle::default())
+                        .insert(GoToMenuButton)
+                        .with_children(|parent| {
+                            parent
+                                .spawn(TextBundle::from_section("Go to menu", text_style.clone()));
+                        });
+
+                    parent
+                        .spawn(ArkanoidButtonBundle::default())
+                        .insert(ReadyButton)
+                        .insert(Focused)
+                        .with_children(|parent| {
+                            parent.spawn(TextBundle::from_section("Start", text_style.clone()));
+                        });
+                });
+        });
+}
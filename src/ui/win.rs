@@ -2,16 +2,27 @@ use bevy::prelude::*;
 
 use crate::{
     assets::{FontAssets, LevelAssets},
+    high_score::{HighScoreSystem, PendingHighScore},
     level::CurrentLevel,
+    progress::{LevelResult, ProgressSystem},
+    run::{GameMode, Run},
+    time_attack::{SplitResult, TimeAttackSystem},
     util::cleanup,
     GameState,
 };
 
 use super::{
-    button::{ArkanoidButtonBundle, ButtonInteraction, ButtonSystem},
-    set_state_button,
+    button::{ArkanoidButtonBundle, ButtonInteraction, ButtonSystem, Focused},
+    format_time,
+    initials::{enter_initials, spawn_name_entry, InitialsEnteredEvent},
+    level_select::star_sections,
 };
 
+const STAT_NAME_COLOR: Color = Color::rgb(0.6, 0.6, 0.6);
+const NEW_BEST_COLOR: Color = Color::rgb(1., 0.8, 0.2);
+const AHEAD_COLOR: Color = Color::rgb(0.3, 0.9, 0.4);
+const BEHIND_COLOR: Color = Color::rgb(0.9, 0.3, 0.3);
+
 pub struct WinUiPlugin;
 
 impl Plugin for WinUiPlugin {
@@ -19,13 +30,22 @@ impl Plugin for WinUiPlugin {
         app.register_type::<WinUi>()
             .register_type::<GoToMenuButton>()
             .register_type::<NextLevelButton>()
-            .add_system_set(SystemSet::on_enter(GameState::Win).with_system(spawn_ui))
+            .add_system_set(
+                SystemSet::on_enter(GameState::Win).with_system(
+                    spawn_ui
+                        .after(ProgressSystem::Rate)
+                        .after(TimeAttackSystem::Split)
+                        .after(HighScoreSystem::Check),
+                ),
+            )
             .add_system_set(
                 SystemSet::on_update(GameState::Win)
-                    .with_system(set_state_button::<GoToMenuButton, { GameState::Menu }>)
+                    .with_system(go_to_menu)
                     .after(ButtonSystem::UpdateButtonInteraction)
                     .with_system(go_to_next_level)
-                    .after(ButtonSystem::UpdateButtonInteraction),
+                    .after(ButtonSystem::UpdateButtonInteraction)
+                    .with_system(enter_initials)
+                    .with_system(leave_after_initials.after(enter_initials)),
             )
             .add_system_set(SystemSet::on_exit(GameState::Win).with_system(cleanup::<WinUi>));
     }
@@ -40,12 +60,54 @@ struct GoToMenuButton;
 #[derive(Component, Reflect)]
 struct NextLevelButton;
 
+/// Holds the buttons, which make way for the initials entry when the player goes to the menu
+/// with a score that made it onto the high score table.
+#[derive(Component)]
+struct ButtonRow;
+
+/// A line of the breakdown, with the name on the left and the value on the right.
+fn spawn_stat(parent: &mut ChildBuilder, name: &str, value: String, text_style: &TextStyle) {
+    parent
+        .spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Px(320.), Val::Auto),
+                justify_content: JustifyContent::SpaceBetween,
+                margin: UiRect::bottom(Val::Px(4.)),
+                ..default()
+            },
+            ..default()
+        })
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                name,
+                TextStyle {
+                    color: STAT_NAME_COLOR,
+                    ..text_style.clone()
+                },
+            ));
+            parent.spawn(TextBundle::from_section(value, text_style.clone()));
+        });
+}
+
 fn spawn_ui(
     mut commands: Commands,
     fonts: Res<FontAssets>,
     level_assets: Res<LevelAssets>,
     current_level: Res<CurrentLevel>,
+    level_result: Res<LevelResult>,
+    split_result: Res<SplitResult>,
+    pending: Res<PendingHighScore>,
+    run: Res<Run>,
 ) {
+    let has_next_level =
+        run.mode.uses_level_pack() && level_assets.levels.len() > current_level.0 + 1;
+
+    let stat_style = TextStyle {
+        font: fonts.title_font.clone(),
+        font_size: 20.,
+        color: Color::WHITE,
+    };
+
     commands
         .spawn(NodeBundle {
             transform: Transform::from_translation(Vec3::new(0., 0., 1.)),
@@ -72,17 +134,131 @@ fn spawn_ui(
                 ))
                 .insert(Name::new("GameOverUITitle"));
 
+            parent.spawn(
+                TextBundle::from_sections(star_sections(
+                    level_result.stars,
+                    &TextStyle {
+                        font_size: 48.,
+                        ..stat_style.clone()
+                    },
+                ))
+                .with_style(Style {
+                    margin: UiRect::vertical(Val::Px(8.)),
+                    ..default()
+                }),
+            );
+
+            let stats = &level_result.stats;
+
+            spawn_stat(parent, "Score", level_result.score.to_string(), &stat_style);
+            spawn_stat(
+                parent,
+                "Blocks broken",
+                stats.blocks_broken.to_string(),
+                &stat_style,
+            );
+            spawn_stat(
+                parent,
+                "Best combo",
+                stats.best_combo.to_string(),
+                &stat_style,
+            );
+            spawn_stat(parent, "Time", format_time(stats.time), &stat_style);
+            spawn_stat(
+                parent,
+                "Lives lost",
+                stats.lives_lost.to_string(),
+                &stat_style,
+            );
+            spawn_stat(
+                parent,
+                "Power-ups collected",
+                stats.power_ups_collected.to_string(),
+                &stat_style,
+            );
+
+            if run.mode == GameMode::TimeAttack {
+                spawn_stat(
+                    parent,
+                    "Split",
+                    format_time(split_result.split),
+                    &stat_style,
+                );
+
+                if let Some(best) = split_result.best {
+                    let (difference, color) = if split_result.split <= best {
+                        (
+                            format!("-{}", format_time(best - split_result.split)),
+                            AHEAD_COLOR,
+                        )
+                    } else {
+                        (
+                            format!("+{}", format_time(split_result.split - best)),
+                            BEHIND_COLOR,
+                        )
+                    };
+
+                    parent.spawn(TextBundle::from_section(
+                        format!("{difference} against the best run"),
+                        TextStyle {
+                            color,
+                            ..stat_style.clone()
+                        },
+                    ));
+                }
+            }
+
+            if level_result.new_best {
+                parent.spawn(TextBundle::from_section(
+                    "New best!",
+                    TextStyle {
+                        color: NEW_BEST_COLOR,
+                        ..stat_style.clone()
+                    },
+                ));
+            }
+
+            if let (GameMode::TimeAttack, Some(place)) = (run.mode, split_result.place) {
+                let text = if place == 0 {
+                    "New record time!".to_string()
+                } else {
+                    format!("#{} on the times table", place + 1)
+                };
+
+                parent.spawn(TextBundle::from_section(
+                    text,
+                    TextStyle {
+                        color: NEW_BEST_COLOR,
+                        ..stat_style.clone()
+                    },
+                ));
+            }
+
+            // The last level ends the run, so the initials are asked for right away.
+            if !has_next_level {
+                if let Some(high_score) = &pending.0 {
+                    spawn_name_entry(parent, high_score, &name_entry_style(&fonts));
+                    return;
+                }
+            }
+
             parent
                 .spawn(NodeBundle {
                     style: Style {
-                        margin: UiRect::top(Val::Px(64.)),
+                        margin: UiRect::top(Val::Px(24.)),
                         ..default()
                     },
                     ..default()
                 })
+                .insert(ButtonRow)
                 .with_children(|parent| {
-                    parent
-                        .spawn(ArkanoidButtonBundle::default())
+                    let mut go_to_menu_button = parent.spawn(ArkanoidButtonBundle::default());
+
+                    if !has_next_level {
+                        go_to_menu_button.insert(Focused);
+                    }
+
+                    go_to_menu_button
                         .insert(GoToMenuButton)
                         .with_children(|parent| {
                             parent.spawn(TextBundle::from_section(
@@ -95,7 +271,7 @@ fn spawn_ui(
                             ));
                         });
 
-                    if level_assets.levels.len() > current_level.0 + 1 {
+                    if has_next_level {
                         parent
                             .spawn(ArkanoidButtonBundle {
                                 button_bundle: ButtonBundle {
@@ -111,6 +287,7 @@ fn spawn_ui(
                                 ..default()
                             })
                             .insert(NextLevelButton)
+                            .insert(Focused)
                             .with_children(|parent| {
                                 parent.spawn(TextBundle::from_section(
                                     "Next Level",
@@ -126,6 +303,60 @@ fn spawn_ui(
         });
 }
 
+fn name_entry_style(fonts: &FontAssets) -> TextStyle {
+    TextStyle {
+        font: fonts.title_font.clone(),
+        font_size: 24.,
+        color: Color::WHITE,
+    }
+}
+
+/// Leaving for the menu ends the run, so a score that made it onto the table asks for the
+/// initials first.
+fn go_to_menu(
+    mut commands: Commands,
+    fonts: Res<FontAssets>,
+    pending: Res<PendingHighScore>,
+    go_to_menu_query: Query<&ButtonInteraction, With<GoToMenuButton>>,
+    button_row_query: Query<Entity, With<ButtonRow>>,
+    win_ui_query: Query<Entity, With<WinUi>>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if !go_to_menu_query
+        .iter()
+        .any(|button_interaction| button_interaction.just_released)
+    {
+        return;
+    }
+
+    let high_score = match &pending.0 {
+        Some(high_score) => high_score,
+        None => {
+            let _ = state.set(GameState::Menu);
+            return;
+        }
+    };
+
+    for entity in button_row_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    for entity in win_ui_query.iter() {
+        commands.entity(entity).with_children(|parent| {
+            spawn_name_entry(parent, high_score, &name_entry_style(&fonts))
+        });
+    }
+}
+
+fn leave_after_initials(
+    mut entered_events: EventReader<InitialsEnteredEvent>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if entered_events.iter().count() > 0 {
+        let _ = state.set(GameState::Menu);
+    }
+}
+
 fn go_to_next_level(
     next_level_query: Query<&ButtonInteraction, With<NextLevelButton>>,
     mut current_level: ResMut<CurrentLevel>,
@@ -1,9 +1,9 @@
 use bevy::prelude::*;
-use bevy_kira_audio::{Audio, AudioControl};
 
 use crate::{
-    assets::AudioAssets,
+    audio::{Sound, SoundEvent},
     block::{Block, BlockType},
+    run::{GameMode, Run},
     GameState,
 };
 
@@ -15,11 +15,12 @@ impl Plugin for WinPlugin {
     }
 }
 
+/// A versus run ends once the blocks are cleared, and the best score wins.
 fn check_for_win(
+    run: Res<Run>,
     block_query: Query<&Block>,
     mut state: ResMut<State<GameState>>,
-    audio: Res<Audio>,
-    audio_assets: Res<AudioAssets>,
+    mut sound_events: EventWriter<SoundEvent>,
 ) {
     let non_gold_blocks_num = block_query
         .iter()
@@ -27,8 +28,12 @@ fn check_for_win(
         .count();
 
     if non_gold_blocks_num == 0 {
-        let _ = state.set(GameState::Win);
+        let _ = state.set(if run.mode == GameMode::Versus {
+            GameState::GameOver
+        } else {
+            GameState::Win
+        });
 
-        audio.play(audio_assets.win.clone());
+        sound_events.send(SoundEvent(Sound::Win));
     }
 }